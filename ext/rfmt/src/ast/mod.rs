@@ -161,6 +161,7 @@ pub enum NodeType {
     AliasGlobalVariableNode,
     UndefNode,
     AssocSplatNode,
+    ArgumentsNode,
     BlockArgumentNode,
     MultiWriteNode,
     MultiTargetNode,
@@ -341,6 +342,7 @@ impl std::str::FromStr for NodeType {
             "alias_global_variable_node" => Self::AliasGlobalVariableNode,
             "undef_node" => Self::UndefNode,
             "assoc_splat_node" => Self::AssocSplatNode,
+            "arguments_node" => Self::ArgumentsNode,
             "block_argument_node" => Self::BlockArgumentNode,
             "multi_write_node" => Self::MultiWriteNode,
             "multi_target_node" => Self::MultiTargetNode,
@@ -0,0 +1,49 @@
+//! Native `rfmt-lsp` language server.
+//!
+//! A Rust counterpart of the gem's `exe/rfmt-lsp` that speaks LSP over
+//! stdin/stdout; see [`rfmt::lsp`] for what it supports. Logs and panics
+//! go to stderr, which editors show as the server's output.
+
+use std::io::{self, BufReader};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: rfmt-lsp [--stdio]
+
+Serves the Language Server Protocol over stdin and stdout.
+
+Options:
+      --stdio          Accepted for editors that pass it; stdio is the only transport
+  -v, --version        Print the version
+  -h, --help           Print this help
+";
+
+fn main() -> ExitCode {
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--stdio" => {}
+            "-v" | "--version" => {
+                println!("rfmt-lsp {} (Rust)", env!("CARGO_PKG_VERSION"));
+                return ExitCode::SUCCESS;
+            }
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ => {
+                eprintln!("Unknown option: {}\n\n{}", arg, USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let input = BufReader::new(io::stdin().lock());
+    match rfmt::lsp::run(input, io::stdout().lock()) {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("rfmt-lsp: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,769 @@
+//! Native `rfmt` command line.
+//!
+//! A Rust counterpart of the gem's Thor CLI (`lib/rfmt/cli.rb`) for hooks
+//! and CI: no Ruby startup, and files are formatted on all cores.
+//!
+//! ```text
+//! rfmt [OPTIONS] [PATH...]
+//! ```
+//!
+//! Directories are walked for files matching the configuration's `include`
+//! patterns and not its `exclude` patterns; with no paths the current
+//! directory is walked. Files named explicitly are always formatted. Each
+//! file is formatted with the configuration discovered from its own
+//! directory, with the `overrides` matching it applied.
+//!
+//! `--output-format json|sarif|github` checks the files and prints the
+//! results for CI (see `rfmt::report`) instead of the text output.
+//!
+//! Exit codes match the Ruby CLI: 0 on success, 1 when a file could not be
+//! formatted, `--check` found unformatted files, or the arguments or the
+//! configuration are invalid.
+
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Instant;
+use std::{env, fs};
+
+use rfmt::config::{migrate, Config, PathFilter, CONFIG_FILE_NAMES};
+use rfmt::diff::unified_diff;
+use rfmt::error::RfmtError;
+use rfmt::report::{self, FileReport, Outcome, ReportFormat};
+
+const USAGE: &str = "\
+Usage: rfmt [OPTIONS] [PATH...]
+       rfmt migrate-config [--check] [FILE]
+
+Formats Ruby files in place. Directories are searched for files matching
+the configuration's include/exclude patterns.
+
+migrate-config rewrites a configuration file (default: the nearest
+.rfmt.yml) to the current version, keeping its comments; with --check it
+only reports whether the file needs it.
+
+Options:
+      --check          Report files that need formatting instead of writing them
+      --diff           Print a unified diff of the changes instead of writing them
+      --write          Write formatted files in place (default)
+      --no-write       Print formatted output instead of writing it
+      --stdin, -       Format standard input to standard output
+      --output-format FORMAT
+                       Check and report results as json, sarif or github
+                       annotations instead of text
+      --config PATH    Configuration file (default: discovered .rfmt.yml)
+      --config-schema  Print the JSON Schema of configuration files
+  -j, --jobs N         Number of formatting threads (default: CPU count)
+  -q, --quiet          Print errors and the summary only
+      --verbose        Print unchanged files and timing details
+  -v, --version        Print the version
+  -h, --help           Print this help
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Report unformatted files, printing their diff with `--diff`.
+    Check {
+        diff: bool,
+    },
+    Diff,
+    Write,
+    Print,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    mode: Mode,
+    stdin: bool,
+    output_format: Option<ReportFormat>,
+    config: Option<PathBuf>,
+    jobs: Option<usize>,
+    quiet: bool,
+    verbose: bool,
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Format(Options),
+    MigrateConfig { file: Option<PathBuf>, check: bool },
+    ConfigSchema,
+    Version,
+    Help,
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)) {
+        Ok(Command::Format(options)) => run(&options),
+        Ok(Command::MigrateConfig { file, check }) => migrate_config(file.as_deref(), check),
+        Ok(Command::ConfigSchema) => {
+            let schema = rfmt::config::schema::json_schema();
+            println!("{:#}", schema);
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Version) => {
+            println!("rfmt {} (Rust)", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Help) => {
+            print!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let (mut check, mut diff, mut write) = (false, false, true);
+    let mut options = Options {
+        mode: Mode::Write,
+        stdin: false,
+        output_format: None,
+        config: None,
+        jobs: None,
+        quiet: false,
+        verbose: false,
+        paths: Vec::new(),
+    };
+
+    let mut args = args.into_iter().peekable();
+    // The Ruby CLI's subcommands, so existing hook scripts keep working
+    match args.peek().map(String::as_str) {
+        Some("format") => {
+            args.next();
+        }
+        Some("check") => {
+            args.next();
+            check = true;
+        }
+        Some("version") => return Ok(Command::Version),
+        Some("migrate-config") => {
+            args.next();
+            return parse_migrate_args(args);
+        }
+        _ => {}
+    }
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| match inline_value {
+            Some(value) => Ok(value.to_string()),
+            None => args
+                .next()
+                .ok_or_else(|| format!("Option {} requires a value", name)),
+        };
+        match flag.as_str() {
+            "--check" => check = true,
+            "--diff" => diff = true,
+            "--write" => write = true,
+            "--no-write" => write = false,
+            "--stdin" | "-" => options.stdin = true,
+            "--output-format" => {
+                options.output_format = Some(value("--output-format")?.parse()?);
+            }
+            "--config" => options.config = Some(PathBuf::from(value("--config")?)),
+            "--config-schema" => return Ok(Command::ConfigSchema),
+            "-j" | "--jobs" => {
+                let jobs = value("--jobs")?;
+                match jobs.parse::<usize>() {
+                    Ok(jobs) if jobs > 0 => options.jobs = Some(jobs),
+                    _ => return Err(format!("Invalid --jobs value: {}", jobs)),
+                }
+            }
+            "--parallel" | "--cache" | "--no-cache" => {}
+            "--no-parallel" => options.jobs = Some(1),
+            "-q" | "--quiet" => options.quiet = true,
+            "--verbose" => options.verbose = true,
+            "-v" | "--version" => return Ok(Command::Version),
+            "-h" | "--help" => return Ok(Command::Help),
+            "--" => options.paths.extend(args.by_ref().map(PathBuf::from)),
+            _ if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+            _ => options.paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    // Reports describe what formatting would change; nothing is written
+    let check = check || options.output_format.is_some();
+    options.mode = match (check, diff, write) {
+        (true, diff, _) => Mode::Check { diff },
+        (false, true, _) => Mode::Diff,
+        (false, false, true) => Mode::Write,
+        (false, false, false) => Mode::Print,
+    };
+    if options.stdin && !options.paths.is_empty() {
+        return Err("Paths cannot be combined with --stdin".to_string());
+    }
+    Ok(Command::Format(options))
+}
+
+fn parse_migrate_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let (mut file, mut check) = (None, false);
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+            _ if file.is_some() => return Err("migrate-config takes one file".to_string()),
+            _ => file = Some(PathBuf::from(arg)),
+        }
+    }
+    Ok(Command::MigrateConfig { file, check })
+}
+
+fn run(options: &Options) -> ExitCode {
+    let config = match load_config(options.config.as_deref()) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if options.stdin {
+        return match options.output_format {
+            Some(format) => report_stdin(format, &config),
+            None => format_stdin(options.mode, &config),
+        };
+    }
+
+    let files = collect_files(&options.paths, &config.path_filter());
+    if files.is_empty() && options.output_format.is_none() {
+        println!("No files to format");
+        return ExitCode::SUCCESS;
+    }
+    if !options.quiet && options.output_format.is_none() {
+        match files.as_slice() {
+            [file] => println!("Processing {}...", file.display()),
+            _ => println!("Processing {} file(s)...", files.len()),
+        }
+    }
+
+    let started = Instant::now();
+    let jobs = options
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get));
+    let outcomes = format_files(&files, options.config.as_deref(), options.mode, jobs);
+    if let Some(format) = options.output_format {
+        let reports: Vec<FileReport> = files
+            .into_iter()
+            .zip(outcomes)
+            .map(|(path, outcome)| FileReport { path, outcome })
+            .collect();
+        return print_reports(format, &reports);
+    }
+    report(options, &files, &outcomes, started)
+}
+
+/// Rewrites `file`, or the configuration file nearest the working
+/// directory, to the current configuration version.
+fn migrate_config(file: Option<&Path>, check: bool) -> ExitCode {
+    let path = match file {
+        Some(file) => file.to_path_buf(),
+        None => {
+            let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            let nearest = cwd.ancestors().find_map(|dir| {
+                CONFIG_FILE_NAMES
+                    .iter()
+                    .map(|name| dir.join(name))
+                    .find(|path| path.is_file())
+            });
+            match nearest {
+                Some(path) => path,
+                None => {
+                    eprintln!("No configuration file found");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let migration = match migrate::migrate(&contents) {
+        Ok(migration) => migration,
+        Err(e) => {
+            eprintln!("Error in {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for deprecation in &migration.deprecations {
+        println!("{}: {}", path.display(), deprecation);
+    }
+    if migration.source == contents {
+        println!(
+            "{} is already at version {}",
+            path.display(),
+            migrate::CURRENT_VERSION
+        );
+        return ExitCode::SUCCESS;
+    }
+    if check {
+        println!(
+            "{} needs migrating to version {}",
+            path.display(),
+            migrate::CURRENT_VERSION
+        );
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = atomic_write(&path, &migration.source) {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        return ExitCode::FAILURE;
+    }
+    if !migration.comments_kept {
+        eprintln!(
+            "Warning: {} could not be edited in place and was rewritten without its comments",
+            path.display()
+        );
+    }
+    println!(
+        "Migrated {} to version {}",
+        path.display(),
+        migrate::CURRENT_VERSION
+    );
+    ExitCode::SUCCESS
+}
+
+fn load_config(path: Option<&Path>) -> Result<Config, String> {
+    if let Some(path) = path {
+        if !path.exists() {
+            return Err(format!("Configuration file not found: {}", path.display()));
+        }
+    }
+    Config::resolve(path, None).map_err(|e| e.to_string())
+}
+
+fn format_stdin(mode: Mode, config: &Config) -> ExitCode {
+    let mut source = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut source) {
+        eprintln!("Error in <stdin>: {}", e);
+        return ExitCode::FAILURE;
+    }
+    let formatted = match Outcome::new(source.clone(), config) {
+        Outcome::Unchanged => source.clone(),
+        Outcome::Changed { formatted, .. } => formatted,
+        Outcome::Failed { error, .. } => {
+            eprintln!("Error in <stdin>: {}", error);
+            return ExitCode::FAILURE;
+        }
+        Outcome::IoError(error) => {
+            eprintln!("Error in <stdin>: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut stdout = io::stdout().lock();
+    let result = match mode {
+        Mode::Check { diff } => {
+            if source == formatted {
+                return ExitCode::SUCCESS;
+            }
+            eprintln!("<stdin> needs formatting");
+            if diff {
+                let _ = stdout
+                    .write_all(unified_diff(&source, &formatted, "<stdin>", "<stdin>").as_bytes());
+            }
+            return ExitCode::FAILURE;
+        }
+        Mode::Diff => {
+            stdout.write_all(unified_diff(&source, &formatted, "<stdin>", "<stdin>").as_bytes())
+        }
+        Mode::Write | Mode::Print => stdout.write_all(formatted.as_bytes()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error in <stdin>: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn report_stdin(format: ReportFormat, config: &Config) -> ExitCode {
+    let mut source = String::new();
+    let outcome = match io::stdin().read_to_string(&mut source) {
+        Ok(_) => Outcome::new(source, config),
+        Err(e) => Outcome::IoError(e.to_string()),
+    };
+    let report = FileReport {
+        path: PathBuf::from("<stdin>"),
+        outcome,
+    };
+    print_reports(format, &[report])
+}
+
+/// Prints `reports` in `format`; fails unless every file is formatted.
+fn print_reports(format: ReportFormat, reports: &[FileReport]) -> ExitCode {
+    let _ = io::stdout()
+        .lock()
+        .write_all(report::render(format, reports).as_bytes());
+    if reports.iter().all(|report| report.outcome.is_unchanged()) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Files to format: explicit file arguments as given, plus the included
+/// files under each directory argument (the current directory when there
+/// are none), sorted.
+fn collect_files(paths: &[PathBuf], filter: &PathFilter) -> Vec<PathBuf> {
+    let cwd = env::current_dir().unwrap_or_default();
+    let mut files = Vec::new();
+    if paths.is_empty() {
+        walk(Path::new("."), &cwd, filter, &mut files);
+    }
+    for path in paths {
+        if path.is_dir() {
+            walk(path, &cwd, filter, &mut files);
+        } else {
+            files.push(display_path(path).to_path_buf());
+        }
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Collects included files below `dir`. Like the Ruby CLI's `Dir.glob`,
+/// hidden entries and symlinked directories are skipped; directories
+/// matching an `exclude` pattern are not entered.
+fn walk(dir: &Path, cwd: &Path, filter: &PathFilter, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        let relative = pattern_path(&path, cwd);
+        if file_type.is_dir() {
+            if !filter.excludes(relative) {
+                walk(&path, cwd, filter, files);
+            }
+        } else if (file_type.is_file() || path.is_file()) && filter.includes(relative) {
+            files.push(display_path(&path).to_path_buf());
+        }
+    }
+}
+
+/// `path` as the include/exclude patterns see it: relative to the working
+/// directory the configuration applies to.
+fn pattern_path<'a>(path: &'a Path, cwd: &Path) -> &'a Path {
+    path.strip_prefix(cwd)
+        .unwrap_or_else(|_| display_path(path))
+}
+
+fn display_path(path: &Path) -> &Path {
+    path.strip_prefix(".").unwrap_or(path)
+}
+
+/// Formats `files` on `jobs` threads; outcomes are returned in file order.
+/// A file whose worker panicked outside the formatter is reported as failed.
+fn format_files(
+    files: &[PathBuf],
+    config_path: Option<&Path>,
+    mode: Mode,
+    jobs: usize,
+) -> Vec<Outcome> {
+    let next = AtomicUsize::new(0);
+    let jobs = jobs.clamp(1, files.len().max(1));
+    // Filled as each file finishes, so a panic loses only the file it hit
+    let outcomes: Mutex<Vec<Option<Outcome>>> = Mutex::new(files.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(file) = files.get(index) else {
+                        break;
+                    };
+                    let outcome = format_file(file, config_path, mode);
+                    outcomes.lock().unwrap_or_else(PoisonError::into_inner)[index] = Some(outcome);
+                })
+            })
+            .collect();
+        // Joined here rather than by the scope, which would re-raise a panic;
+        // the other workers carry on with the remaining files
+        for worker in workers {
+            let _ = worker.join();
+        }
+    });
+
+    let outcomes = outcomes
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+    files
+        .iter()
+        .zip(outcomes)
+        .map(|(file, outcome)| {
+            outcome.unwrap_or_else(|| Outcome::Failed {
+                source: fs::read_to_string(file).unwrap_or_default(),
+                error: RfmtError::format("internal error"),
+            })
+        })
+        .collect()
+}
+
+/// Formats `path` with the configuration resolved for it. In
+/// `Mode::Write`, a `Changed` file has been written.
+fn format_file(path: &Path, config_path: Option<&Path>, mode: Mode) -> Outcome {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => return Outcome::IoError(e.to_string()),
+    };
+    let config = match Config::resolve(config_path, Some(path)) {
+        Ok(config) => config,
+        Err(error) => return Outcome::Failed { source, error },
+    };
+    let outcome = Outcome::new(source, &config);
+
+    if let (Mode::Write, Outcome::Changed { formatted, .. }) = (mode, &outcome) {
+        if let Err(e) = atomic_write(path, formatted) {
+            return Outcome::IoError(e.to_string());
+        }
+    }
+    outcome
+}
+
+/// Temp file must live in the same directory: rename across filesystems is
+/// not atomic.
+fn atomic_write(path: &Path, content: &str) -> io::Result<()> {
+    // rename would replace a symlink itself; write to its target instead
+    let path = if fs::symlink_metadata(path)?.file_type().is_symlink() {
+        fs::canonicalize(path)?
+    } else {
+        path.to_path_buf()
+    };
+    let permissions = fs::metadata(&path)?.permissions();
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".rfmt-{}.tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = fs::write(&tmp_path, content)
+        .and_then(|()| fs::set_permissions(&tmp_path, permissions))
+        .and_then(|()| fs::rename(&tmp_path, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Prints per-file results and the summary the Ruby CLI prints, and picks
+/// the exit code.
+fn report(
+    options: &Options,
+    files: &[PathBuf],
+    outcomes: &[Outcome],
+    started: Instant,
+) -> ExitCode {
+    let (mut changed, mut needs_formatting, mut errors) = (0, 0, 0);
+    let mut stdout = io::stdout().lock();
+
+    for (file, outcome) in files.iter().zip(outcomes) {
+        let name = file.display();
+        match outcome {
+            Outcome::Failed { error, .. } => {
+                eprintln!("Error in {}: {}", name, error);
+                errors += 1;
+            }
+            Outcome::IoError(message) => {
+                eprintln!("Error in {}: {}", name, message);
+                errors += 1;
+            }
+            Outcome::Changed {
+                original,
+                formatted,
+            } => {
+                changed += 1;
+                let diff = || {
+                    unified_diff(
+                        original,
+                        formatted,
+                        &format!("a/{}", name),
+                        &format!("b/{}", name),
+                    )
+                };
+                let _ = match options.mode {
+                    Mode::Check { diff: show_diff } => {
+                        needs_formatting += 1;
+                        let _ = writeln!(stdout, "{} needs formatting", name);
+                        if show_diff {
+                            stdout.write_all(diff().as_bytes())
+                        } else {
+                            Ok(())
+                        }
+                    }
+                    Mode::Diff => stdout.write_all(diff().as_bytes()),
+                    Mode::Print => stdout.write_all(formatted.as_bytes()),
+                    Mode::Write if options.quiet => Ok(()),
+                    Mode::Write => writeln!(stdout, "✓ Formatted {}", name),
+                };
+            }
+            Outcome::Unchanged => {
+                if options.verbose && !matches!(options.mode, Mode::Check { .. }) {
+                    let _ = writeln!(stdout, "✓ {} already formatted", name);
+                }
+            }
+        }
+    }
+
+    let total = files.len();
+    let unchanged = total - changed - errors;
+    let summary = if errors > 0 {
+        format!("\n✗ Failed: {} error(s) occurred", errors)
+    } else if needs_formatting > 0 {
+        format!(
+            "\n✗ Check failed: {} file(s) need formatting",
+            needs_formatting
+        )
+    } else if options.quiet {
+        if changed > 0 {
+            format!("✓ {} files formatted", changed)
+        } else {
+            String::new()
+        }
+    } else if total == 1 {
+        if changed > 0 {
+            "\n✓ Formatted 1 file".to_string()
+        } else {
+            "\n✓ File is already formatted".to_string()
+        }
+    } else {
+        let mut parts = Vec::new();
+        if changed > 0 {
+            parts.push(format!("{} formatted", changed));
+        }
+        if unchanged > 0 {
+            parts.push(format!("{} unchanged", unchanged));
+        }
+        let mut summary = format!("\n✓ Processed {} files", total);
+        if !parts.is_empty() {
+            summary.push_str(&format!("\n  ({})", parts.join(", ")));
+        }
+        summary
+    };
+    if !summary.is_empty() {
+        let _ = writeln!(stdout, "{}", summary);
+    }
+
+    if options.verbose && !options.quiet {
+        let seconds = started.elapsed().as_secs_f64();
+        let _ = writeln!(stdout, "\nDetails:\n  Total files: {}", total);
+        let _ = writeln!(stdout, "  Total time: {:.2}s", seconds);
+        if seconds > 0.0 {
+            let _ = writeln!(stdout, "  Files/sec: {:.1}", total as f64 / seconds);
+        }
+    }
+
+    if errors > 0 || needs_formatting > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn options(args: &[&str]) -> Options {
+        match parse(args) {
+            Ok(Command::Format(options)) => options,
+            other => panic!("expected format options, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_modes_follow_ruby_cli_precedence() {
+        assert_eq!(options(&[]).mode, Mode::Write);
+        assert_eq!(options(&["--no-write"]).mode, Mode::Print);
+        assert_eq!(options(&["--diff"]).mode, Mode::Diff);
+        assert_eq!(
+            options(&["--check", "--diff"]).mode,
+            Mode::Check { diff: true }
+        );
+        assert_eq!(options(&["check", "lib"]).mode, Mode::Check { diff: false });
+    }
+
+    #[test]
+    fn test_output_format_implies_check() {
+        let options = options(&["--output-format=sarif", "lib"]);
+        assert_eq!(options.output_format, Some(ReportFormat::Sarif));
+        assert_eq!(options.mode, Mode::Check { diff: false });
+        assert!(parse(&["--output-format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_option_values() {
+        let options = options(&["--config=ci.yml", "-j", "4", "--", "--odd.rb"]);
+        assert_eq!(options.config, Some(PathBuf::from("ci.yml")));
+        assert_eq!(options.jobs, Some(4));
+        assert_eq!(options.paths, vec![PathBuf::from("--odd.rb")]);
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["--jobs", "0"]).is_err());
+        assert!(parse(&["--config"]).is_err());
+        assert!(parse(&["--stdin", "app.rb"]).is_err());
+        assert_eq!(parse(&["-v"]), Ok(Command::Version));
+        assert_eq!(parse(&["--config-schema"]), Ok(Command::ConfigSchema));
+        assert_eq!(
+            parse(&["migrate-config", "--check", "ci.yml"]),
+            Ok(Command::MigrateConfig {
+                file: Some(PathBuf::from("ci.yml")),
+                check: true
+            })
+        );
+        assert!(parse(&["migrate-config", "a.yml", "b.yml"]).is_err());
+    }
+
+    #[test]
+    fn test_walk_honors_include_and_exclude() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "app/user.rb",
+            "lib/tasks/db.rake",
+            "lib/readme.md",
+            "vendor/gem/gem.rb",
+            ".hidden/skip.rb",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x = 1\n").unwrap();
+        }
+
+        let mut files = Vec::new();
+        walk(root, root, &Config::default().path_filter(), &mut files);
+        files.sort();
+        assert_eq!(
+            files,
+            vec![root.join("app/user.rb"), root.join("lib/tasks/db.rake")]
+        );
+    }
+}
@@ -0,0 +1,521 @@
+//! Formatting settings read from other tools' configuration files, so a
+//! project does not declare its line length or quote style twice.
+//!
+//! [`Source::Rubocop`] maps the layout and style cops of the nearest
+//! `.rubocop.yml`; [`Source::Editorconfig`] maps the properties of the
+//! `.editorconfig` sections matching Ruby files. Both produce settings in the
+//! `.rfmt.yml` layout, layered under the project's own configuration.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde_yaml::{Mapping, Value};
+
+use crate::error::{Result, RfmtError, Span};
+use crate::line_index::LineIndex;
+
+/// A configuration format settings can be imported from, named in the
+/// `import:` list of `.rfmt.yml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Rubocop,
+    Editorconfig,
+}
+
+impl Source {
+    /// The names `import:` accepts, in the order of [`Source`]'s variants.
+    pub const NAMES: [&'static str; 2] = ["rubocop", "editorconfig"];
+}
+
+impl std::str::FromStr for Source {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, String> {
+        match name {
+            "rubocop" => Ok(Source::Rubocop),
+            "editorconfig" => Ok(Source::Editorconfig),
+            _ => Err(format!(
+                "unknown import {:?}, expected {}",
+                name,
+                Source::NAMES.join(" or ")
+            )),
+        }
+    }
+}
+
+/// Settings imported for one directory.
+#[derive(Debug, Clone)]
+pub struct Imported {
+    /// `.rfmt.yml` settings, e.g. `{formatting: {line_length: 120}}`.
+    pub settings: Mapping,
+    /// Files read, nearest first.
+    pub files: Vec<PathBuf>,
+    /// Cops and properties found but not mapped onto rfmt settings.
+    pub unmapped: Vec<String>,
+}
+
+impl Source {
+    /// Reads this source's files for the code in `dir`, or `None` when there
+    /// are none. Unmapped entries are also logged as warnings.
+    pub fn import(self, dir: &Path) -> Result<Option<Imported>> {
+        self.import_reading(dir, &mut Vec::new())
+    }
+
+    /// [`Source::import`], adding every file read to `read` with its mtime.
+    pub(super) fn import_reading(
+        self,
+        dir: &Path,
+        read: &mut Vec<(PathBuf, Option<SystemTime>)>,
+    ) -> Result<Option<Imported>> {
+        let imported = match self {
+            Source::Rubocop => import_rubocop(dir, read)?,
+            Source::Editorconfig => import_editorconfig(dir, read)?,
+        };
+        if let Some(imported) = &imported {
+            for entry in &imported.unmapped {
+                log::warn!("Not imported from {:?}: {}", imported.files[0], entry);
+            }
+        }
+        Ok(imported)
+    }
+}
+
+fn import_rubocop(
+    dir: &Path,
+    read: &mut Vec<(PathBuf, Option<SystemTime>)>,
+) -> Result<Option<Imported>> {
+    let Some(path) = dir
+        .ancestors()
+        .map(|dir| dir.join(".rubocop.yml"))
+        .find(|path| path.is_file())
+    else {
+        return Ok(None);
+    };
+
+    read.push((path.clone(), super::file_mtime(&path)));
+    let contents = read_file(&path)?;
+    let (settings, unmapped) = map_rubocop(&contents).map_err(|e| {
+        let span = e.location().map(|location| {
+            let index = LineIndex::new(contents.as_bytes());
+            Span::new(&index, location.index()..location.index())
+        });
+        RfmtError::ConfigError {
+            message: format!("Failed to parse RuboCop config file: {}", e),
+            file: Some(path.clone()),
+            span,
+        }
+    })?;
+    Ok(Some(Imported {
+        settings,
+        files: vec![path],
+        unmapped,
+    }))
+}
+
+/// Maps the cops of a `.rubocop.yml`. `inherit_from` and other RuboCop
+/// files are not followed.
+fn map_rubocop(contents: &str) -> std::result::Result<(Mapping, Vec<String>), serde_yaml::Error> {
+    let cops = match serde_yaml::from_str::<Value>(contents)? {
+        Value::Mapping(cops) => cops,
+        _ => Mapping::new(),
+    };
+
+    let mut formatting = Mapping::new();
+    let mut style = Mapping::new();
+    let mut trailing_comma_cop: Option<&str> = None;
+    let mut unmapped = Vec::new();
+    for (name, options) in &cops {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        let option = |key: &str| options.get(key);
+        let enforced = option("EnforcedStyle").and_then(Value::as_str);
+        if option("Enabled").and_then(Value::as_bool) == Some(false) {
+            // A disabled cop rfmt does not enforce anyway changes nothing.
+            if is_mapped(name) {
+                unmapped.push(format!("{} (disabled, rfmt still formats it)", name));
+            }
+            continue;
+        }
+        let mapped = match name {
+            "Layout/LineLength" => set(
+                &mut formatting,
+                "line_length",
+                option("Max").and_then(Value::as_u64).map(Value::from),
+            ),
+            "Layout/IndentationWidth" => set(
+                &mut formatting,
+                "indent_width",
+                option("Width").and_then(Value::as_u64).map(Value::from),
+            ),
+            "Layout/IndentationStyle" => {
+                let indent_style = enforced.filter(|style| matches!(*style, "spaces" | "tabs"));
+                set(
+                    &mut formatting,
+                    "indent_style",
+                    indent_style.map(Value::from),
+                )
+            }
+            "Style/StringLiterals" => {
+                let quote_style = match enforced {
+                    Some("single_quotes") => Some("single"),
+                    Some("double_quotes") => Some("double"),
+                    _ => None,
+                };
+                set(&mut formatting, "quote_style", quote_style.map(Value::from))
+            }
+            "Style/HashSyntax" => {
+                let hash_syntax = match enforced {
+                    Some("ruby19") => Some("ruby19"),
+                    Some("hash_rockets") => Some("hash_rockets"),
+                    Some("ruby19_no_mixed_keys") | Some("no_mixed_keys") => Some("consistent"),
+                    _ => None,
+                };
+                set(&mut style, "hash_syntax", hash_syntax.map(Value::from))
+            }
+            "Style/TrailingCommaInArrayLiteral"
+            | "Style/TrailingCommaInHashLiteral"
+            | "Style/TrailingCommaInArguments" => {
+                let comma = match option("EnforcedStyleForMultiline").and_then(Value::as_str) {
+                    Some("comma") | Some("consistent_comma") => Some("multiline"),
+                    Some("no_comma") => Some("never"),
+                    _ => None,
+                };
+                match (comma, trailing_comma_cop) {
+                    // rfmt has one setting for every list; the first cop wins.
+                    (Some(comma), Some(first)) => {
+                        if style.get("trailing_comma").and_then(Value::as_str) != Some(comma) {
+                            unmapped.push(format!("{} (conflicts with {})", name, first));
+                            continue;
+                        }
+                        true
+                    }
+                    (Some(comma), None) => {
+                        trailing_comma_cop = Some(name);
+                        set(&mut style, "trailing_comma", Some(comma.into()))
+                    }
+                    (None, _) => false,
+                }
+            }
+            _ if name.starts_with("Layout/") || name.starts_with("Style/") => false,
+            _ => continue,
+        };
+        if !mapped {
+            unmapped.push(name.to_string());
+        }
+    }
+
+    if !style.is_empty() {
+        formatting.insert("style".into(), style.into());
+    }
+    Ok((settings_for(formatting), unmapped))
+}
+
+/// Sets `key` when there is a value, returning whether it was set.
+fn set(mapping: &mut Mapping, key: &str, value: Option<Value>) -> bool {
+    match value {
+        Some(value) => {
+            mapping.insert(key.into(), value);
+            true
+        }
+        None => false,
+    }
+}
+
+fn is_mapped(cop: &str) -> bool {
+    matches!(
+        cop,
+        "Layout/LineLength"
+            | "Layout/IndentationWidth"
+            | "Layout/IndentationStyle"
+            | "Style/StringLiterals"
+            | "Style/HashSyntax"
+            | "Style/TrailingCommaInArrayLiteral"
+            | "Style/TrailingCommaInHashLiteral"
+            | "Style/TrailingCommaInArguments"
+    )
+}
+
+/// Like editors do, reads `.editorconfig` files from `dir` up to the first
+/// one declaring `root = true`, nearer files winning.
+fn import_editorconfig(
+    dir: &Path,
+    read: &mut Vec<(PathBuf, Option<SystemTime>)>,
+) -> Result<Option<Imported>> {
+    let mut files = Vec::new();
+    let mut sources = Vec::new();
+    for dir in dir.ancestors() {
+        let path = dir.join(".editorconfig");
+        if !path.is_file() {
+            continue;
+        }
+        read.push((path.clone(), super::file_mtime(&path)));
+        let contents = read_file(&path)?;
+        let root = editorconfig_is_root(&contents);
+        files.push(path);
+        sources.push(contents);
+        if root {
+            break;
+        }
+    }
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    let mut properties = BTreeMap::new();
+    let mut unmapped = Vec::new();
+    for contents in sources.iter().rev() {
+        ruby_properties(contents, &mut properties, &mut unmapped);
+    }
+    let (settings, unmapped_properties) = map_editorconfig(&properties);
+    unmapped.extend(unmapped_properties);
+    Ok(Some(Imported {
+        settings,
+        files,
+        unmapped,
+    }))
+}
+
+fn editorconfig_is_root(contents: &str) -> bool {
+    let mut root = false;
+    for (section, key, value) in editorconfig_entries(contents) {
+        if section.is_none() && key == "root" {
+            root = value == "true";
+        }
+    }
+    root
+}
+
+/// Collects the properties of the sections that apply to Ruby files,
+/// later sections winning. Sections for paths (`[lib/**.rb]`) cannot be
+/// applied per directory and are reported instead.
+fn ruby_properties(
+    contents: &str,
+    properties: &mut BTreeMap<String, String>,
+    unmapped: &mut Vec<String>,
+) {
+    for (section, key, value) in editorconfig_entries(contents) {
+        let Some(section) = section else {
+            continue;
+        };
+        if section.contains('/') {
+            let entry = format!("[{}]", section);
+            if !unmapped.contains(&entry) {
+                unmapped.push(entry);
+            }
+            continue;
+        }
+        let glob = globset::Glob::new(&format!("**/{}", section))
+            .map(|glob| glob.compile_matcher().is_match("file.rb"));
+        if glob != Ok(true) {
+            continue;
+        }
+        if value == "unset" {
+            properties.remove(&key);
+        } else {
+            properties.insert(key, value);
+        }
+    }
+}
+
+/// `(section, key, value)` for each property line, with keys and values
+/// lowercased as the format is case-insensitive.
+fn editorconfig_entries(contents: &str) -> Vec<(Option<String>, String, String)> {
+    let mut section = None;
+    let mut entries = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Some(name.to_string());
+        } else if let Some((key, value)) = line.split_once('=') {
+            entries.push((
+                section.clone(),
+                key.trim().to_ascii_lowercase(),
+                value.trim().to_ascii_lowercase(),
+            ));
+        }
+    }
+    entries
+}
+
+fn map_editorconfig(properties: &BTreeMap<String, String>) -> (Mapping, Vec<String>) {
+    let mut formatting = Mapping::new();
+    let mut unmapped = Vec::new();
+    let number = |key: &str| {
+        properties
+            .get(key)
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+
+    match properties.get("indent_style").map(String::as_str) {
+        Some("space") => {
+            formatting.insert("indent_style".into(), "spaces".into());
+        }
+        Some("tab") => {
+            formatting.insert("indent_style".into(), "tabs".into());
+        }
+        _ => {}
+    }
+    // indent_size = tab (or no indent_size with tabs) means the tab width.
+    let indent_width = number("indent_size").or_else(|| number("tab_width"));
+    if let Some(width) = indent_width {
+        formatting.insert("indent_width".into(), width.into());
+    }
+    if let Some(length) = number("max_line_length") {
+        formatting.insert("line_length".into(), length.into());
+    }
+
+    for key in properties.keys() {
+        let mapped = matches!(
+            key.as_str(),
+            "indent_style" | "indent_size" | "tab_width" | "max_line_length"
+        );
+        if !mapped {
+            unmapped.push(key.clone());
+        }
+    }
+    (settings_for(formatting), unmapped)
+}
+
+fn settings_for(formatting: Mapping) -> Mapping {
+    let mut settings = Mapping::new();
+    if !formatting.is_empty() {
+        settings.insert("formatting".into(), formatting.into());
+    }
+    settings
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| {
+        RfmtError::config(format!("Failed to read config file: {}", e)).with_file(path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatting(settings: &Mapping) -> String {
+        serde_yaml::to_string(&settings["formatting"]).unwrap()
+    }
+
+    #[test]
+    fn test_rubocop_cops_map_onto_formatting() {
+        let yaml = r#"
+AllCops:
+  TargetRubyVersion: 3.0
+Layout/LineLength:
+  Max: 120
+Layout/IndentationWidth:
+  Width: 4
+Style/StringLiterals:
+  EnforcedStyle: single_quotes
+Style/HashSyntax:
+  EnforcedStyle: ruby19_no_mixed_keys
+Style/TrailingCommaInArrayLiteral:
+  EnforcedStyleForMultiline: consistent_comma
+Style/TrailingCommaInHashLiteral:
+  EnforcedStyleForMultiline: no_comma
+Metrics/BlockLength:
+  Max: 50
+Layout/EmptyLines:
+  Enabled: true
+Style/FrozenStringLiteralComment:
+  Enabled: false
+"#;
+        let (settings, unmapped) = map_rubocop(yaml).unwrap();
+        assert_eq!(
+            formatting(&settings),
+            "line_length: 120\nindent_width: 4\nquote_style: single\nstyle:\n  hash_syntax: consistent\n  trailing_comma: multiline\n"
+        );
+        assert_eq!(
+            unmapped,
+            vec![
+                "Style/TrailingCommaInHashLiteral (conflicts with Style/TrailingCommaInArrayLiteral)",
+                "Layout/EmptyLines",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rubocop_disabled_or_unknown_styles_are_reported() {
+        let yaml =
+            "Style/StringLiterals:\n  Enabled: false\nStyle/HashSyntax:\n  EnforcedStyle: either\n";
+        let (settings, unmapped) = map_rubocop(yaml).unwrap();
+        assert!(settings.is_empty());
+        assert_eq!(
+            unmapped,
+            vec![
+                "Style/StringLiterals (disabled, rfmt still formats it)",
+                "Style/HashSyntax"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_editorconfig_sections_for_ruby_files() {
+        let contents = r#"
+root = true
+
+[*]
+indent_style = space
+indent_size = 4
+insert_final_newline = true
+
+[*.{rb,rake}]
+indent_size = 2
+max_line_length = 120
+
+[*.md]
+max_line_length = off
+
+[lib/**.rb]
+indent_size = 3
+"#;
+        let mut properties = BTreeMap::new();
+        let mut unmapped = Vec::new();
+        assert!(editorconfig_is_root(contents));
+        ruby_properties(contents, &mut properties, &mut unmapped);
+        let (settings, unmapped_properties) = map_editorconfig(&properties);
+        unmapped.extend(unmapped_properties);
+
+        assert_eq!(
+            formatting(&settings),
+            "indent_style: spaces\nindent_width: 2\nline_length: 120\n"
+        );
+        assert_eq!(unmapped, vec!["[lib/**.rb]", "insert_final_newline"]);
+    }
+
+    #[test]
+    fn test_editorconfig_cascades_to_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("app");
+        std::fs::create_dir(&app).unwrap();
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n[*]\nindent_style = tab\ntab_width = 4\nmax_line_length = 90\n",
+        )
+        .unwrap();
+        std::fs::write(app.join(".editorconfig"), "[*.rb]\nmax_line_length = 80\n").unwrap();
+
+        let imported = Source::Editorconfig.import(&app).unwrap().unwrap();
+        assert_eq!(
+            formatting(&imported.settings),
+            "indent_style: tabs\nindent_width: 4\nline_length: 80\n"
+        );
+        assert_eq!(
+            imported.files,
+            vec![app.join(".editorconfig"), dir.path().join(".editorconfig")]
+        );
+    }
+
+    #[test]
+    fn test_unknown_source_name() {
+        assert_eq!("rubocop".parse(), Ok(Source::Rubocop));
+        assert!("prettier".parse::<Source>().is_err());
+    }
+}
@@ -0,0 +1,448 @@
+//! Configuration file versions, and upgrading files to the current one.
+//!
+//! | `version` | Layout |
+//! |-----------|--------|
+//! | `"1.0"`, or none | `formatting.style.quotes` overrides `formatting.quote_style`; `style.hash_syntax` may also be spelled `Ruby19` / `HashRockets` / `Consistent` |
+//! | `"2"` | `formatting.quote_style` only; `style.hash_syntax` is `ruby19` / `hash_rockets` / `consistent` |
+//!
+//! An older file is read as if upgraded by [`upgrade`], with a warning for
+//! each deprecated setting; in a current file those settings are errors.
+//! [`migrate`] rewrites a file to the current version, keeping its comments
+//! and layout where it can.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_yaml::Value;
+
+use super::schema;
+use crate::error::RfmtError;
+
+pub const CURRENT_VERSION: &str = "2";
+
+/// Every version a configuration file may declare, oldest first.
+pub const VERSIONS: [&str; 2] = ["1.0", CURRENT_VERSION];
+
+/// A setting spelled the way an older version did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    /// Where the setting is, such as `overrides[0].formatting.style.quotes`.
+    pub setting: String,
+    pub replacement: String,
+    /// The mapping keys leading to the setting, for locating it in the file.
+    pub keys: Vec<&'static str>,
+}
+
+impl fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is deprecated, use {}",
+            self.setting, self.replacement
+        )
+    }
+}
+
+/// A configuration file rewritten to the current version by [`migrate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// The rewritten file; the input as it was when nothing changed.
+    pub source: String,
+    /// The version the file declared.
+    pub from_version: Option<String>,
+    pub deprecations: Vec<Deprecation>,
+    /// `false` when the file could not be edited line by line and `source`
+    /// was serialized from its settings instead, losing comments.
+    pub comments_kept: bool,
+}
+
+/// The version `settings` declares. An unquoted number (`version: 1.0`)
+/// counts as written.
+pub fn version_of(settings: &Value) -> Option<String> {
+    match settings.get("version")? {
+        Value::String(version) => Some(version.clone()),
+        Value::Number(version) => Some(version.to_string()),
+        _ => None,
+    }
+}
+
+/// Replaces the deprecated settings in `settings`, whatever its version,
+/// and returns them. A file of an older version, or of none, is marked
+/// current; an unknown version is left for validation to reject.
+pub fn upgrade(settings: &mut Value) -> Vec<Deprecation> {
+    let mut deprecations = Vec::new();
+    let is_old = version_of(settings)
+        .is_none_or(|version| version != CURRENT_VERSION && VERSIONS.contains(&version.as_str()));
+    let Value::Mapping(mapping) = settings else {
+        return deprecations;
+    };
+
+    if let Some(formatting) = mapping.get_mut("formatting") {
+        upgrade_formatting(formatting, "formatting", &["formatting"], &mut deprecations);
+    }
+    if let Some(Value::Sequence(entries)) = mapping.get_mut("overrides") {
+        for (index, entry) in entries.iter_mut().enumerate() {
+            if let Some(formatting) = entry.get_mut("formatting") {
+                upgrade_formatting(
+                    formatting,
+                    &format!("overrides[{}].formatting", index),
+                    &["overrides", "formatting"],
+                    &mut deprecations,
+                );
+            }
+        }
+    }
+    if is_old {
+        mapping.insert("version".into(), CURRENT_VERSION.into());
+    }
+    deprecations
+}
+
+fn upgrade_formatting(
+    formatting: &mut Value,
+    location: &str,
+    keys: &[&'static str],
+    deprecations: &mut Vec<Deprecation>,
+) {
+    let Value::Mapping(formatting) = formatting else {
+        return;
+    };
+    let keys_to = |key: &'static str| [keys, &["style", key]].concat();
+
+    let quotes = match formatting.get_mut("style") {
+        Some(Value::Mapping(style)) => style
+            .shift_remove("quotes")
+            .map(|quotes| (quotes, style.is_empty())),
+        _ => None,
+    };
+    if let Some((quotes, style_is_empty)) = quotes {
+        if style_is_empty {
+            formatting.shift_remove("style");
+        }
+        formatting.insert("quote_style".into(), quotes);
+        deprecations.push(Deprecation {
+            setting: format!("{}.style.quotes", location),
+            replacement: format!("{}.quote_style", location),
+            keys: keys_to("quotes"),
+        });
+    }
+
+    if let Some(Value::Mapping(style)) = formatting.get_mut("style") {
+        if let Some(Value::String(spelling)) = style.get_mut("hash_syntax") {
+            if let Some(current) = current_hash_syntax(spelling) {
+                deprecations.push(Deprecation {
+                    setting: format!("{}.style.hash_syntax: {}", location, spelling),
+                    replacement: current.to_string(),
+                    keys: keys_to("hash_syntax"),
+                });
+                *spelling = current.to_string();
+            }
+        }
+    }
+}
+
+/// The current spelling of a `hash_syntax` value written as its variant
+/// name.
+fn current_hash_syntax(spelling: &str) -> Option<&'static str> {
+    match spelling {
+        "Ruby19" => Some("ruby19"),
+        "HashRockets" => Some("hash_rockets"),
+        "Consistent" => Some("consistent"),
+        _ => None,
+    }
+}
+
+pub(super) fn unsupported_version(version: &str) -> String {
+    format!(
+        "version must be {}, got {:?}",
+        VERSIONS
+            .map(|version| format!("{:?}", version))
+            .join(" or "),
+        version
+    )
+}
+
+/// Rewrites the configuration file `contents` to the current version.
+///
+/// Settings are edited in place line by line, so comments and key order
+/// survive. When that cannot reproduce the upgraded settings (flow-style
+/// mappings such as `style: { quotes: single }`), the settings are
+/// serialized anew and [`Migration::comments_kept`] is `false`.
+pub fn migrate(contents: &str) -> crate::error::Result<Migration> {
+    let original: Value = serde_yaml::from_str(contents)
+        .map_err(|e| RfmtError::config(format!("Failed to parse config file: {}", e)))?;
+    let from_version = version_of(&original);
+    if let Some(version) = &from_version {
+        if !VERSIONS.contains(&version.as_str()) {
+            return Err(RfmtError::config(unsupported_version(version)));
+        }
+    }
+
+    let mut upgraded = original.clone();
+    let deprecations = upgrade(&mut upgraded);
+    if upgraded == original {
+        return Ok(Migration {
+            source: contents.to_string(),
+            from_version,
+            deprecations,
+            comments_kept: true,
+        });
+    }
+
+    let rewritten = rewrite(contents);
+    let (source, comments_kept) =
+        if serde_yaml::from_str::<Value>(&rewritten).is_ok_and(|settings| settings == upgraded) {
+            (rewritten, true)
+        } else {
+            (serialize(upgraded)?, false)
+        };
+    Ok(Migration {
+        source,
+        from_version,
+        deprecations,
+        comments_kept,
+    })
+}
+
+/// `contents` with the changes [`upgrade`] makes applied to its lines,
+/// everything else kept as written.
+fn rewrite(contents: &str) -> String {
+    let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let entries = schema::key_lines(contents);
+    // Line number to its new text, empty to delete it.
+    let mut replaced: BTreeMap<usize, String> = BTreeMap::new();
+    // Line number to the text inserted before it.
+    let mut inserted: BTreeMap<usize, String> = BTreeMap::new();
+
+    let with_value = |entry: &schema::KeyLine, value: &str| {
+        let line = lines[entry.index];
+        let (start, end) = (
+            entry.value.start - entry.start,
+            entry.value.end - entry.start,
+        );
+        format!("{}{}{}", &line[..start], value, &line[end..])
+    };
+    // The comment lines directly above a line, re-indented to `column` to
+    // move them along with it.
+    let comments_above = |index: usize, column: usize| {
+        let first = lines[..index]
+            .iter()
+            .rposition(|line| !line.trim_start().starts_with('#'))
+            .map_or(0, |line| line + 1);
+        (first..index)
+            .map(|line| format!("{}{}", " ".repeat(column), lines[line].trim_start()))
+            .collect::<String>()
+    };
+    let children = |parent: usize| {
+        entries
+            .iter()
+            .filter(move |entry| entry.parent == Some(parent))
+    };
+
+    for entry in &entries {
+        let value = &contents[entry.value.clone()];
+        match entry.keys.as_slice() {
+            ["version"] => {
+                replaced.insert(
+                    entry.index,
+                    with_value(entry, &format!("{:?}", CURRENT_VERSION)),
+                );
+            }
+            [.., "formatting", "style", "quotes"] => {
+                let Some(style) = entries
+                    .iter()
+                    .find(|style| Some(style.index) == entry.parent)
+                else {
+                    continue;
+                };
+                let Some(formatting) = style.parent else {
+                    continue;
+                };
+                let comments = comments_above(entry.index, style.column);
+                for line in entry.index - comments.lines().count()..=entry.index {
+                    replaced.insert(line, String::new());
+                }
+                if children(style.index).count() == 1 {
+                    replaced.insert(style.index, String::new());
+                }
+                match children(formatting)
+                    .find(|sibling| sibling.keys.last() == Some(&"quote_style"))
+                {
+                    Some(quote_style) => {
+                        replaced.insert(quote_style.index, with_value(quote_style, value));
+                        inserted
+                            .entry(quote_style.index)
+                            .or_default()
+                            .push_str(&comments);
+                    }
+                    None => inserted.entry(style.index).or_default().push_str(&format!(
+                        "{}{}quote_style: {}\n",
+                        comments,
+                        " ".repeat(style.column),
+                        value
+                    )),
+                }
+            }
+            [.., "formatting", "style", "hash_syntax"] => {
+                let spelling = value.trim_matches(|c| c == '"' || c == '\'');
+                if let Some(current) = current_hash_syntax(spelling) {
+                    let value = value.replace(spelling, current);
+                    replaced.insert(entry.index, with_value(entry, &value));
+                }
+            }
+            _ => {}
+        }
+    }
+    if !entries.iter().any(|entry| entry.keys == ["version"]) {
+        let first = entries.first().map_or(lines.len(), |entry| entry.index);
+        inserted
+            .entry(first)
+            .or_default()
+            .insert_str(0, &format!("version: {:?}\n\n", CURRENT_VERSION));
+    }
+
+    let mut output = String::with_capacity(contents.len());
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(text) = inserted.get(&index) {
+            output.push_str(text);
+        }
+        output.push_str(replaced.get(&index).map_or(line, String::as_str));
+    }
+    if let Some(text) = inserted.get(&lines.len()) {
+        if !output.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str(text.trim_end_matches('\n'));
+        output.push('\n');
+    }
+    output
+}
+
+/// `settings` as YAML, `version` first.
+fn serialize(mut settings: Value) -> crate::error::Result<String> {
+    if let Value::Mapping(mapping) = &mut settings {
+        if let Some(version) = mapping.shift_remove("version") {
+            let mut ordered = serde_yaml::Mapping::new();
+            ordered.insert("version".into(), version);
+            ordered.extend(std::mem::take(mapping));
+            *mapping = ordered;
+        }
+    }
+    serde_yaml::to_string(&settings).map_err(|e| RfmtError::config(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrade_maps_deprecated_settings() {
+        let mut settings: Value = serde_yaml::from_str(
+            "formatting:\n  quote_style: double\n  style:\n    quotes: single\n    hash_syntax: HashRockets\noverrides:\n  - files: [\"spec/**\"]\n    formatting:\n      style:\n        quotes: consistent\n",
+        )
+        .unwrap();
+        let messages: Vec<String> = upgrade(&mut settings)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "formatting.style.quotes is deprecated, use formatting.quote_style",
+                "formatting.style.hash_syntax: HashRockets is deprecated, use hash_rockets",
+                "overrides[0].formatting.style.quotes is deprecated, use overrides[0].formatting.quote_style",
+            ]
+        );
+        let expected: Value = serde_yaml::from_str(
+            "version: \"2\"\nformatting:\n  quote_style: single\n  style:\n    hash_syntax: hash_rockets\noverrides:\n  - files: [\"spec/**\"]\n    formatting:\n      quote_style: consistent\n",
+        )
+        .unwrap();
+        assert_eq!(settings, expected);
+    }
+
+    #[test]
+    fn test_upgrade_leaves_current_and_unknown_versions() {
+        for yaml in ["version: \"2\"\n", "version: \"3\"\n"] {
+            let mut settings: Value = serde_yaml::from_str(yaml).unwrap();
+            assert!(upgrade(&mut settings).is_empty());
+            assert_eq!(settings, serde_yaml::from_str::<Value>(yaml).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_migrate_keeps_comments_and_order() {
+        let source = "\
+# Project formatting
+version: \"1.0\"
+
+formatting:
+  line_length: 120 # wide screens
+  style:
+    # team preference
+    quotes: 'single'
+  indent_width: 2
+overrides:
+  - files: [\"spec/**\"]
+    formatting:
+      quote_style: double
+      style:
+        quotes: consistent
+        hash_syntax: \"Ruby19\"
+";
+        let migration = migrate(source).unwrap();
+        assert!(migration.comments_kept);
+        assert_eq!(migration.from_version.as_deref(), Some("1.0"));
+        assert_eq!(migration.deprecations.len(), 3);
+        assert_eq!(
+            migration.source,
+            "\
+# Project formatting
+version: \"2\"
+
+formatting:
+  line_length: 120 # wide screens
+  # team preference
+  quote_style: 'single'
+  indent_width: 2
+overrides:
+  - files: [\"spec/**\"]
+    formatting:
+      quote_style: consistent
+      style:
+        hash_syntax: \"ruby19\"
+"
+        );
+        assert_eq!(migrate(&migration.source).unwrap().source, migration.source);
+    }
+
+    #[test]
+    fn test_migrate_adds_the_version() {
+        let migration = migrate("# rfmt\nformatting:\n  line_length: 120\n").unwrap();
+        assert_eq!(
+            migration.source,
+            "# rfmt\nversion: \"2\"\n\nformatting:\n  line_length: 120\n"
+        );
+        assert!(migration.deprecations.is_empty());
+        assert_eq!(migrate("").unwrap().source, "");
+    }
+
+    #[test]
+    fn test_migrate_serializes_what_it_cannot_edit() {
+        let migration =
+            migrate("version: \"1.0\"\nformatting: { style: { quotes: single } }\n").unwrap();
+        assert!(!migration.comments_kept);
+        assert_eq!(
+            migration.source,
+            "version: '2'\nformatting:\n  quote_style: single\n"
+        );
+    }
+
+    #[test]
+    fn test_migrate_rejects_unknown_versions() {
+        assert_eq!(
+            migrate("version: \"3\"\n").unwrap_err().to_string(),
+            "version must be \"1.0\" or \"2\", got \"3\""
+        );
+    }
+}
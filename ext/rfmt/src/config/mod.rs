@@ -1,4 +1,9 @@
+pub mod import;
+pub mod migrate;
+pub mod schema;
+
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::SystemTime;
@@ -6,7 +11,7 @@ use std::time::SystemTime;
 /// Complete configuration structure matching .rfmt.yml format
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    #[serde(default)]
+    #[serde(default, deserialize_with = "scalar_string")]
     pub version: String,
 
     #[serde(default)]
@@ -15,20 +20,61 @@ pub struct Config {
     #[serde(default)]
     pub formatting: FormattingConfig,
 
+    #[serde(default)]
+    pub validation: ValidationConfig,
+
     #[serde(default)]
     pub include: Vec<String>,
 
     #[serde(default)]
     pub exclude: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<Override>,
+
+    /// Directory `overrides` patterns are relative to: that of the nearest
+    /// discovered configuration file. `None` for explicit and default
+    /// configurations, whose patterns are relative to the working directory.
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
+}
+
+/// Formatting for the files matching `files`, merged onto the base
+/// `formatting` section by [`Config::for_file`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Override {
+    pub files: Vec<String>,
+
+    /// Only the keys given here replace the base values.
+    #[serde(default)]
+    pub formatting: serde_yaml::Mapping,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ParserConfig {
+    #[serde(deserialize_with = "scalar_string")]
     pub version: String,
+    /// Format around top-level statements that do not parse, copying them
+    /// verbatim, instead of failing (`rfmt::format_source_tolerant`).
     pub error_tolerance: bool,
+    #[serde(deserialize_with = "scalar_string")]
     pub encoding: String,
 }
 
+/// Checks run on the formatted output, beyond the parse check that always
+/// runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    /// Parse the output again and fail unless its tree matches the input's
+    /// (`validation::verify_equivalence`).
+    pub ast_equivalence: bool,
+    /// Format the output a second time and fail unless nothing changes
+    /// (`Formatter::with_idempotency_check`).
+    pub idempotency: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FormattingConfig {
     #[serde(default = "default_line_length")]
@@ -47,6 +93,25 @@ pub struct FormattingConfig {
     pub style: StyleConfig,
 }
 
+/// A string setting written as any scalar, so that unquoted `version: 2`
+/// or `version: 3.3` reads the same from merged settings
+/// (`serde_yaml::from_value`) as from the file text.
+fn scalar_string<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::String(text) => Ok(text),
+        serde_yaml::Value::Number(number) => Ok(number.to_string()),
+        serde_yaml::Value::Bool(flag) => Ok(flag.to_string()),
+        other => Err(serde::de::Error::invalid_type(
+            match other {
+                serde_yaml::Value::Null => serde::de::Unexpected::Unit,
+                serde_yaml::Value::Sequence(_) => serde::de::Unexpected::Seq,
+                _ => serde::de::Unexpected::Map,
+            },
+            &"a string",
+        )),
+    }
+}
+
 fn default_line_length() -> usize {
     100
 }
@@ -65,9 +130,6 @@ pub enum IndentStyle {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StyleConfig {
-    #[serde(default)]
-    pub quotes: QuoteStyle,
-
     #[serde(default)]
     pub hash_syntax: HashSyntax,
 
@@ -75,7 +137,7 @@ pub struct StyleConfig {
     pub trailing_comma: TrailingComma,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum QuoteStyle {
     #[default]
@@ -84,11 +146,18 @@ pub enum QuoteStyle {
     Consistent,
 }
 
+/// Spelled `ruby19` / `hash_rockets` / `consistent`; the variant names
+/// (`Ruby19`, ...) are still accepted, and deprecated since version 2 (see
+/// [`migrate`]).
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum HashSyntax {
     #[default]
+    #[serde(alias = "Ruby19")]
     Ruby19,
+    #[serde(alias = "HashRockets")]
     HashRockets,
+    #[serde(alias = "Consistent")]
     Consistent,
 }
 
@@ -102,22 +171,33 @@ pub enum TrailingComma {
 }
 
 /// Search order within each directory: rfmt.yml, rfmt.yaml, .rfmt.yml, .rfmt.yaml
-const CONFIG_FILE_NAMES: [&str; 4] = ["rfmt.yml", "rfmt.yaml", ".rfmt.yml", ".rfmt.yaml"];
+pub const CONFIG_FILE_NAMES: [&str; 4] = ["rfmt.yml", "rfmt.yaml", ".rfmt.yml", ".rfmt.yaml"];
 
-/// Discovery result cached per process so repeated format calls (CLI batch,
-/// long-lived LSP) skip the cwd-to-root-to-home filesystem walk.
+/// Files settings are imported from when no directory has an rfmt file.
+pub const IMPORTED_FILE_NAMES: [&str; 2] = [".editorconfig", ".rubocop.yml"];
+
+/// Discovery results cached per process, keyed by the directory discovery
+/// started from, so repeated format calls (CLI batch, long-lived LSP) skip
+/// the directory-to-root-to-home filesystem walk.
 struct DiscoveryCache {
-    cwd: PathBuf,
-    /// Discovered file and its mtime; `None` when nothing was found.
-    found: Option<(PathBuf, SystemTime)>,
+    /// Every file the configuration was read from, with its mtime; empty
+    /// when nothing was found.
+    files: Vec<(PathBuf, SystemTime)>,
     config: Config,
 }
 
-static DISCOVERY_CACHE: Mutex<Option<DiscoveryCache>> = Mutex::new(None);
+static DISCOVERY_CACHE: Mutex<BTreeMap<PathBuf, DiscoveryCache>> = Mutex::new(BTreeMap::new());
 
-/// Explicit-path loads cached separately, keyed by canonical path + mtime,
-/// so a CLI batch does not re-parse the same YAML once per file.
-static EXPLICIT_CACHE: Mutex<Option<(PathBuf, SystemTime, Config)>> = Mutex::new(None);
+/// Explicit-path loads cached separately, keyed by canonical path and the
+/// mtimes of the files read (it and its `inherit_from` files), so a CLI batch
+/// does not re-parse the same YAML once per file.
+static EXPLICIT_CACHE: Mutex<Option<ExplicitCache>> = Mutex::new(None);
+
+struct ExplicitCache {
+    path: PathBuf,
+    files: Vec<(PathBuf, SystemTime)>,
+    config: Config,
+}
 
 impl Config {
     /// Resolve the effective configuration.
@@ -126,129 +206,301 @@ impl Config {
     /// name, so load failures must surface loudly; discovery merely stumbles
     /// on files, so a broken discovered file logs a warning and falls back
     /// to defaults (an LSP mid-edit of .rfmt.yml must not break formatting).
-    pub fn resolve(explicit_path: Option<&Path>) -> crate::error::Result<Self> {
-        match explicit_path {
-            Some(path) => Self::load_explicit_cached(path),
-            None => {
-                let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-                Ok(Self::discover_cached_from(&cwd))
+    ///
+    /// With a `target` file, discovery starts from the file's directory
+    /// instead of the cwd, and the overrides matching the file (relative to
+    /// [`Config::base_dir`]) are applied, see [`Config::for_file`].
+    pub fn resolve(
+        explicit_path: Option<&Path>,
+        target: Option<&Path>,
+    ) -> crate::error::Result<Self> {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let target = target.map(|target| absolute_target(target, &cwd));
+        let config = match (explicit_path, target.as_deref().and_then(Path::parent)) {
+            (Some(path), _) => Self::load_explicit_cached(path)?,
+            (None, Some(dir)) => Self::discover_cached_from(dir),
+            (None, None) => Self::discover_cached_from(&cwd),
+        };
+        Ok(match target {
+            Some(target) => {
+                let base_dir = config.base_dir.as_deref().unwrap_or(&cwd);
+                config.for_file(relative_to(&target, base_dir))
+            }
+            None => config,
+        })
+    }
+
+    /// The configuration for the file at `path`: the `formatting` of each
+    /// override whose `files` match, merged onto the base in order so later
+    /// entries win. `path` is matched as given, like [`PathFilter`]. The
+    /// result has no overrides left.
+    pub fn for_file(&self, path: &Path) -> Self {
+        let mut config = Self {
+            overrides: Vec::new(),
+            ..self.clone()
+        };
+        for entry in &self.overrides {
+            if !glob_set(&entry.files).is_match(path) {
+                continue;
+            }
+            match merge_formatting(&config.formatting, &entry.formatting) {
+                Ok(formatting) => config.formatting = formatting,
+                Err(e) => log::warn!("Ignoring override for {:?}: {}", entry.files, e),
             }
         }
+        config
+    }
+
+    /// Discover the configuration for files in `start`, walking up from it
+    /// like [`Config::resolve`] does, but bypassing the process-wide cache:
+    /// long-lived callers (the language server) keep their own
+    /// per-directory results and drop them when a config changes.
+    pub fn discover(start: &Path) -> Self {
+        Self::discover_from(start).0
+    }
+
+    /// Like [`Config::discover`], also returning every file the
+    /// configuration was read from: the rfmt files found with the files
+    /// they `inherit_from`, or the imported `.editorconfig` / `.rubocop.yml`.
+    pub fn discover_reading(start: &Path) -> (Self, Vec<PathBuf>) {
+        let (config, files) = Self::discover_from(start);
+        (config, files.into_iter().map(|(path, _)| path).collect())
     }
 
     fn load_explicit_cached(path: &Path) -> crate::error::Result<Self> {
         // Canonicalize so a relative path is not confused across cwd changes.
-        let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
-        // mtime read before the load: a racing write can only make the cache
-        // entry look older than the content, forcing a reload, never staleness.
-        let mtime = file_mtime(&key);
+        let key = canonical(path);
 
         let mut guard = EXPLICIT_CACHE
             .lock()
             .unwrap_or_else(|poisoned| poisoned.into_inner());
 
-        if let (Some((cached_path, cached_mtime, config)), Some(mtime)) = (guard.as_ref(), mtime) {
-            if *cached_path == key && *cached_mtime == mtime {
-                return Ok(config.clone());
+        if let Some(cache) = guard.as_ref() {
+            let fresh = cache
+                .files
+                .iter()
+                .all(|(file, mtime)| file_mtime(file) == Some(*mtime));
+            if cache.path == key && fresh {
+                return Ok(cache.config.clone());
             }
         }
 
-        let config = Self::load_file(path)?;
-        if let Some(mtime) = mtime {
-            *guard = Some((key, mtime, config.clone()));
-        }
+        let mut read = Vec::new();
+        let config = Self::load_file_reading(path, &mut read)?;
+        let files: Option<Vec<_>> = read
+            .into_iter()
+            .map(|(file, mtime)| mtime.map(|mtime| (file, mtime)))
+            .collect();
+        let Some(files) = files else {
+            return Ok(config);
+        };
+        *guard = Some(ExplicitCache {
+            path: key,
+            files,
+            config: config.clone(),
+        });
         Ok(config)
     }
 
-    fn discover_cached_from(cwd: &Path) -> Self {
-        let mut guard = DISCOVERY_CACHE
+    fn discover_cached_from(start: &Path) -> Self {
+        let mut cache = DISCOVERY_CACHE
             .lock()
             .unwrap_or_else(|poisoned| poisoned.into_inner());
 
-        if let Some(cache) = guard.as_ref() {
-            if cache.cwd == cwd && cache_is_fresh(cwd, cache) {
-                return cache.config.clone();
+        if let Some(entry) = cache.get(start) {
+            if cache_is_fresh(start, entry) {
+                return entry.config.clone();
             }
         }
 
-        let (config, found) = Self::discover_from(cwd);
-        *guard = Some(DiscoveryCache {
-            cwd: cwd.to_path_buf(),
-            found,
-            config: config.clone(),
-        });
+        let (config, files) = Self::discover_from(start);
+        cache.insert(
+            start.to_path_buf(),
+            DiscoveryCache {
+                files,
+                config: config.clone(),
+            },
+        );
         config
     }
 
-    /// Walk `start` up to the root, then the home directory.
-    fn find_config_file(start: &Path) -> Option<PathBuf> {
-        let mut current_dir = start.to_path_buf();
-        loop {
-            if let Some(path) = first_candidate_in(&current_dir) {
-                return Some(path);
+    fn discover_from(start: &Path) -> (Self, Vec<(PathBuf, SystemTime)>) {
+        let mut read = Vec::new();
+        let config = match Self::load_hierarchy(start, &mut read) {
+            Ok(Some(config)) => config,
+            Ok(None) => {
+                log::info!("No config file found, using defaults");
+                Config::default()
+            }
+            Err(e) => {
+                log::warn!("Ignoring config file: {}", e);
+                Config::default()
             }
-            if !current_dir.pop() {
+        };
+
+        // Broken files are still cached with their mtimes so fixing one triggers a reload.
+        let files = read
+            .into_iter()
+            .map(|(path, mtime)| (path, mtime.unwrap_or(SystemTime::UNIX_EPOCH)))
+            .collect();
+        (config, files)
+    }
+
+    /// Loads the configuration files from `start` up to the root: each
+    /// directory's file is layered over the ones above it, until a file sets
+    /// `root: true`. When no directory has one, settings are imported from
+    /// `.editorconfig` and `.rubocop.yml`, and failing that the home
+    /// directory's file is used. Every file read is added to `read` with its
+    /// mtime.
+    fn load_hierarchy(
+        start: &Path,
+        read: &mut Vec<(PathBuf, Option<SystemTime>)>,
+    ) -> crate::error::Result<Option<Self>> {
+        let home = dirs::home_dir();
+        let mut layers = Vec::new();
+        for dir in start.ancestors() {
+            if !layers.is_empty() && home.as_deref() == Some(dir) {
+                break;
+            }
+            let Some(path) = first_candidate_in(dir) else {
+                continue;
+            };
+            let layer = read_layer(&path, &mut vec![canonical(&path)], read)?;
+            let root = layer.root;
+            layers.push((path, layer.settings));
+            if root {
                 break;
             }
         }
+        if layers.is_empty() {
+            if let Some(config) = Self::import_from(start, read)? {
+                return Ok(Some(config));
+            }
+            if let Some(path) = home.as_deref().and_then(first_candidate_in) {
+                let layer = read_layer(&path, &mut vec![canonical(&path)], read)?;
+                layers.push((path, layer.settings));
+            }
+        }
+
+        let Some((nearest, _)) = layers.first() else {
+            return Ok(None);
+        };
+        let nearest = nearest.clone();
+        let mut settings = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+        for (_, layer) in layers.iter().rev() {
+            merge_mapping(&mut settings, layer);
+        }
 
-        dirs::home_dir().and_then(|home| first_candidate_in(&home))
+        log::info!("Found config file: {:?}", nearest);
+        let mut config = Self::from_settings(settings, &nearest)?;
+        config.base_dir = nearest.parent().map(Path::to_path_buf);
+        Ok(Some(config))
     }
 
-    fn discover_from(start: &Path) -> (Self, Option<(PathBuf, SystemTime)>) {
-        let Some(path) = Self::find_config_file(start) else {
-            log::info!("No config file found, using defaults");
-            return (Config::default(), None);
+    /// Settings imported from `.editorconfig` and `.rubocop.yml` (which wins
+    /// where both set an option) for a project without an rfmt config.
+    fn import_from(
+        start: &Path,
+        read: &mut Vec<(PathBuf, Option<SystemTime>)>,
+    ) -> crate::error::Result<Option<Self>> {
+        let mut settings = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+        let mut origin = None;
+        for source in [import::Source::Editorconfig, import::Source::Rubocop] {
+            if let Some(imported) = source.import_reading(start, read)? {
+                merge_mapping(&mut settings, &imported.settings);
+                origin = imported.files.into_iter().next();
+            }
+        }
+        let Some(origin) = origin else {
+            return Ok(None);
         };
-
-        // A broken file is still cached with its mtime so fixing it triggers a reload.
-        let config = Self::load_file(&path).unwrap_or_else(|e| {
-            log::warn!("Ignoring config file {:?}: {}", path, e);
-            Config::default()
-        });
-        let mtime = file_mtime(&path).unwrap_or(SystemTime::UNIX_EPOCH);
-        log::info!("Found config file: {:?}", path);
-        (config, Some((path, mtime)))
+        log::info!("No config file found, importing settings from {:?}", origin);
+        Self::from_settings(settings, &origin).map(Some)
     }
 
-    /// Load configuration from a YAML file
+    /// Load configuration from a YAML file, with the files it names in
+    /// `inherit_from` and `import` merged underneath
     pub fn load_file(path: &std::path::Path) -> crate::error::Result<Self> {
+        Self::load_file_reading(path, &mut Vec::new())
+    }
+
+    fn load_file_reading(
+        path: &Path,
+        read: &mut Vec<(PathBuf, Option<SystemTime>)>,
+    ) -> crate::error::Result<Self> {
+        let layer = read_layer(path, &mut vec![canonical(path)], read)?;
+        Self::from_settings(serde_yaml::Value::Mapping(layer.settings), path)
+    }
+
+    /// Deserializes and validates merged settings, reporting errors against
+    /// `path`.
+    fn from_settings(settings: serde_yaml::Value, path: &Path) -> crate::error::Result<Self> {
         use crate::error::RfmtError;
 
-        let contents = std::fs::read_to_string(path).map_err(|e| RfmtError::ConfigError {
-            message: format!("Failed to read config file: {}", e),
+        let config: Config = serde_yaml::from_value(settings).map_err(|e| {
+            RfmtError::config(format!("Failed to parse config file: {}", e)).with_file(path)
         })?;
 
-        let config: Config =
-            serde_yaml::from_str(&contents).map_err(|e| RfmtError::ConfigError {
-                message: format!("Failed to parse config file: {}", e),
-            })?;
-
-        config.validate()?;
+        config.validate().map_err(|e| e.with_file(path))?;
 
         Ok(config)
     }
 
     /// Validate configuration values
     fn validate(&self) -> crate::error::Result<()> {
-        use crate::error::RfmtError;
+        self.check()
+            .map_err(|invalid| crate::error::RfmtError::config(invalid.message))
+    }
 
-        if self.formatting.line_length < 40 || self.formatting.line_length > 500 {
-            return Err(RfmtError::ConfigError {
-                message: format!(
-                    "line_length must be between 40 and 500, got {}",
-                    self.formatting.line_length
+    fn check(&self) -> Result<(), Invalid> {
+        if !self.version.is_empty() && !migrate::VERSIONS.contains(&self.version.as_str()) {
+            return Err(Invalid::new(
+                &["version"],
+                migrate::unsupported_version(&self.version),
+            ));
+        }
+        if !is_ruby_version(&self.parser.version) {
+            return Err(Invalid::new(
+                &["parser", "version"],
+                format!(
+                    "parser.version must be \"latest\" or a Ruby version such as \"3.3\", got {:?}",
+                    self.parser.version
                 ),
-            });
+            ));
         }
-
-        if self.formatting.indent_width < 1 || self.formatting.indent_width > 8 {
-            return Err(RfmtError::ConfigError {
-                message: format!(
-                    "indent_width must be between 1 and 8, got {}",
-                    self.formatting.indent_width
+        if !self.parser.encoding.eq_ignore_ascii_case("UTF-8") {
+            return Err(Invalid::new(
+                &["parser", "encoding"],
+                format!(
+                    "parser.encoding must be \"UTF-8\", the only encoding rfmt reads, got {:?}",
+                    self.parser.encoding
                 ),
-            });
+            ));
+        }
+
+        validate_formatting(&self.formatting)
+            .map_err(|(key, message)| Invalid::new(&["formatting", key], message))?;
+
+        // Each override is checked merged onto the base, as it is applied.
+        for (index, entry) in self.overrides.iter().enumerate() {
+            let invalid = |keys: &[&'static str], message: String| {
+                let mut path = vec!["overrides"];
+                path.extend_from_slice(keys);
+                Invalid::new(&path, format!("overrides[{}]: {}", index, message))
+            };
+            if entry.files.is_empty() {
+                return Err(invalid(
+                    &["files"],
+                    "files must list at least one pattern".to_string(),
+                ));
+            }
+            for pattern in &entry.files {
+                globset::Glob::new(pattern).map_err(|e| invalid(&["files"], e.to_string()))?;
+            }
+            let formatting = merge_formatting(&self.formatting, &entry.formatting)
+                .map_err(|e| invalid(&["formatting"], schema::with_suggestion(e.to_string())))?;
+            validate_formatting(&formatting)
+                .map_err(|(key, message)| invalid(&["formatting", key], message))?;
         }
 
         Ok(())
@@ -264,39 +516,296 @@ impl Config {
     }
 
     /// Check if a file path should be included based on include/exclude patterns
-    #[cfg(test)]
     pub fn should_include(&self, path: &std::path::Path) -> bool {
-        use globset::{Glob, GlobSetBuilder};
+        self.path_filter().includes(path)
+    }
+
+    /// Compiles `include` and `exclude` once for checking many paths.
+    pub fn path_filter(&self) -> PathFilter {
+        PathFilter {
+            include: glob_set(&self.include),
+            exclude: glob_set(&self.exclude),
+        }
+    }
+}
 
-        let path_str = path.to_string_lossy();
+/// Compiled `include` / `exclude` patterns of a `Config`.
+///
+/// Paths are matched as given, so callers pass them relative to the
+/// directory the patterns are written for (`vendor/**/*` matches
+/// `vendor/gem/foo.rb`, not `/app/vendor/gem/foo.rb`). Invalid patterns
+/// match nothing.
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    include: globset::GlobSet,
+    exclude: globset::GlobSet,
+}
 
-        // Check exclude patterns first
-        let mut exclude_builder = GlobSetBuilder::new();
-        for pattern in &self.exclude {
-            if let Ok(glob) = Glob::new(pattern) {
-                exclude_builder.add(glob);
-            }
+impl PathFilter {
+    /// Matches an `include` pattern and no `exclude` pattern.
+    pub fn includes(&self, path: &Path) -> bool {
+        !self.excludes(path) && self.include.is_match(path)
+    }
+
+    /// Matches an `exclude` pattern.
+    pub fn excludes(&self, path: &Path) -> bool {
+        self.exclude.is_match(path)
+    }
+}
+
+fn glob_set(patterns: &[String]) -> globset::GlobSet {
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        if let Ok(glob) = globset::Glob::new(pattern) {
+            builder.add(glob);
         }
+    }
+    builder
+        .build()
+        .unwrap_or_else(|_| globset::GlobSet::empty())
+}
 
-        if let Ok(exclude_set) = exclude_builder.build() {
-            if exclude_set.is_match(&*path_str) {
-                return false;
-            }
+/// Range checks, failing with the offending key.
+fn validate_formatting(formatting: &FormattingConfig) -> Result<(), (&'static str, String)> {
+    if formatting.line_length < 40 || formatting.line_length > 500 {
+        return Err((
+            "line_length",
+            format!(
+                "line_length must be between 40 and 500, got {}",
+                formatting.line_length
+            ),
+        ));
+    }
+
+    if formatting.indent_width < 1 || formatting.indent_width > 8 {
+        return Err((
+            "indent_width",
+            format!(
+                "indent_width must be between 1 and 8, got {}",
+                formatting.indent_width
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// `"latest"` or a `major.minor[.patch]` version number.
+fn is_ruby_version(version: &str) -> bool {
+    if version == "latest" {
+        return true;
+    }
+    let parts: Vec<&str> = version.split('.').collect();
+    (2..=3).contains(&parts.len())
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// A rejected setting: why, and the keys leading to it for locating it in
+/// the file.
+struct Invalid {
+    keys: Vec<&'static str>,
+    message: String,
+}
+
+impl Invalid {
+    fn new(keys: &[&'static str], message: String) -> Self {
+        Self {
+            keys: keys.to_vec(),
+            message,
         }
+    }
+}
 
-        // Check include patterns
-        let mut include_builder = GlobSetBuilder::new();
-        for pattern in &self.include {
-            if let Ok(glob) = Glob::new(pattern) {
-                include_builder.add(glob);
+/// `base` with the keys of `changes` replaced, nested mappings (`style`)
+/// merged key by key.
+fn merge_formatting(
+    base: &FormattingConfig,
+    changes: &serde_yaml::Mapping,
+) -> Result<FormattingConfig, serde_yaml::Error> {
+    let mut merged = serde_yaml::to_value(base)?;
+    merge_mapping(&mut merged, changes);
+    serde_yaml::from_value(merged)
+}
+
+fn merge_mapping(target: &mut serde_yaml::Value, changes: &serde_yaml::Mapping) {
+    let serde_yaml::Value::Mapping(target) = target else {
+        return;
+    };
+    for (key, value) in changes {
+        match (target.get_mut(key), value) {
+            (
+                Some(existing @ serde_yaml::Value::Mapping(_)),
+                serde_yaml::Value::Mapping(nested),
+            ) => merge_mapping(existing, nested),
+            _ => {
+                target.insert(key.clone(), value.clone());
             }
         }
+    }
+}
+
+/// `path` as the override patterns see it: relative to `dir` when inside
+/// it, without a leading `./`.
+fn relative_to<'a>(path: &'a Path, dir: &Path) -> &'a Path {
+    let path = path.strip_prefix(dir).unwrap_or(path);
+    path.strip_prefix(".").unwrap_or(path)
+}
 
-        if let Ok(include_set) = include_builder.build() {
-            return include_set.is_match(&*path_str);
+/// A configuration file's settings, with its `inherit_from` files and
+/// `import` sources merged underneath. `root`, `inherit_from` and `import`
+/// are removed.
+struct Layer {
+    settings: serde_yaml::Mapping,
+    root: bool,
+}
+
+/// Reads `path` and, recursively, the files it inherits from. `chain` holds
+/// the canonical paths being read to catch a file inheriting from itself.
+fn read_layer(
+    path: &Path,
+    chain: &mut Vec<PathBuf>,
+    read: &mut Vec<(PathBuf, Option<SystemTime>)>,
+) -> crate::error::Result<Layer> {
+    use crate::error::{RfmtError, Span};
+    use crate::line_index::LineIndex;
+
+    // mtime read before the file: a racing write can only make a cache entry
+    // look older than the content, forcing a reload, never staleness.
+    read.push((path.to_path_buf(), file_mtime(path)));
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        RfmtError::config(format!("Failed to read config file: {}", e)).with_file(path)
+    })?;
+
+    let parse_error = |e: serde_yaml::Error| {
+        let span = e.location().map(|location| {
+            let index = LineIndex::new(contents.as_bytes());
+            Span::new(&index, location.index()..location.index())
+        });
+        RfmtError::ConfigError {
+            message: schema::with_suggestion(format!("Failed to parse config file: {}", e)),
+            file: Some(path.to_path_buf()),
+            span,
+        }
+    };
+    let located = |message: String, keys: &[&str]| {
+        let span = schema::locate(&contents, keys).map(|offset| {
+            let index = LineIndex::new(contents.as_bytes());
+            let key = keys.last().map_or(0, |key| key.len());
+            Span::new(&index, offset..offset + key)
+        });
+        RfmtError::ConfigError {
+            message,
+            file: Some(path.to_path_buf()),
+            span,
         }
+    };
+    let mut value = serde_yaml::from_str::<serde_yaml::Value>(&contents).map_err(parse_error)?;
+    let current = migrate::version_of(&value).as_deref() == Some(migrate::CURRENT_VERSION);
+    for deprecation in migrate::upgrade(&mut value) {
+        if current {
+            return Err(located(
+                format!(
+                    "{} (version {} no longer accepts it)",
+                    deprecation,
+                    migrate::CURRENT_VERSION
+                ),
+                &deprecation.keys,
+            ));
+        }
+        log::warn!(
+            "{}: {}; `rfmt migrate-config` rewrites the file",
+            path.display(),
+            deprecation
+        );
+    }
+    if let Some(unknown) = schema::unknown_key(&value) {
+        let keys: Vec<&str> = unknown.keys.iter().map(String::as_str).collect();
+        return Err(located(unknown.to_string(), &keys));
+    }
+    // Deserialized and validated on its own first so a wrong value is
+    // reported at its line in this file rather than after merging.
+    serde_yaml::from_str::<Config>(&contents)
+        .map_err(parse_error)?
+        .check()
+        .map_err(|invalid| located(invalid.message, &invalid.keys))?;
+    let mut settings = match value {
+        serde_yaml::Value::Mapping(settings) => settings,
+        _ => serde_yaml::Mapping::new(),
+    };
+
+    let invalid = |message: &str| RfmtError::config(message).with_file(path);
+    let root = match settings.remove("root") {
+        None => false,
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| invalid("root must be true or false"))?,
+    };
+    let bases = string_list(settings.remove("inherit_from"))
+        .ok_or_else(|| invalid("inherit_from must be a path or a list of paths"))?;
+    let imports = string_list(settings.remove("import"))
+        .ok_or_else(|| invalid("import must be a name or a list of names"))?
+        .iter()
+        .map(|name| name.parse::<import::Source>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|message| invalid(&format!("import: {}", message)))?;
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut merged = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+    for base in bases {
+        let base_path = dir.join(&base);
+        let key = canonical(&base_path);
+        if chain.contains(&key) {
+            return Err(invalid(&format!(
+                "inherit_from {:?} inherits from itself",
+                base
+            )));
+        }
+        chain.push(key);
+        let layer = read_layer(&base_path, chain, read)?;
+        chain.pop();
+        merge_mapping(&mut merged, &layer.settings);
+    }
+    for source in imports {
+        match source.import_reading(dir, read)? {
+            Some(imported) => merge_mapping(&mut merged, &imported.settings),
+            None => log::warn!("Nothing to import from {:?} for {:?}", source, path),
+        }
+    }
+    merge_mapping(&mut merged, &settings);
 
-        false
+    let settings = match merged {
+        serde_yaml::Value::Mapping(settings) => settings,
+        _ => serde_yaml::Mapping::new(),
+    };
+    Ok(Layer { settings, root })
+}
+
+/// A string or a list of strings; `None` for anything else.
+fn string_list(value: Option<serde_yaml::Value>) -> Option<Vec<String>> {
+    match value {
+        None => Some(Vec::new()),
+        Some(serde_yaml::Value::String(value)) => Some(vec![value]),
+        Some(serde_yaml::Value::Sequence(values)) => values
+            .into_iter()
+            .map(|value| value.as_str().map(str::to_string))
+            .collect(),
+        Some(_) => None,
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// `target` made absolute against `cwd`, with its directory canonicalized
+/// when it exists so it lines up with discovered configuration paths.
+fn absolute_target(target: &Path, cwd: &Path) -> PathBuf {
+    let path = cwd.join(target);
+    match (path.parent().map(canonical), path.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => path,
     }
 }
 
@@ -311,36 +820,39 @@ fn file_mtime(path: &Path) -> Option<SystemTime> {
     std::fs::metadata(path).and_then(|m| m.modified()).ok()
 }
 
-/// Cheap per-call re-validation replacing the full walk: stat the cwd
-/// candidates (catches a config newly created where formatting runs) and the
-/// discovered file's mtime (catches edits; a vanished file forces a re-walk).
-/// A config newly created outside cwd (parent directory or home) is not
-/// detected until something else invalidates the cache.
-fn cache_is_fresh(cwd: &Path, cache: &DiscoveryCache) -> bool {
-    let cwd_candidate = first_candidate_in(cwd);
-    match &cache.found {
-        Some((path, mtime)) => {
-            if cwd_candidate.is_some_and(|candidate| candidate != *path) {
-                return false;
-            }
-            file_mtime(path) == Some(*mtime)
-        }
-        None => cwd_candidate.is_none(),
+/// Cheap per-call re-validation replacing the full walk: stat the start
+/// directory's candidates (catches a config newly created where formatting
+/// runs) and the mtimes of the files read (catches edits; a vanished file
+/// forces a re-walk). A config newly created above the start directory (a
+/// parent directory or home) is not detected until something else
+/// invalidates the cache.
+fn cache_is_fresh(start: &Path, cache: &DiscoveryCache) -> bool {
+    if first_candidate_in(start)
+        .is_some_and(|candidate| !cache.files.iter().any(|(path, _)| *path == candidate))
+    {
+        return false;
     }
+    cache
+        .files
+        .iter()
+        .all(|(path, mtime)| file_mtime(path) == Some(*mtime))
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            version: "1.0".to_string(),
+            version: migrate::CURRENT_VERSION.to_string(),
             parser: ParserConfig::default(),
             formatting: FormattingConfig::default(),
+            validation: ValidationConfig::default(),
             include: vec!["**/*.rb".to_string(), "**/*.rake".to_string()],
             exclude: vec![
                 "vendor/**/*".to_string(),
                 "tmp/**/*".to_string(),
                 "node_modules/**/*".to_string(),
             ],
+            overrides: Vec::new(),
+            base_dir: None,
         }
     }
 }
@@ -349,7 +861,7 @@ impl Default for ParserConfig {
     fn default() -> Self {
         Self {
             version: "latest".to_string(),
-            error_tolerance: true,
+            error_tolerance: false,
             encoding: "UTF-8".to_string(),
         }
     }
@@ -370,7 +882,6 @@ impl Default for FormattingConfig {
 impl Default for StyleConfig {
     fn default() -> Self {
         Self {
-            quotes: QuoteStyle::Double,
             hash_syntax: HashSyntax::Ruby19,
             trailing_comma: TrailingComma::Multiline,
         }
@@ -388,7 +899,7 @@ mod tests {
     #[test]
     fn test_default_config() {
         let config = Config::default();
-        assert_eq!(config.version, "1.0");
+        assert_eq!(config.version, migrate::CURRENT_VERSION);
         assert_eq!(config.formatting.line_length, 100);
         assert_eq!(config.formatting.indent_width, 2);
         assert!(matches!(
@@ -423,6 +934,61 @@ exclude:
         assert!(matches!(config.formatting.quote_style, QuoteStyle::Single));
     }
 
+    #[test]
+    fn test_style_quotes_overrides_quote_style() {
+        let yaml = r#"
+formatting:
+  quote_style: single
+  style:
+    quotes: consistent
+"#;
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+        file.flush().unwrap();
+
+        // Read as version 1.0, where style.quotes wins
+        let config = Config::load_file(file.path()).unwrap();
+        assert_eq!(config.formatting.quote_style, QuoteStyle::Consistent);
+        assert_eq!(Config::default().formatting.quote_style, QuoteStyle::Double);
+    }
+
+    #[test]
+    fn test_hash_syntax_spellings() {
+        for (spelling, expected) in [
+            ("ruby19", "Ruby19"),
+            ("hash_rockets", "HashRockets"),
+            ("HashRockets", "HashRockets"),
+            ("consistent", "Consistent"),
+        ] {
+            let yaml = format!("formatting:\n  style:\n    hash_syntax: {}\n", spelling);
+            let config: Config = serde_yaml::from_str(&yaml).unwrap();
+            assert_eq!(
+                format!("{:?}", config.formatting.style.hash_syntax),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_error_tolerance_is_opt_in() {
+        assert!(!Config::default().parser.error_tolerance);
+
+        let config: Config = serde_yaml::from_str("parser:\n  error_tolerance: true\n").unwrap();
+        assert!(config.parser.error_tolerance);
+        assert_eq!(config.parser.version, "latest");
+    }
+
+    #[test]
+    fn test_ast_equivalence_is_opt_in() {
+        assert!(!Config::default().validation.ast_equivalence);
+
+        let config: Config =
+            serde_yaml::from_str("validation:\n  ast_equivalence: true\n").unwrap();
+        assert!(config.validation.ast_equivalence);
+        assert!(!config.validation.idempotency);
+    }
+
     #[test]
     fn test_validate_line_length_too_small() {
         let yaml = r#"
@@ -502,6 +1068,120 @@ formatting:
         assert!(!config.should_include(Path::new("test/foo.rb")));
     }
 
+    #[test]
+    fn test_path_filter_matches_like_should_include() {
+        let config = Config {
+            include: vec!["**/*.rb".to_string(), "[invalid".to_string()],
+            ..Config::default()
+        };
+        let filter = config.path_filter();
+        assert!(filter.includes(Path::new("app/models/user.rb")));
+        assert!(!filter.includes(Path::new("app/models/user.erb.html")));
+        assert!(filter.excludes(Path::new("vendor/bundle")));
+        assert!(!filter.includes(Path::new("vendor/bundle/foo.rb")));
+    }
+
+    #[test]
+    fn test_overrides_apply_to_matching_files() {
+        let yaml = r#"
+formatting:
+  line_length: 80
+  style:
+    trailing_comma: never
+overrides:
+  - files: ["spec/**"]
+    formatting:
+      line_length: 120
+  - files: ["db/migrate/**", "spec/fixtures/**"]
+    formatting:
+      quote_style: single
+      style:
+        hash_syntax: hash_rockets
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.validate().unwrap();
+
+        let spec = config.for_file(Path::new("spec/models/user_spec.rb"));
+        assert_eq!(spec.formatting.line_length, 120);
+        assert_eq!(spec.formatting.quote_style, QuoteStyle::Double);
+        assert!(spec.overrides.is_empty());
+
+        let migration = config.for_file(Path::new("db/migrate/001_create_users.rb"));
+        assert_eq!(migration.formatting.line_length, 80);
+        assert_eq!(migration.formatting.quote_style, QuoteStyle::Single);
+        assert!(matches!(
+            migration.formatting.style.hash_syntax,
+            HashSyntax::HashRockets
+        ));
+        assert!(matches!(
+            migration.formatting.style.trailing_comma,
+            TrailingComma::Never
+        ));
+
+        // Later entries win where both match.
+        let fixture = config.for_file(Path::new("spec/fixtures/a.rb"));
+        assert_eq!(fixture.formatting.line_length, 120);
+        assert_eq!(fixture.formatting.quote_style, QuoteStyle::Single);
+
+        let model = config.for_file(Path::new("app/models/user.rb"));
+        assert_eq!(model.formatting.line_length, 80);
+    }
+
+    #[test]
+    fn test_overrides_validated_as_merged() {
+        for (yaml, expected) in [
+            (
+                "overrides:\n  - files: [\"spec/**\"]\n    formatting:\n      line_length: 10\n",
+                "overrides[0]: line_length must be between 40 and 500, got 10",
+            ),
+            (
+                "overrides:\n  - files: [\"[spec\"]\n    formatting: {}\n",
+                "overrides[0]: ",
+            ),
+            (
+                "overrides:\n  - files: []\n    formatting:\n      line_length: 80\n",
+                "overrides[0]: files must list at least one pattern",
+            ),
+            (
+                "overrides:\n  - files: [\"spec/**\"]\n    formatting:\n      indent_style: wide\n",
+                "overrides[0]: ",
+            ),
+        ] {
+            let config: Config = serde_yaml::from_str(yaml).unwrap();
+            match config.validate() {
+                Err(RfmtError::ConfigError { message, .. }) => {
+                    assert!(message.starts_with(expected), "{}", message)
+                }
+                other => panic!("expected a config error for {:?}, got {:?}", yaml, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_applies_overrides_for_target() {
+        let _lock = CACHE_TEST_LOCK.lock().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.yml");
+        std::fs::write(
+            &path,
+            "overrides:\n  - files: [\"spec/**\"]\n    formatting:\n      line_length: 120\n",
+        )
+        .unwrap();
+
+        let base = Config::resolve(Some(&path), None).unwrap();
+        assert_eq!(base.formatting.line_length, 100);
+        assert_eq!(base.overrides.len(), 1);
+
+        for target in ["spec/a_spec.rb", "./spec/a_spec.rb"] {
+            let config = Config::resolve(Some(&path), Some(Path::new(target))).unwrap();
+            assert_eq!(config.formatting.line_length, 120, "{}", target);
+        }
+        let cwd = std::env::current_dir().unwrap();
+        let absolute = cwd.join("spec/a_spec.rb");
+        let config = Config::resolve(Some(&path), Some(&absolute)).unwrap();
+        assert_eq!(config.formatting.line_length, 120);
+    }
+
     #[test]
     fn test_invalid_yaml_syntax() {
         let yaml = r#"
@@ -515,11 +1195,110 @@ formatting:
 
         let result = Config::load_file(file.path());
         assert!(result.is_err());
-        if let Err(RfmtError::ConfigError { message, .. }) = result {
+        if let Err(RfmtError::ConfigError {
+            message,
+            file: error_file,
+            span,
+        }) = result
+        {
             assert!(message.contains("parse"));
+            assert_eq!(error_file.as_deref(), Some(file.path()));
+            assert_eq!(span.map(|span| span.line), Some(3));
         }
     }
 
+    fn load_error(yaml: &str) -> (String, Option<usize>) {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(yaml.as_bytes()).unwrap();
+        file.flush().unwrap();
+
+        match Config::load_file(file.path()) {
+            Err(RfmtError::ConfigError { message, span, .. }) => {
+                (message, span.map(|span| span.line))
+            }
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_keys_are_located() {
+        assert_eq!(
+            load_error("version: \"1.0\"\nformatting:\n  line_lenght: 80\n"),
+            (
+                "unknown key \"line_lenght\" in formatting, did you mean \"line_length\"?"
+                    .to_string(),
+                Some(3)
+            )
+        );
+        assert_eq!(
+            load_error(
+                "overrides:\n  - files: [\"spec/**\"]\n    formating:\n      line_length: 120\n"
+            )
+            .1,
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_version_and_parser_values_are_validated() {
+        let (message, line) = load_error("version: \"2.0\"\n");
+        assert_eq!(message, "version must be \"1.0\" or \"2\", got \"2.0\"");
+        assert_eq!(line, Some(1));
+
+        let (message, line) = load_error("parser:\n  version: \"three\"\n");
+        assert!(message.starts_with("parser.version must be"), "{}", message);
+        assert_eq!(line, Some(2));
+
+        let (message, line) = load_error("parser:\n  encoding: Shift_JIS\n");
+        assert!(
+            message.starts_with("parser.encoding must be"),
+            "{}",
+            message
+        );
+        assert_eq!(line, Some(2));
+
+        let (message, line) =
+            load_error("overrides:\n  - files: [\"a\"]\n    formatting:\n      indent_width: 9\n");
+        assert!(
+            message.starts_with("overrides[0]: indent_width"),
+            "{}",
+            message
+        );
+        assert_eq!(line, Some(4));
+
+        let (message, _) = load_error("formatting:\n  quote_style: singel\n");
+        assert!(message.ends_with("did you mean \"single\"?"), "{}", message);
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"parser:\n  version: \"3.3.0\"\n  encoding: utf-8\n")
+            .unwrap();
+        file.flush().unwrap();
+        assert!(Config::load_file(file.path()).is_ok());
+    }
+
+    #[test]
+    fn test_deprecated_settings_depend_on_version() {
+        let settings = "formatting:\n  style:\n    quotes: single\n    hash_syntax: HashRockets\n";
+
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "version: \"1.0\"\n{}", settings).unwrap();
+        file.flush().unwrap();
+        let config = Config::load_file(file.path()).unwrap();
+        assert_eq!(config.version, migrate::CURRENT_VERSION);
+        assert_eq!(config.formatting.quote_style, QuoteStyle::Single);
+        assert!(matches!(
+            config.formatting.style.hash_syntax,
+            HashSyntax::HashRockets
+        ));
+
+        let (message, line) = load_error(&format!("version: \"2\"\n{}", settings));
+        assert_eq!(
+            message,
+            "formatting.style.quotes is deprecated, use formatting.quote_style (version 2 no longer accepts it)"
+        );
+        assert_eq!(line, Some(4));
+    }
+
     // The discovery cache is process-global; serialize the tests that touch it.
     static CACHE_TEST_LOCK: Mutex<()> = Mutex::new(());
 
@@ -533,7 +1312,7 @@ formatting:
 
     #[test]
     fn test_resolve_explicit_missing_path_errors_loudly() {
-        let result = Config::resolve(Some(Path::new("/nonexistent/rfmt.yml")));
+        let result = Config::resolve(Some(Path::new("/nonexistent/rfmt.yml")), None);
         assert!(result.is_err());
     }
 
@@ -544,7 +1323,7 @@ formatting:
             .unwrap();
         file.flush().unwrap();
 
-        assert!(Config::resolve(Some(file.path())).is_err());
+        assert!(Config::resolve(Some(file.path()), None).is_err());
     }
 
     #[test]
@@ -555,7 +1334,7 @@ formatting:
 
         write_indent_config(&path, 4);
         assert_eq!(
-            Config::resolve(Some(&path))
+            Config::resolve(Some(&path), None)
                 .unwrap()
                 .formatting
                 .indent_width,
@@ -565,7 +1344,7 @@ formatting:
         std::thread::sleep(std::time::Duration::from_millis(20));
         write_indent_config(&path, 3);
         assert_eq!(
-            Config::resolve(Some(&path))
+            Config::resolve(Some(&path), None)
                 .unwrap()
                 .formatting
                 .indent_width,
@@ -588,6 +1367,22 @@ formatting:
         assert_eq!(config.formatting.indent_width, 2);
     }
 
+    #[test]
+    fn test_discovery_reads_unquoted_versions() {
+        let _lock = CACHE_TEST_LOCK.lock().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(".rfmt.yml"),
+            "version: 2\nparser:\n  version: 3.3\nformatting:\n  line_length: 80\n",
+        )
+        .unwrap();
+
+        let config = Config::discover_cached_from(dir.path());
+        assert_eq!(config.version, "2");
+        assert_eq!(config.parser.version, "3.3");
+        assert_eq!(config.formatting.line_length, 80);
+    }
+
     #[test]
     fn test_discovery_cache_reloads_on_mtime_change() {
         let _lock = CACHE_TEST_LOCK.lock().unwrap();
@@ -654,6 +1449,167 @@ formatting:
         );
     }
 
+    #[test]
+    fn test_discovery_layers_parent_configs_until_root() {
+        let _lock = CACHE_TEST_LOCK.lock().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let engine = dir.path().join("engines/billing");
+        let lib = engine.join("lib");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(
+            dir.path().join(".rfmt.yml"),
+            "formatting:\n  indent_width: 4\n  line_length: 120\n",
+        )
+        .unwrap();
+        std::fs::write(engine.join(".rfmt.yml"), "formatting:\n  line_length: 80\n").unwrap();
+
+        let config = Config::discover_cached_from(&lib);
+        assert_eq!(config.formatting.line_length, 80);
+        assert_eq!(config.formatting.indent_width, 4);
+        assert_eq!(config.base_dir.as_deref(), Some(engine.as_path()));
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(
+            engine.join(".rfmt.yml"),
+            "root: true\nformatting:\n  line_length: 80\n",
+        )
+        .unwrap();
+        let config = Config::discover_cached_from(&lib);
+        assert_eq!(config.formatting.line_length, 80);
+        assert_eq!(config.formatting.indent_width, 2);
+    }
+
+    #[test]
+    fn test_inherit_from_merges_bases_underneath() {
+        let _lock = CACHE_TEST_LOCK.lock().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let shared = dir.path().join("shared");
+        let app = dir.path().join("app");
+        std::fs::create_dir_all(&shared).unwrap();
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(
+            shared.join("base.yml"),
+            "formatting:\n  indent_width: 4\n  quote_style: single\n  style:\n    trailing_comma: never\n",
+        )
+        .unwrap();
+        std::fs::write(
+            app.join(".rfmt.yml"),
+            "root: true\ninherit_from: ../shared/base.yml\nformatting:\n  quote_style: double\n  style:\n    hash_syntax: hash_rockets\n",
+        )
+        .unwrap();
+
+        let config = Config::discover_cached_from(&app);
+        assert_eq!(config.formatting.indent_width, 4);
+        assert_eq!(config.formatting.quote_style, QuoteStyle::Double);
+        assert!(matches!(
+            config.formatting.style.trailing_comma,
+            TrailingComma::Never
+        ));
+        assert!(matches!(
+            config.formatting.style.hash_syntax,
+            HashSyntax::HashRockets
+        ));
+
+        // Explicit loads follow inherit_from too, and notice a changed base.
+        let path = app.join(".rfmt.yml");
+        assert_eq!(
+            Config::resolve(Some(&path), None)
+                .unwrap()
+                .formatting
+                .indent_width,
+            4
+        );
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        write_indent_config(&shared.join("base.yml"), 3);
+        assert_eq!(
+            Config::resolve(Some(&path), None)
+                .unwrap()
+                .formatting
+                .indent_width,
+            3
+        );
+        assert_eq!(
+            Config::discover_cached_from(&app).formatting.indent_width,
+            3
+        );
+    }
+
+    #[test]
+    fn test_discovery_imports_rubocop_and_editorconfig_without_rfmt_config() {
+        let _lock = CACHE_TEST_LOCK.lock().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("lib");
+        std::fs::create_dir(&lib).unwrap();
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n[*]\nindent_size = 4\nmax_line_length = 80\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".rubocop.yml"),
+            "Layout/LineLength:\n  Max: 120\nStyle/StringLiterals:\n  EnforcedStyle: single_quotes\n",
+        )
+        .unwrap();
+
+        let config = Config::discover_cached_from(&lib);
+        assert_eq!(config.formatting.line_length, 120);
+        assert_eq!(config.formatting.indent_width, 4);
+        assert_eq!(config.formatting.quote_style, QuoteStyle::Single);
+
+        // An rfmt config takes over, importing only what it asks for.
+        std::fs::write(
+            dir.path().join(".rfmt.yml"),
+            "import: [rubocop]\nformatting:\n  quote_style: double\n",
+        )
+        .unwrap();
+        let config = Config::discover(&lib);
+        assert_eq!(config.formatting.line_length, 120);
+        assert_eq!(config.formatting.indent_width, 2);
+        assert_eq!(config.formatting.quote_style, QuoteStyle::Double);
+    }
+
+    #[test]
+    fn test_inherit_from_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rfmt.yml");
+        for (yaml, expected) in [
+            ("inherit_from: rfmt.yml\n", "inherits from itself"),
+            ("inherit_from: missing.yml\n", "Failed to read config file"),
+            ("inherit_from: [1]\n", "inherit_from must be a path"),
+            ("root: yes please\n", "root must be true or false"),
+            (
+                "import: [prettier]\n",
+                "import: unknown import \"prettier\"",
+            ),
+        ] {
+            std::fs::write(&path, yaml).unwrap();
+            match Config::load_file(&path) {
+                Err(RfmtError::ConfigError { message, .. }) => {
+                    assert!(message.contains(expected), "{}", message)
+                }
+                other => panic!("expected a config error for {:?}, got {:?}", yaml, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_discovers_from_target_directory() {
+        let _lock = CACHE_TEST_LOCK.lock().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let engine = dir.path().join("engines/billing");
+        std::fs::create_dir_all(engine.join("spec")).unwrap();
+        std::fs::write(
+            engine.join(".rfmt.yml"),
+            "overrides:\n  - files: [\"spec/**\"]\n    formatting:\n      line_length: 120\n",
+        )
+        .unwrap();
+
+        let config = Config::resolve(None, Some(&engine.join("spec/invoice_spec.rb"))).unwrap();
+        assert_eq!(config.formatting.line_length, 120);
+        let config = Config::resolve(None, Some(&engine.join("invoice.rb"))).unwrap();
+        assert_eq!(config.formatting.line_length, 100);
+    }
+
     #[test]
     fn test_partial_config_uses_defaults() {
         let yaml = r#"
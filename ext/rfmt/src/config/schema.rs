@@ -0,0 +1,657 @@
+//! JSON Schema of `.rfmt.yml`, published as `docs/rfmt.schema.json` for
+//! editor completion, and the unknown-key check that walks configuration
+//! files against it.
+
+use super::{import, migrate, Config, HashSyntax, IndentStyle, QuoteStyle, TrailingComma};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// The configuration file schema (draft-07), with defaults taken from
+/// [`Config::default`] and the enum names from the types' serde spellings.
+pub fn json_schema() -> Value {
+    let defaults = serde_json::to_value(Config::default()).unwrap_or_default();
+    let default = |pointer: &str| defaults.pointer(pointer).cloned().unwrap_or_default();
+    let globs = |description: &str, pointer: &str| {
+        json!({
+            "description": description,
+            "type": "array",
+            "items": { "type": "string" },
+            "default": default(pointer),
+        })
+    };
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "rfmt configuration",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "version": {
+                "description": "Configuration format version.",
+                "type": "string",
+                "enum": migrate::VERSIONS,
+                "default": default("/version"),
+            },
+            "root": {
+                "description": "Stop looking for configuration files in parent directories.",
+                "type": "boolean",
+                "default": false,
+            },
+            "inherit_from": {
+                "description": "Configuration files, relative to this one, merged underneath this file's settings.",
+                "oneOf": [
+                    { "type": "string" },
+                    { "type": "array", "items": { "type": "string" } },
+                ],
+            },
+            "import": {
+                "description": "Settings read from other tools' configuration, underneath this file's.",
+                "oneOf": [
+                    { "$ref": "#/definitions/import" },
+                    { "type": "array", "items": { "$ref": "#/definitions/import" } },
+                ],
+            },
+            "parser": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "version": {
+                        "description": "Ruby version to parse as: \"latest\" or a version such as \"3.3\".",
+                        "type": "string",
+                        "pattern": "^(latest|[0-9]+\\.[0-9]+(\\.[0-9]+)?)$",
+                        "default": default("/parser/version"),
+                    },
+                    "error_tolerance": {
+                        "description": "Copy top-level statements that do not parse verbatim instead of failing.",
+                        "type": "boolean",
+                        "default": default("/parser/error_tolerance"),
+                    },
+                    "encoding": {
+                        "description": "Source encoding; only UTF-8 is supported.",
+                        "type": "string",
+                        "pattern": "^[Uu][Tt][Ff]-8$",
+                        "default": default("/parser/encoding"),
+                    },
+                },
+            },
+            "formatting": { "$ref": "#/definitions/formatting" },
+            "validation": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "ast_equivalence": {
+                        "description": "Fail unless the formatted output parses to the same tree as the input.",
+                        "type": "boolean",
+                        "default": default("/validation/ast_equivalence"),
+                    },
+                    "idempotency": {
+                        "description": "Fail unless formatting the output again changes nothing.",
+                        "type": "boolean",
+                        "default": default("/validation/idempotency"),
+                    },
+                },
+            },
+            "include": globs("Glob patterns of the files to format.", "/include"),
+            "exclude": globs("Glob patterns of the files never to format.", "/exclude"),
+            "overrides": {
+                "description": "Formatting for the files matching each entry's patterns, applied in order.",
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["files"],
+                    "properties": {
+                        "files": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "minItems": 1,
+                        },
+                        "formatting": { "$ref": "#/definitions/formatting" },
+                    },
+                },
+            },
+        },
+        "definitions": {
+            "import": { "enum": import::Source::NAMES },
+            "formatting": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "line_length": {
+                        "type": "integer",
+                        "minimum": 40,
+                        "maximum": 500,
+                        "default": default("/formatting/line_length"),
+                    },
+                    "indent_width": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 8,
+                        "default": default("/formatting/indent_width"),
+                    },
+                    "indent_style": {
+                        "enum": variants::<IndentStyle>(),
+                        "default": default("/formatting/indent_style"),
+                    },
+                    "quote_style": {
+                        "enum": variants::<QuoteStyle>(),
+                        "default": default("/formatting/quote_style"),
+                    },
+                    "style": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "hash_syntax": {
+                                "enum": variants::<HashSyntax>(),
+                                "default": default("/formatting/style/hash_syntax"),
+                            },
+                            "trailing_comma": {
+                                "enum": variants::<TrailingComma>(),
+                                "default": default("/formatting/style/trailing_comma"),
+                            },
+                        },
+                    },
+                },
+            },
+        },
+    })
+}
+
+/// The names the unit-variant enum `T` serializes to, in declaration
+/// order: those serde lists when rejecting a name that is none of them,
+/// less aliases, which serialize to another name.
+fn variants<T: Serialize + DeserializeOwned>() -> Value {
+    let rejection = serde_json::from_value::<T>(json!(""))
+        .err()
+        .map(|e| e.to_string())
+        .unwrap_or_default();
+    let expected = rejection
+        .split_once(", expected ")
+        .map_or("", |(_, expected)| expected);
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|name| json!(name))
+        .filter(|name| {
+            serde_json::from_value::<T>(name.clone())
+                .ok()
+                .and_then(|variant| serde_json::to_value(variant).ok())
+                .as_ref()
+                == Some(name)
+        })
+        .collect()
+}
+
+/// A key the schema does not define.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct UnknownKey {
+    /// Where the key is, such as `overrides[0].formatting`; empty at the top
+    /// level.
+    pub location: String,
+    /// The mapping keys leading to the key, ending with it.
+    pub keys: Vec<String>,
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = self.keys.last().map_or("", String::as_str);
+        write!(f, "unknown key {:?}", key)?;
+        if !self.location.is_empty() {
+            write!(f, " in {}", self.location)?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean {:?}?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// The first key of `settings`, in document order, that the schema does not
+/// define.
+pub(super) fn unknown_key(settings: &serde_yaml::Value) -> Option<UnknownKey> {
+    let schema = json_schema();
+    find_unknown(settings, &schema, &schema, String::new(), &mut Vec::new())
+}
+
+fn find_unknown(
+    value: &serde_yaml::Value,
+    schema: &Value,
+    root: &Value,
+    location: String,
+    keys: &mut Vec<String>,
+) -> Option<UnknownKey> {
+    let schema = match schema["$ref"].as_str() {
+        Some(reference) => root.pointer(reference.trim_start_matches('#'))?,
+        None => schema,
+    };
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            let properties = schema["properties"].as_object()?;
+            for (key, value) in mapping {
+                let Some(key) = key.as_str() else { continue };
+                keys.push(key.to_string());
+                let Some(property) = properties.get(key) else {
+                    let suggestion = closest(key, properties.keys().map(String::as_str));
+                    return Some(UnknownKey {
+                        location,
+                        keys: keys.clone(),
+                        suggestion: suggestion.map(str::to_string),
+                    });
+                };
+                let nested = match location.as_str() {
+                    "" => key.to_string(),
+                    _ => format!("{}.{}", location, key),
+                };
+                if let Some(unknown) = find_unknown(value, property, root, nested, keys) {
+                    return Some(unknown);
+                }
+                keys.pop();
+            }
+            None
+        }
+        serde_yaml::Value::Sequence(items) => {
+            let schema = &schema["items"];
+            items.iter().enumerate().find_map(|(index, item)| {
+                find_unknown(item, schema, root, format!("{}[{}]", location, index), keys)
+            })
+        }
+        _ => None,
+    }
+}
+
+/// `message` with a suggestion appended when it is serde's rejection of an
+/// unknown variant close to an expected one.
+pub(super) fn with_suggestion(message: String) -> String {
+    let Some((_, rest)) = message.split_once("unknown variant `") else {
+        return message;
+    };
+    let Some((variant, expected)) = rest.split_once('`') else {
+        return message;
+    };
+    let expected = expected.split(" at line ").next().unwrap_or(expected);
+    let candidates = expected.split('`').skip(1).step_by(2);
+    match closest(variant, candidates) {
+        Some(suggestion) => format!("{}, did you mean {:?}?", message, suggestion),
+        None => message,
+    }
+}
+
+/// The candidate nearest `name`, if it is within a third of the longer
+/// one's length in edits.
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, candidate)| distance * 3 <= name.len().max(candidate.len()))
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Byte offset of the key reached through `keys` in block-style YAML
+/// `contents`. Sequence entries are not told apart, so the first entry
+/// holding the key wins; keys inside flow collections are not found.
+pub(super) fn locate<S: AsRef<str>>(contents: &str, keys: &[S]) -> Option<usize> {
+    key_lines(contents)
+        .into_iter()
+        .find(|line| {
+            line.keys.len() == keys.len()
+                && line
+                    .keys
+                    .iter()
+                    .zip(keys)
+                    .all(|(key, expected)| *key == expected.as_ref())
+        })
+        .map(|line| line.start + line.column)
+}
+
+/// A line of block-style YAML that starts a mapping entry.
+#[derive(Debug)]
+pub(super) struct KeyLine<'a> {
+    /// Zero-based line number.
+    pub index: usize,
+    /// Byte offset of the line.
+    pub start: usize,
+    /// Byte column of the key, after any `- ` sequence markers.
+    pub column: usize,
+    /// The mapping keys leading to this entry, ending with its own.
+    pub keys: Vec<&'a str>,
+    /// Line number of the entry this one is nested in.
+    pub parent: Option<usize>,
+    /// Byte range of the value written on the line, without a trailing
+    /// comment; empty when the value is nested below.
+    pub value: std::ops::Range<usize>,
+}
+
+/// The mapping entries of block-style YAML `contents`. Lines are matched by
+/// indentation, which serde_yaml does not report, and each entry is kept
+/// only if the parsed document has its keys, so text inside multi-line
+/// scalars is never taken for a key. Flow collections are not looked into.
+pub(super) fn key_lines(contents: &str) -> Vec<KeyLine<'_>> {
+    let Ok(document) = serde_yaml::from_str::<serde_yaml::Value>(contents) else {
+        return Vec::new();
+    };
+    let mut lines: Vec<KeyLine> = Vec::new();
+    // Indices into `lines` of the entries enclosing the current one.
+    let mut parents: Vec<usize> = Vec::new();
+    let mut offset = 0;
+    for (index, line) in contents.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += line.len();
+
+        let mut column = line.len() - line.trim_start_matches(' ').len();
+        let mut rest = &line[column..];
+        while let Some(item) = rest.strip_prefix('-').filter(|item| item.starts_with(' ')) {
+            let trimmed = item.trim_start_matches(' ');
+            column += rest.len() - trimmed.len();
+            rest = trimmed;
+        }
+        if rest.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = rest
+            .split_once(": ")
+            .or_else(|| rest.trim_end().strip_suffix(':').map(|key| (key, "")))
+        else {
+            continue;
+        };
+        let key = key.trim_matches(|c| c == '"' || c == '\'');
+
+        let trimmed = value.trim_start_matches(' ');
+        let value_start = start + line.len() - trimmed.len();
+        let value = trimmed;
+        let value_len = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].find(quote).map_or(value.len(), |end| end + 2),
+            _ => value
+                .find(" #")
+                .unwrap_or(value.len())
+                .min(value.trim_end().len()),
+        };
+
+        while parents
+            .last()
+            .is_some_and(|&parent| lines[parent].column >= column)
+        {
+            parents.pop();
+        }
+        let mut keys = parents
+            .last()
+            .map_or_else(Vec::new, |&parent| lines[parent].keys.clone());
+        keys.push(key);
+        lines.push(KeyLine {
+            index,
+            start,
+            column,
+            keys,
+            parent: parents.last().map(|&parent| lines[parent].index),
+            value: value_start..value_start + value_len,
+        });
+        parents.push(lines.len() - 1);
+    }
+    lines.retain(|line| has_keys(&document, &line.keys));
+    lines
+}
+
+/// Whether the mapping keys `keys` lead somewhere in `value`, passing
+/// through any sequence entry.
+fn has_keys(value: &serde_yaml::Value, keys: &[&str]) -> bool {
+    match (value, keys) {
+        (_, []) => true,
+        (serde_yaml::Value::Sequence(items), _) => items.iter().any(|item| has_keys(item, keys)),
+        (serde_yaml::Value::Mapping(mapping), [key, rest @ ..]) => {
+            mapping.get(*key).is_some_and(|value| has_keys(value, rest))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unknown(yaml: &str) -> Option<String> {
+        unknown_key(&serde_yaml::from_str(yaml).unwrap()).map(|key| key.to_string())
+    }
+
+    #[test]
+    fn test_unknown_keys_are_reported_with_suggestions() {
+        assert_eq!(
+            unknown("formatting:\n  line_lenght: 80\n").as_deref(),
+            Some("unknown key \"line_lenght\" in formatting, did you mean \"line_length\"?")
+        );
+        assert_eq!(
+            unknown("formating:\n  line_length: 80\n").as_deref(),
+            Some("unknown key \"formating\", did you mean \"formatting\"?")
+        );
+        assert_eq!(
+            unknown("overrides:\n  - files: [\"a\"]\n  - files: [\"b\"]\n    formatting:\n      style:\n        hash_sytnax: ruby19\n")
+                .as_deref(),
+            Some("unknown key \"hash_sytnax\" in overrides[1].formatting.style, did you mean \"hash_syntax\"?")
+        );
+        assert_eq!(
+            unknown("banana: 1\n").as_deref(),
+            Some("unknown key \"banana\"")
+        );
+        assert_eq!(
+            unknown("root: true\ninherit_from: [base.yml]\nimport: rubocop\nversion: \"1.0\"\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_every_setting_is_in_the_schema() {
+        let mut config = Config::default();
+        config.overrides.push(super::super::Override {
+            files: vec!["spec/**".to_string()],
+            formatting: serde_yaml::from_str("line_length: 120").unwrap(),
+        });
+        let settings = serde_yaml::to_value(&config).unwrap();
+        assert_eq!(unknown_key(&settings), None);
+    }
+
+    /// Asserts that the schema at `path` describes `value`, the serialized
+    /// setting, and that every property it defines is a setting.
+    fn assert_describes(schema: &Value, root: &Value, value: &Value, path: &str) {
+        let schema = match schema["$ref"].as_str() {
+            Some(reference) => root.pointer(reference.trim_start_matches('#')).unwrap(),
+            None => schema,
+        };
+        if let Some(names) = schema["enum"].as_array() {
+            assert!(
+                names.contains(value),
+                "{}: {} is not in {:?}",
+                path,
+                value,
+                names
+            );
+            return;
+        }
+        let kind = match value {
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "integer",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::Null => "null",
+        };
+        assert_eq!(schema["type"], kind, "{}", path);
+        match value {
+            Value::Object(fields) => {
+                let properties = schema["properties"].as_object().unwrap();
+                for (key, field) in fields {
+                    let property = properties
+                        .get(key)
+                        .unwrap_or_else(|| panic!("{}.{} is not in the schema", path, key));
+                    assert_describes(property, root, field, &format!("{}.{}", path, key));
+                }
+                // Read while loading the file, not settings.
+                let file_only = ["root", "inherit_from", "import"];
+                for key in properties.keys() {
+                    assert!(
+                        fields.contains_key(key)
+                            || path.is_empty() && file_only.contains(&key.as_str()),
+                        "{}.{} is not a setting",
+                        path,
+                        key
+                    );
+                }
+            }
+            Value::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    assert_describes(
+                        &schema["items"],
+                        root,
+                        item,
+                        &format!("{}[{}]", path, index),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_schema_describes_every_field() {
+        let mut config = Config::default();
+        config.overrides.push(super::super::Override {
+            files: vec!["spec/**".to_string()],
+            formatting: serde_yaml::to_value(&config.formatting)
+                .unwrap()
+                .as_mapping()
+                .cloned()
+                .unwrap(),
+        });
+        let schema = json_schema();
+        assert_describes(
+            &schema,
+            &schema,
+            &serde_json::to_value(&config).unwrap(),
+            "",
+        );
+    }
+
+    #[test]
+    fn test_schema_enums_are_the_serde_spellings() {
+        assert_eq!(variants::<IndentStyle>(), json!(["spaces", "tabs"]));
+        assert_eq!(
+            variants::<QuoteStyle>(),
+            json!(["double", "single", "consistent"])
+        );
+        // The deprecated aliases are accepted but not offered.
+        assert_eq!(
+            variants::<HashSyntax>(),
+            json!(["ruby19", "hash_rockets", "consistent"])
+        );
+        assert_eq!(
+            variants::<TrailingComma>(),
+            json!(["always", "never", "multiline"])
+        );
+        for name in import::Source::NAMES {
+            assert!(name.parse::<import::Source>().is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_schema_enums_deserialize() {
+        let schema = json_schema();
+        let formatting = &schema["definitions"]["formatting"]["properties"];
+        for (key, values) in [
+            ("indent_style", &formatting["indent_style"]["enum"]),
+            ("quote_style", &formatting["quote_style"]["enum"]),
+            (
+                "style:\n  hash_syntax",
+                &formatting["style"]["properties"]["hash_syntax"]["enum"],
+            ),
+            (
+                "style:\n  trailing_comma",
+                &formatting["style"]["properties"]["trailing_comma"]["enum"],
+            ),
+        ] {
+            for value in values.as_array().unwrap() {
+                let yaml = format!("formatting:\n  {}: {}\n", key, value);
+                assert!(serde_yaml::from_str::<Config>(&yaml).is_ok(), "{}", yaml);
+            }
+        }
+    }
+
+    #[test]
+    fn test_published_schema_is_current() {
+        let published: Value =
+            serde_json::from_str(include_str!("../../../../docs/rfmt.schema.json")).unwrap();
+        assert!(
+            published == json_schema(),
+            "docs/rfmt.schema.json is stale; regenerate it with `rfmt --config-schema`"
+        );
+    }
+
+    #[test]
+    fn test_variant_suggestions() {
+        assert_eq!(
+            with_suggestion(
+                "formatting.quote_style: unknown variant `singel`, expected one of `double`, `single`, `consistent` at line 2 column 16".to_string()
+            ),
+            "formatting.quote_style: unknown variant `singel`, expected one of `double`, `single`, `consistent` at line 2 column 16, did you mean \"single\"?"
+        );
+        assert_eq!(
+            with_suggestion("unknown variant `x`, expected `a` or `b`".to_string()),
+            "unknown variant `x`, expected `a` or `b`"
+        );
+    }
+
+    #[test]
+    fn test_locate_follows_nesting() {
+        let yaml = "\
+# settings
+formatting:
+  line_length: 100
+  style:
+    hash_syntax: ruby19
+overrides:
+  - files: [\"spec/**\"]
+    formatting:
+      line_length: 120
+";
+        let line_of = |keys: &[&str]| {
+            locate(yaml, keys).map(|offset| yaml[..offset].matches('\n').count() + 1)
+        };
+        assert_eq!(line_of(&["formatting", "line_length"]), Some(3));
+        assert_eq!(line_of(&["formatting", "style", "hash_syntax"]), Some(5));
+        assert_eq!(line_of(&["overrides", "files"]), Some(7));
+        assert_eq!(
+            line_of(&["overrides", "formatting", "line_length"]),
+            Some(9)
+        );
+        assert_eq!(line_of(&["line_length"]), None);
+    }
+
+    #[test]
+    fn test_locate_skips_text_in_block_scalars() {
+        let yaml = "\
+exclude:
+  - |
+    line_length: 80
+formatting:
+  line_length: 100
+";
+        assert_eq!(locate(yaml, &["exclude", "line_length"]), None);
+        assert_eq!(
+            locate(yaml, &["formatting", "line_length"]),
+            yaml.find("  line_length: 100").map(|offset| offset + 2)
+        );
+    }
+}
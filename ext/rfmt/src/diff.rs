@@ -0,0 +1,613 @@
+//! Line diffs and text edits between a source and its formatted output.
+//!
+//! `unified_diff` renders the familiar `diff -u` format the CLI prints for
+//! `--diff`. The edit script comes from Myers' O(ND) algorithm in its
+//! linear-space form, which stays cheap for formatter output: files are
+//! large but differ in few lines, and a file that changes throughout costs
+//! time rather than memory.
+//! `text_edits` turns the same line script into the few small `TextEdit`s
+//! editors apply instead of replacing the whole document, which would reset
+//! their undo history, folds and marks.
+
+use std::ops::Range;
+
+use crate::line_index::{LineIndex, Position};
+
+/// A replacement of part of a source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Byte range of the replaced text.
+    pub range: Range<usize>,
+    /// Text put in its place.
+    pub new_text: String,
+}
+
+impl TextEdit {
+    /// Returns `source` with the edit applied.
+    pub fn apply(&self, source: &str) -> String {
+        let mut result = String::with_capacity(source.len() + self.new_text.len());
+        result.push_str(&source[..self.range.start]);
+        result.push_str(&self.new_text);
+        result.push_str(&source[self.range.end..]);
+        result
+    }
+}
+
+/// One step of a line edit script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineOp {
+    /// The line at this index of the old text is kept.
+    Equal { old: usize, new: usize },
+    /// The line at this index of the old text is removed.
+    Delete { old: usize },
+    /// The line at this index of the new text is added.
+    Insert { new: usize },
+}
+
+/// Lines of context around each hunk, as in `diff -u`.
+const CONTEXT: usize = 3;
+
+/// Renders a unified diff from `old` to `new`, or an empty string when they
+/// are equal.
+///
+/// ```
+/// let diff = rfmt::diff::unified_diff("a\nb\n", "a\nc\n", "a/x.rb", "b/x.rb");
+/// assert_eq!(diff, "--- a/x.rb\n+++ b/x.rb\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n");
+/// ```
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for hunk in hunks(&ops) {
+        let (old_start, old_len, new_start, new_len) = hunk_range(hunk);
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        ));
+        for op in hunk {
+            let (prefix, line) = match *op {
+                LineOp::Equal { old, .. } => (' ', old_lines[old]),
+                LineOp::Delete { old } => ('-', old_lines[old]),
+                LineOp::Insert { new } => ('+', new_lines[new]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Computes a short list of non-overlapping edits turning `old` into `new`,
+/// in ascending order of their byte ranges in `old`.
+///
+/// Each run of changed lines becomes one edit, or one per line when as
+/// many lines are removed as added (the common reindentation case), and
+/// every edit is trimmed to the characters that actually differ.
+///
+/// ```
+/// let edits = rfmt::diff::text_edits("def a\nb\nend\n", "def a\n  b\nend\n");
+/// assert_eq!(edits.len(), 1);
+/// assert_eq!((edits[0].range.clone(), edits[0].new_text.as_str()), (6..6, "  "));
+/// ```
+pub fn text_edits(old: &str, new: &str) -> Vec<TextEdit> {
+    if old == new {
+        return Vec::new();
+    }
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let old_starts = line_starts(&old_lines);
+    let new_starts = line_starts(&new_lines);
+
+    let mut edits = Vec::new();
+    // Line indices just past the last equal line on each side
+    let (mut old_line, mut new_line) = (0, 0);
+    let ops = diff_lines(&old_lines, &new_lines);
+    let mut ops = ops.iter().peekable();
+    while let Some(op) = ops.next() {
+        let (mut deleted, mut inserted) = match *op {
+            LineOp::Equal { old, new } => {
+                (old_line, new_line) = (old + 1, new + 1);
+                continue;
+            }
+            LineOp::Delete { .. } => (1, 0),
+            LineOp::Insert { .. } => (0, 1),
+        };
+        while let Some(op) = ops.next_if(|op| !matches!(op, LineOp::Equal { .. })) {
+            match op {
+                LineOp::Delete { .. } => deleted += 1,
+                _ => inserted += 1,
+            }
+        }
+
+        let pairs = if deleted == inserted {
+            (0..deleted).map(|i| (i, 1, 1)).collect()
+        } else {
+            vec![(0, deleted, inserted)]
+        };
+        for (i, old_count, new_count) in pairs {
+            let (o, n) = (old_line + i, new_line + i);
+            edits.extend(trimmed_edit(
+                old,
+                old_starts[o]..old_starts[o + old_count],
+                &new[new_starts[n]..new_starts[n + new_count]],
+            ));
+        }
+        (old_line, new_line) = (old_line + deleted, new_line + inserted);
+    }
+    edits
+}
+
+/// `text_edits` in LSP coordinates: zero-based lines and UTF-16 columns of
+/// `old`, each with its replacement text.
+pub fn lsp_text_edits(old: &str, new: &str) -> Vec<(Range<Position>, String)> {
+    let index = LineIndex::new(old.as_bytes());
+    text_edits(old, new)
+        .into_iter()
+        .map(|edit| {
+            let start = index.position(old, edit.range.start);
+            let end = index.position(old, edit.range.end);
+            (start..end, edit.new_text)
+        })
+        .collect()
+}
+
+/// Byte offset of each line, plus the end of the text.
+fn line_starts(lines: &[&str]) -> Vec<usize> {
+    let mut starts = Vec::with_capacity(lines.len() + 1);
+    let mut offset = 0;
+    starts.push(offset);
+    for line in lines {
+        offset += line.len();
+        starts.push(offset);
+    }
+    starts
+}
+
+/// The edit replacing `range` of `old` by `new_text`, minus the text both
+/// have in common at either end; `None` when nothing differs.
+fn trimmed_edit(old: &str, range: Range<usize>, new_text: &str) -> Option<TextEdit> {
+    let replaced = &old[range.clone()];
+    let prefix = replaced
+        .char_indices()
+        .zip(new_text.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(replaced.len().min(new_text.len()), |((i, _), _)| i);
+    let (replaced_rest, new_rest) = (&replaced[prefix..], &new_text[prefix..]);
+    let suffix = replaced_rest
+        .char_indices()
+        .rev()
+        .zip(new_rest.chars().rev())
+        .find(|((_, a), b)| a != b)
+        .map_or(replaced_rest.len().min(new_rest.len()), |((i, a), _)| {
+            replaced_rest.len() - i - a.len_utf8()
+        });
+
+    if replaced_rest.len() == suffix && new_rest.len() == suffix {
+        return None;
+    }
+    Some(TextEdit {
+        range: range.start + prefix..range.end - suffix,
+        new_text: new_rest[..new_rest.len() - suffix].to_string(),
+    })
+}
+
+/// Computes a shortest edit script turning `old` into `new`.
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<LineOp> {
+    // Furthest reaching x per diagonal of the forward and backward searches,
+    // reused by every subproblem
+    let diagonals = 2 * ((old.len() + new.len()).div_ceil(2) + 1);
+    let mut forward = vec![0isize; diagonals];
+    let mut backward = vec![0isize; diagonals];
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    conquer(
+        old,
+        new,
+        0..old.len(),
+        0..new.len(),
+        (&mut forward, &mut backward),
+        &mut ops,
+    );
+    // Within each run of changes, deletions come before insertions as in
+    // `diff -u`
+    for run in ops.split_mut(|op| matches!(op, LineOp::Equal { .. })) {
+        run.sort_by_key(|op| matches!(op, LineOp::Insert { .. }));
+    }
+    ops
+}
+
+/// Appends the script turning `old[olds]` into `new[news]`: common ends are
+/// kept, and the rest is split where a shortest path crosses its middle
+/// and each half solved in turn, so memory stays linear in the input.
+fn conquer<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    mut olds: Range<usize>,
+    mut news: Range<usize>,
+    v: (&mut [isize], &mut [isize]),
+    ops: &mut Vec<LineOp>,
+) {
+    let prefix = old[olds.clone()]
+        .iter()
+        .zip(&new[news.clone()])
+        .take_while(|(a, b)| a == b)
+        .count();
+    ops.extend((0..prefix).map(|i| LineOp::Equal {
+        old: olds.start + i,
+        new: news.start + i,
+    }));
+    olds.start += prefix;
+    news.start += prefix;
+    let suffix = old[olds.clone()]
+        .iter()
+        .rev()
+        .zip(new[news.clone()].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    olds.end -= suffix;
+    news.end -= suffix;
+
+    if olds.is_empty() {
+        ops.extend(news.clone().map(|new| LineOp::Insert { new }));
+    } else if news.is_empty() {
+        ops.extend(olds.clone().map(|old| LineOp::Delete { old }));
+    } else {
+        let (forward, backward) = v;
+        let (x, y) = middle_snake(&old[olds.clone()], &new[news.clone()], forward, backward);
+        let (split_old, split_new) = (olds.start + x, news.start + y);
+        conquer(
+            old,
+            new,
+            olds.start..split_old,
+            news.start..split_new,
+            (forward, backward),
+            ops,
+        );
+        conquer(
+            old,
+            new,
+            split_old..olds.end,
+            split_new..news.end,
+            (forward, backward),
+            ops,
+        );
+    }
+    ops.extend((0..suffix).map(|i| LineOp::Equal {
+        old: olds.end + i,
+        new: news.end + i,
+    }));
+}
+
+/// Myers' forward and backward searches run in alternation until their
+/// furthest reaching paths overlap, returning a point of `old` and `new`
+/// (both non-empty) that a shortest edit path goes through.
+fn middle_snake<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    forward: &mut [isize],
+    backward: &mut [isize],
+) -> (usize, usize) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta & 1 == 1;
+    let offset = (forward.len() / 2) as isize;
+    let slot = |k: isize| (k + offset) as usize;
+    forward[slot(1)] = 0;
+    backward[slot(1)] = 0;
+
+    // Diagonal k holds the points where x - y == k; backward diagonals
+    // count from the ends of both texts
+    for d in 0..=(n + m + 1) / 2 {
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && forward[slot(k - 1)] < forward[slot(k + 1)]) {
+                forward[slot(k + 1)]
+            } else {
+                forward[slot(k - 1)] + 1
+            };
+            let (start_x, start_y) = (x, x - k);
+            let mut y = start_y;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[slot(k)] = x;
+            if odd && (k - delta).abs() < d && x + backward[slot(delta - k)] >= n {
+                return (start_x as usize, start_y as usize);
+            }
+        }
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && backward[slot(k - 1)] < backward[slot(k + 1)]) {
+                backward[slot(k + 1)]
+            } else {
+                backward[slot(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[slot(k)] = x;
+            if !odd && (k - delta).abs() <= d && x + forward[slot(delta - k)] >= n {
+                return ((n - x) as usize, (m - y) as usize);
+            }
+        }
+    }
+    unreachable!("the searches meet within (n + m + 1) / 2 rounds")
+}
+
+/// Splits an edit script into hunks of changes with up to `CONTEXT` equal
+/// lines around them; hunks closer than twice that are merged.
+fn hunks(ops: &[LineOp]) -> Vec<&[LineOp]> {
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, LineOp::Equal { .. }))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut hunks = Vec::new();
+    let mut iter = changes.into_iter().peekable();
+    while let Some(first) = iter.next() {
+        let mut last = first;
+        while let Some(&next) = iter.peek() {
+            if next - last > 2 * CONTEXT {
+                break;
+            }
+            last = next;
+            iter.next();
+        }
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(ops.len());
+        hunks.push(&ops[start..end]);
+    }
+    hunks
+}
+
+/// 1-based start line and length of a hunk on each side.
+fn hunk_range(hunk: &[LineOp]) -> (usize, usize, usize, usize) {
+    let (mut old_start, mut new_start) = (None, None);
+    let (mut old_len, mut new_len) = (0, 0);
+    for op in hunk {
+        match *op {
+            LineOp::Equal { old, new } => {
+                old_start.get_or_insert(old);
+                new_start.get_or_insert(new);
+                old_len += 1;
+                new_len += 1;
+            }
+            LineOp::Delete { old } => {
+                old_start.get_or_insert(old);
+                old_len += 1;
+            }
+            LineOp::Insert { new } => {
+                new_start.get_or_insert(new);
+                new_len += 1;
+            }
+        }
+    }
+    // A side without lines is empty, which diff -u reports as line 0
+    (
+        old_start.map_or(0, |i| i + 1),
+        old_len,
+        new_start.map_or(0, |i| i + 1),
+        new_len,
+    )
+}
+
+fn range(start: usize, len: usize) -> String {
+    if len == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(old: &[&str], new: &[&str], ops: &[LineOp]) -> (Vec<String>, Vec<String>) {
+        let mut from = Vec::new();
+        let mut to = Vec::new();
+        for op in ops {
+            match *op {
+                LineOp::Equal { old: o, new: n } => {
+                    assert_eq!(old[o], new[n]);
+                    from.push(old[o].to_string());
+                    to.push(new[n].to_string());
+                }
+                LineOp::Delete { old: o } => from.push(old[o].to_string()),
+                LineOp::Insert { new: n } => to.push(new[n].to_string()),
+            }
+        }
+        (from, to)
+    }
+
+    #[test]
+    fn test_edit_script_reproduces_both_sides() {
+        let cases: [(&[&str], &[&str]); 5] = [
+            (&["a", "b", "c"], &["a", "b", "c"]),
+            (&[], &["x", "y"]),
+            (&["x", "y"], &[]),
+            (
+                &["a", "b", "c", "a", "b", "b", "a"],
+                &["c", "b", "a", "b", "a", "c"],
+            ),
+            (&["def x", "1", "end"], &["def x", "  1", "end", ""]),
+        ];
+        for (old, new) in cases {
+            let ops = diff_lines(old, new);
+            let (from, to) = apply(old, new, &ops);
+            assert_eq!(from, old);
+            assert_eq!(to, new);
+        }
+    }
+
+    #[test]
+    fn test_edit_script_is_minimal() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let changes = diff_lines(&old, &new)
+            .iter()
+            .filter(|op| !matches!(op, LineOp::Equal { .. }))
+            .count();
+        assert_eq!(changes, 5);
+    }
+
+    #[test]
+    fn test_edit_script_is_minimal_for_generated_texts() {
+        // Small alphabets make many equal lines for the search to choose from
+        let mut seed = 0x2545_f491_u32;
+        let mut next = |bound: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed % bound
+        };
+        let letters = ["a", "b", "c", "d"];
+        for _ in 0..500 {
+            let alphabet = 1 + next(4);
+            let old: Vec<&str> = (0..next(12))
+                .map(|_| letters[next(alphabet) as usize])
+                .collect();
+            let new: Vec<&str> = (0..next(12))
+                .map(|_| letters[next(alphabet) as usize])
+                .collect();
+
+            // Longest common subsequence by dynamic programming
+            let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+            for i in (0..old.len()).rev() {
+                for j in (0..new.len()).rev() {
+                    lcs[i][j] = if old[i] == new[j] {
+                        lcs[i + 1][j + 1] + 1
+                    } else {
+                        lcs[i + 1][j].max(lcs[i][j + 1])
+                    };
+                }
+            }
+
+            let ops = diff_lines(&old, &new);
+            let (from, to) = apply(&old, &new, &ops);
+            assert_eq!(from, old);
+            assert_eq!(to, new);
+            let changes = ops
+                .iter()
+                .filter(|op| !matches!(op, LineOp::Equal { .. }))
+                .count();
+            assert_eq!(
+                changes,
+                old.len() + new.len() - 2 * lcs[0][0],
+                "{:?} -> {:?}",
+                old,
+                new
+            );
+        }
+    }
+
+    #[test]
+    fn test_unified_diff_separates_distant_hunks() {
+        let old: String = (1..=20).map(|i| format!("line{}\n", i)).collect();
+        let new = old.replace("line2\n", "LINE2\n").replace("line18\n", "");
+        let diff = unified_diff(&old, &new, "a/f.rb", "b/f.rb");
+        assert_eq!(
+            diff,
+            "--- a/f.rb\n+++ b/f.rb\n\
+             @@ -1,5 +1,5 @@\n line1\n-line2\n+LINE2\n line3\n line4\n line5\n\
+             @@ -15,6 +15,5 @@\n line15\n line16\n line17\n-line18\n line19\n line20\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_marks_missing_final_newline() {
+        let diff = unified_diff("x = 1", "x = 1\n", "a", "b");
+        assert_eq!(
+            diff,
+            "--- a\n+++ b\n@@ -1 +1 @@\n-x = 1\n\\ No newline at end of file\n+x = 1\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_of_new_file() {
+        let diff = unified_diff("", "a\nb\n", "a", "b");
+        assert_eq!(diff, "--- a\n+++ b\n@@ -0,0 +1,2 @@\n+a\n+b\n");
+    }
+
+    #[test]
+    fn test_text_edit_apply() {
+        let edit = TextEdit {
+            range: 4..9,
+            new_text: "x = 1".to_string(),
+        };
+        assert_eq!(edit.apply("a\nb\nx  =1\nc\n"), "a\nb\nx = 1\nc\n");
+    }
+
+    fn apply_all(old: &str, edits: &[TextEdit]) -> String {
+        // Back to front, so earlier ranges stay valid
+        edits
+            .iter()
+            .rev()
+            .fold(old.to_string(), |text, edit| edit.apply(&text))
+    }
+
+    #[test]
+    fn test_text_edits_reproduce_new_text() {
+        let cases = [
+            ("", "\n"),
+            ("x  =  1\n", "x = 1\n"),
+            (
+                "class Foo\ndef bar\n42\nend\nend\n",
+                "class Foo\n  def bar\n    42\n  end\nend\n",
+            ),
+            ("a\nb\nc\nd\n", "a\nb2\nb3\nd\n"),
+            ("a\n\n\n\nb", "a\n\nb\n"),
+            ("s = 'é'\n", "s = \"é\"\n"),
+        ];
+        for (old, new) in cases {
+            let edits = text_edits(old, new);
+            assert_eq!(apply_all(old, &edits), new, "{:?}", edits);
+            assert!(edits
+                .windows(2)
+                .all(|pair| pair[0].range.end <= pair[1].range.start));
+        }
+        assert!(text_edits("same\n", "same\n").is_empty());
+    }
+
+    #[test]
+    fn test_text_edits_reindent_line_by_line() {
+        let edits = text_edits(
+            "class Foo\ndef bar\n42\nend\nend\n",
+            "class Foo\n  def bar\n    42\n  end\nend\n",
+        );
+        let edits: Vec<(Range<usize>, &str)> = edits
+            .iter()
+            .map(|edit| (edit.range.clone(), edit.new_text.as_str()))
+            .collect();
+        assert_eq!(
+            edits,
+            vec![(10..10, "  "), (18..18, "    "), (21..21, "  ")]
+        );
+    }
+
+    #[test]
+    fn test_lsp_text_edits_use_utf16_columns() {
+        let edits = lsp_text_edits("s = '😀'  # x\n", "s = '😀' # x\n");
+        let position = |line, character| Position { line, character };
+        assert_eq!(
+            edits,
+            vec![(position(0, 9)..position(0, 10), String::new())]
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_of_equal_texts_is_empty() {
+        assert_eq!(unified_diff("a\n", "a\n", "a", "b"), "");
+    }
+}
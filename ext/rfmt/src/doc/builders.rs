@@ -293,6 +293,11 @@ pub fn empty() -> Doc {
     Doc::Empty
 }
 
+/// Creates a cursor marker for the source byte offset `offset`.
+pub fn cursor(offset: usize) -> Doc {
+    Doc::Cursor(offset)
+}
+
 /// Creates a trailing comment.
 ///
 /// # Example
@@ -334,6 +339,8 @@ pub fn line_suffix(contents: Doc) -> Doc {
 }
 
 /// Fill: packs content into lines as tightly as possible.
+///
+/// `docs` alternate content and separator: `[a, line(), b, line(), c]`.
 pub fn fill(docs: Vec<Doc>) -> Doc {
     Doc::Fill(docs)
 }
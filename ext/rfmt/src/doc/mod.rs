@@ -151,8 +151,18 @@ pub enum Doc {
 
     /// Fill: packs content into lines as tightly as possible.
     ///
-    /// Used for array elements, argument lists, etc.
+    /// Parts alternate content and separator (`[a, line, b, line, c]`); a
+    /// separator breaks only when the content after it would overflow.
+    /// Used for word arrays (`%w[]`, `%i[]`).
     Fill(Vec<Doc>),
+
+    /// Marks where a source byte offset ends up in the output; prints
+    /// nothing.
+    ///
+    /// The printer records the output offset of every marker it passes
+    /// (`Printer::cursor_positions`), which is how
+    /// `Formatter::format_with_cursor` carries a caret through formatting.
+    Cursor(usize),
 }
 
 /// Identifier for referencing groups in IfBreak.
@@ -173,7 +183,7 @@ impl Doc {
             Doc::Group { contents, .. } => contents.is_flat(),
             Doc::Indent(contents) => contents.is_flat(),
             Doc::IfBreak { flat_contents, .. } => flat_contents.is_flat(),
-            Doc::Empty => true,
+            Doc::Empty | Doc::Cursor(_) => true,
             Doc::TrailingComment(_) | Doc::LeadingComment { .. } => true,
             Doc::Line { .. } => false,
             Doc::Align { contents, .. } => contents.is_flat(),
@@ -12,7 +12,9 @@
 //! line width. If they fit, Line docs become spaces (flat mode).
 //! If they don't fit, Line docs become newlines (break mode).
 
-use super::Doc;
+use std::collections::HashMap;
+
+use super::{Doc, GroupId};
 use crate::config::{Config, IndentStyle};
 
 /// Print mode for Line docs within a group.
@@ -45,6 +47,11 @@ pub struct Printer<'a> {
     pos: usize,
     /// Pre-computed indent strings by width (avoids allocation during print)
     indent_cache: Vec<String>,
+    /// Mode each identified group was printed in, for `IfBreak` docs that
+    /// refer to a group other than the innermost one
+    group_modes: HashMap<GroupId, Mode>,
+    /// (source offset, output offset) of each `Cursor` marker printed
+    cursors: Vec<(usize, usize)>,
 }
 
 const MAX_PRECACHED_INDENT: usize = 32;
@@ -64,6 +71,8 @@ impl<'a> Printer<'a> {
             output: String::new(),
             pos: 0,
             indent_cache,
+            group_modes: HashMap::new(),
+            cursors: Vec::new(),
         }
     }
 
@@ -92,6 +101,8 @@ impl<'a> Printer<'a> {
     pub fn print(&mut self, doc: &Doc) -> String {
         self.output.clear();
         self.pos = 0;
+        self.group_modes.clear();
+        self.cursors.clear();
 
         let mut commands: Vec<PrintCommand> = vec![PrintCommand {
             indent: 0,
@@ -118,11 +129,17 @@ impl<'a> Printer<'a> {
         // is simpler and safer than threading "next is newline?" state
         // through `Doc::Line` emission, and it also removes stray spaces
         // that show up after inline trailing comments.
-        strip_trailing_line_whitespace(&mut self.output);
+        strip_trailing_line_whitespace(&mut self.output, &mut self.cursors);
 
         std::mem::take(&mut self.output)
     }
 
+    /// (source offset, output byte offset) of each `Doc::Cursor` marker in
+    /// the last printed Doc, in output order.
+    pub fn cursor_positions(&self) -> &[(usize, usize)] {
+        &self.cursors
+    }
+
     /// Processes a single print command.
     #[inline]
     fn process_command<'b>(&mut self, cmd: PrintCommand<'b>, commands: &mut Vec<PrintCommand<'b>>) {
@@ -146,13 +163,19 @@ impl<'a> Printer<'a> {
             Doc::Group {
                 contents,
                 break_parent,
-                ..
+                id,
             } => {
-                // Determine if the group fits on the remaining line
+                // Determine if the group fits on the remaining line. The
+                // contents are measured as they would print flat; measuring
+                // in the enclosing mode would stop at the first line break
+                // and call every group that starts with a softline a fit.
                 let remaining = self.config.formatting.line_length.saturating_sub(self.pos);
-                let fits = !*break_parent && self.fits(contents, cmd.indent, remaining, cmd.mode);
+                let fits = !*break_parent && self.fits(contents, cmd.indent, remaining, Mode::Flat);
 
                 let mode = if fits { Mode::Flat } else { Mode::Break };
+                if let Some(id) = id {
+                    self.group_modes.insert(*id, mode);
+                }
 
                 commands.push(PrintCommand {
                     indent: cmd.indent,
@@ -201,9 +224,9 @@ impl<'a> Printer<'a> {
             Doc::IfBreak {
                 break_contents,
                 flat_contents,
-                ..
+                group_id,
             } => {
-                let doc = match cmd.mode {
+                let doc = match self.if_break_mode(*group_id, cmd.mode) {
                     Mode::Break => break_contents,
                     Mode::Flat => flat_contents,
                 };
@@ -216,6 +239,8 @@ impl<'a> Printer<'a> {
 
             Doc::Empty => {}
 
+            Doc::Cursor(offset) => self.cursors.push((*offset, self.output.len())),
+
             Doc::TrailingComment(text) => {
                 self.output.push(' ');
                 self.output.push_str(text);
@@ -255,31 +280,84 @@ impl<'a> Printer<'a> {
                 });
             }
 
-            Doc::Fill(docs) => {
-                // Fill tries to fit as many items on each line as possible.
-                // For now, simple implementation: join with softline behavior.
-                for (i, doc) in docs.iter().rev().enumerate() {
-                    if i > 0 {
-                        commands.push(PrintCommand {
-                            indent: cmd.indent,
-                            mode: cmd.mode,
-                            doc: &Doc::Line {
-                                soft: true,
-                                hard: false,
-                                literal: false,
-                            },
-                        });
-                    }
-                    commands.push(PrintCommand {
+            Doc::Fill(parts) => {
+                // Parts alternate content and separator. Each separator
+                // prints flat when the content after it still fits on the
+                // current line, and breaks otherwise. Contents are laid out
+                // ahead of time from their flat widths.
+                let line_length = self.config.formatting.line_length;
+                let mut pos = self.pos;
+                let mut planned: Vec<PrintCommand<'b>> = Vec::with_capacity(parts.len());
+                for (i, part) in parts.iter().enumerate() {
+                    let width = self.flat_width(part);
+                    let mode = if i % 2 == 0 {
+                        // Content: flat when it fits, else let it break inside
+                        if pos + width <= line_length {
+                            Mode::Flat
+                        } else {
+                            Mode::Break
+                        }
+                    } else {
+                        let next = parts
+                            .get(i + 1)
+                            .map_or(0, |content| self.flat_width(content));
+                        if pos + width + next <= line_length {
+                            Mode::Flat
+                        } else {
+                            Mode::Break
+                        }
+                    };
+                    pos = match (i % 2, mode) {
+                        (1, Mode::Break) => cmd.indent,
+                        _ => pos + width,
+                    };
+                    planned.push(PrintCommand {
                         indent: cmd.indent,
-                        mode: cmd.mode,
-                        doc,
+                        mode,
+                        doc: part,
                     });
                 }
+                commands.extend(planned.into_iter().rev());
             }
         }
     }
 
+    /// Width of a Doc printed flat, up to its first hard line break.
+    fn flat_width(&self, doc: &Doc) -> usize {
+        let mut width = 0usize;
+        let mut stack: Vec<&Doc> = vec![doc];
+
+        while let Some(doc) = stack.pop() {
+            match doc {
+                Doc::Text(s) | Doc::LeadingComment { text: s, .. } => {
+                    width += s.chars().count();
+                }
+                Doc::TrailingComment(s) => width += 1 + s.chars().count(),
+                Doc::Concat(docs) | Doc::Fill(docs) => stack.extend(docs.iter().rev()),
+                Doc::Group { contents, .. }
+                | Doc::Indent(contents)
+                | Doc::Align { contents, .. }
+                | Doc::LineSuffix(contents) => stack.push(contents),
+                Doc::IfBreak { flat_contents, .. } => stack.push(flat_contents),
+                Doc::Line { hard: true, .. } => return width,
+                Doc::Line { soft: true, .. } => {}
+                Doc::Line { .. } => width += 1,
+                Doc::Empty | Doc::Cursor(_) => {}
+            }
+        }
+
+        width
+    }
+
+    /// Mode an `IfBreak` follows: its group's, once that group has been
+    /// printed, otherwise the enclosing one.
+    #[inline]
+    fn if_break_mode(&self, group_id: Option<GroupId>, mode: Mode) -> Mode {
+        group_id
+            .and_then(|id| self.group_modes.get(&id).copied())
+            .unwrap_or(mode)
+    }
+
     /// Determines if a Doc fits within the remaining width.
     ///
     /// Uses flat mode for inner groups and returns early when width is exceeded.
@@ -338,16 +416,16 @@ impl<'a> Printer<'a> {
                 Doc::IfBreak {
                     flat_contents,
                     break_contents,
-                    ..
+                    group_id,
                 } => {
-                    let contents = match mode {
+                    let contents = match self.if_break_mode(*group_id, mode) {
                         Mode::Flat => flat_contents,
                         Mode::Break => break_contents,
                     };
                     stack.push((contents, indent, mode));
                 }
 
-                Doc::Empty => {}
+                Doc::Empty | Doc::Cursor(_) => {}
 
                 Doc::TrailingComment(s) => {
                     width += 1 + if s.is_ascii() {
@@ -406,21 +484,34 @@ impl<'a> Printer<'a> {
 /// trailing whitespace inside a heredoc body is extremely rare in real Ruby
 /// code and Rails projects universally run with `Layout/TrailingWhitespace`,
 /// so trimming unconditionally matches project conventions.
-fn strip_trailing_line_whitespace(buf: &mut String) {
+///
+/// `cursors` output offsets (ascending) are moved along with the text; one
+/// inside stripped whitespace moves to the end of its line's text.
+fn strip_trailing_line_whitespace(buf: &mut String, cursors: &mut [(usize, usize)]) {
     if !buf.bytes().any(|b| b == b' ' || b == b'\t') {
         return;
     }
 
     let mut out = String::with_capacity(buf.len());
+    let mut cursors = cursors.iter_mut().peekable();
+    let mut line_start = 0;
     for line in buf.split_inclusive('\n') {
         // `split_inclusive` keeps the trailing `\n` attached to the line.
-        if let Some(stripped) = line.strip_suffix('\n') {
-            out.push_str(stripped.trim_end_matches([' ', '\t']));
+        let content = line.strip_suffix('\n');
+        let kept = content.unwrap_or(line).trim_end_matches([' ', '\t']);
+        while let Some((_, position)) =
+            cursors.next_if(|(_, position)| *position < line_start + line.len())
+        {
+            *position = out.len() + (*position - line_start).min(kept.len());
+        }
+        out.push_str(kept);
+        if content.is_some() {
             out.push('\n');
-        } else {
-            // Final line without a trailing newline.
-            out.push_str(line.trim_end_matches([' ', '\t']));
         }
+        line_start += line.len();
+    }
+    for (_, position) in cursors {
+        *position = out.len();
     }
     *buf = out;
 }
@@ -512,6 +603,108 @@ mod tests {
         assert!(result.starts_with(&long));
     }
 
+    #[test]
+    fn test_print_group_breaks_when_softline_comes_first() {
+        // The fit check measures the contents flat; stopping at the leading
+        // softline would wrongly keep this on one line
+        let items: Vec<Doc> = (0..30).map(|i| text(format!("item{}", i))).collect();
+        let doc = group(concat(vec![
+            text("["),
+            indent(concat(vec![
+                softline(),
+                join(concat(vec![text(","), line()]), items),
+            ])),
+            softline(),
+            text("]"),
+        ]));
+        let result = print_doc(&doc);
+        assert!(result.starts_with("[\n  item0,\n  item1,\n"), "{}", result);
+        assert!(result.ends_with("item29\n]\n"), "{}", result);
+    }
+
+    /// `[item, item, ...]` as a group that breaks one item per line.
+    fn bracketed(items: Vec<Doc>) -> Doc {
+        group(concat(vec![
+            text("["),
+            indent(concat(vec![
+                softline(),
+                join(concat(vec![text(","), line()]), items),
+            ])),
+            softline(),
+            text("]"),
+        ]))
+    }
+
+    #[test]
+    fn test_print_group_stays_flat_when_softline_comes_first() {
+        let doc = bracketed(vec![text("a"), text("b")]);
+        assert_eq!(print_doc(&doc), "[a, b]\n");
+    }
+
+    #[test]
+    fn test_print_nested_group_measured_flat_inside_broken_group() {
+        // Inner groups are measured flat too, not in the broken mode of the
+        // group around them, so each one breaks only when it overflows
+        let long: Vec<Doc> = (0..30).map(|i| text(format!("item{}", i))).collect();
+        let doc = group(concat(vec![
+            text("foo("),
+            indent(concat(vec![
+                softline(),
+                bracketed(vec![text("a"), text("b")]),
+                text(","),
+                line(),
+                bracketed(long),
+            ])),
+            softline(),
+            text(")"),
+        ]));
+        let result = print_doc(&doc);
+        assert!(
+            result.starts_with("foo(\n  [a, b],\n  [\n    item0,\n    item1,\n"),
+            "{}",
+            result
+        );
+        assert!(result.ends_with("    item29\n  ]\n)\n"), "{}", result);
+    }
+
+    #[test]
+    fn test_print_group_fit_stops_at_hardline() {
+        // Only the part before a forced break has to fit on the line
+        let long = "a".repeat(150);
+        let doc = group(concat(vec![
+            text("x"),
+            line(),
+            text("y"),
+            hardline(),
+            text(&long),
+        ]));
+        assert_eq!(print_doc(&doc), format!("x y\n{}\n", long));
+    }
+
+    #[test]
+    fn test_print_fill_packs_lines() {
+        let mut parts = Vec::new();
+        for i in 0..40 {
+            if i > 0 {
+                parts.push(line());
+            }
+            parts.push(text(format!("word{:02}", i)));
+        }
+        let result = print_doc(&fill(parts));
+        let lines: Vec<&str> = result.lines().collect();
+        assert!(lines.len() > 1, "{}", result);
+        assert!(lines.iter().all(|l| l.len() <= 100), "{}", result);
+        // Each line is packed: the next word would not have fit
+        assert!(lines[0].len() + " word00".len() > 100, "{}", result);
+        assert!(lines[0].starts_with("word00 word01 word02"));
+    }
+
+    #[test]
+    fn test_print_fill_flat_when_short() {
+        let doc = fill(vec![text("a"), line(), text("b"), line(), text("c")]);
+        assert_eq!(print_doc(&doc), "a b c\n");
+    }
+
     #[test]
     fn test_print_softline_flat() {
         // Softline disappears in flat mode
@@ -626,6 +819,26 @@ end
         assert_eq!(result, "[1, 2, 3]\n");
     }
 
+    #[test]
+    fn test_print_if_break_follows_referenced_group() {
+        let outer = |break_parent| Doc::Group {
+            contents: Box::new(concat(vec![
+                text("a"),
+                line(),
+                group(concat(vec![
+                    text("b"),
+                    if_break_with_group(text(","), empty(), GroupId(1)),
+                ])),
+            ])),
+            break_parent,
+            id: Some(GroupId(1)),
+        };
+
+        // The inner group fits, but the comma follows the outer group
+        assert_eq!(print_doc(&outer(true)), "a\nb,\n");
+        assert_eq!(print_doc(&outer(false)), "a b\n");
+    }
+
     #[test]
     fn test_print_literal_line() {
         // Literal line should not add indentation
@@ -639,6 +852,23 @@ end
         assert!(result.contains("\ncontent\n"));
     }
 
+    #[test]
+    fn test_print_records_cursor_positions() {
+        let config = Config::default();
+        let mut printer = Printer::new(&config);
+        let doc = concat(vec![
+            text("def foo"),
+            indent(concat(vec![hardline(), cursor(10), text("bar  ")])),
+            cursor(20),
+            hardline(),
+            text("end"),
+        ]);
+
+        assert_eq!(printer.print(&doc), "def foo\n  bar\nend\n");
+        // The second marker sat in the stripped trailing spaces
+        assert_eq!(printer.cursor_positions(), &[(10, 10), (20, 13)]);
+    }
+
     // Performance regression tests
     // Run with: cargo test --release perf_
 
@@ -1,57 +1,402 @@
-use magnus::{Error as MagnusError, Ruby};
+#[cfg(feature = "ruby")]
+use magnus::{prelude::*, Attr, Error as MagnusError, ExceptionClass, RModule, RObject, Ruby};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::line_index::LineIndex;
+
 pub type Result<T> = std::result::Result<T, RfmtError>;
 
+/// Where an error points: a 1-based line, a 0-based byte column and the
+/// byte range it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub range: Range<usize>,
+}
+
+impl Span {
+    /// Span of `range` in the text `index` was built from.
+    pub fn new(index: &LineIndex, range: Range<usize>) -> Self {
+        let (line, column) = index.line_column(range.start);
+        Self {
+            line,
+            column,
+            range,
+        }
+    }
+}
+
+/// One syntax error reported by prism.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDiagnostic {
+    pub message: String,
+    /// Stable identifier for the kind of error, such as
+    /// `expected_an_end_to_close_the_def_statement`. The ruby-prism crate
+    /// does not expose prism's diagnostic ids, so this is derived from the
+    /// message's leading clause.
+    pub kind: String,
+    pub span: Span,
+}
+
+impl ParseDiagnostic {
+    /// Diagnostic for a prism error spanning `range` of the parsed source.
+    pub fn new(index: &LineIndex, message: &str, range: Range<usize>) -> Self {
+        Self {
+            message: message.to_string(),
+            kind: kind_of(message),
+            span: Span::new(index, range),
+        }
+    }
+}
+
+/// `expected an `end` to close the `def` statement; ...` becomes
+/// `expected_an_end_to_close_the_def_statement`.
+fn kind_of(message: &str) -> String {
+    let clause = message.split([';', ',']).next().unwrap_or(message);
+    let mut kind = String::new();
+    for word in clause
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if !kind.is_empty() {
+            kind.push('_');
+        }
+        kind.push_str(&word.to_ascii_lowercase());
+    }
+    kind
+}
+
 #[derive(Error, Debug)]
 pub enum RfmtError {
-    #[error("Prism integration error: {0}")]
-    PrismError(String),
+    #[error("Prism integration error: {message}")]
+    PrismError { message: String },
 
-    // Message-only kinds: lib/rfmt.rb rewrites these into its public
-    // exception classes by their [Rfmt::...] prefix, so Display must not
-    // add any wrapper text around the message.
-    #[error("{0}")]
-    ParseError(String),
+    // Display must not add wrapper text around the messages of these
+    // kinds: the Ruby exceptions prefix them (see `ruby_message`).
+    #[error("Parse errors:\n{}", parse_error_lines(.errors))]
+    ParseError {
+        file: Option<PathBuf>,
+        errors: Vec<ParseDiagnostic>,
+    },
 
-    #[error("{0}")]
-    ValidationError(String),
+    /// `span` points into the formatted output, not the input.
+    #[error("{message}")]
+    ValidationError {
+        message: String,
+        file: Option<PathBuf>,
+        span: Option<Span>,
+    },
 
+    /// `file` is the configuration file and `span` points into it.
     #[error("{message}")]
-    ConfigError { message: String },
+    ConfigError {
+        message: String,
+        file: Option<PathBuf>,
+        span: Option<Span>,
+    },
+
+    /// Formatting the output again changed it; `diff` goes from the
+    /// first pass to the second and `span` points into the first.
+    #[error("Formatting is not idempotent (this is a bug in rfmt, not in your code); a second pass changes the output:\n{diff}")]
+    IdempotencyError {
+        diff: String,
+        file: Option<PathBuf>,
+        span: Option<Span>,
+    },
 
-    #[error("Format error: {0}")]
-    FormatError(String),
+    #[error("Format error: {message}")]
+    FormatError {
+        message: String,
+        file: Option<PathBuf>,
+        span: Option<Span>,
+    },
 
     #[error("Unsupported feature: {feature}\n{explanation}")]
     UnsupportedFeature {
         feature: String,
         explanation: String,
+        file: Option<PathBuf>,
     },
 }
 
+fn parse_error_lines(errors: &[ParseDiagnostic]) -> String {
+    errors
+        .iter()
+        .map(|error| {
+            format!(
+                "{}:{}: {}",
+                error.span.line, error.span.column, error.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 // Implement From for std::fmt::Error
 impl From<std::fmt::Error> for RfmtError {
     fn from(err: std::fmt::Error) -> Self {
-        RfmtError::FormatError(err.to_string())
+        RfmtError::format(err.to_string())
     }
 }
 
+impl RfmtError {
+    /// Configuration error with no file or location attached.
+    pub fn config(message: impl Into<String>) -> Self {
+        RfmtError::ConfigError {
+            message: message.into(),
+            file: None,
+            span: None,
+        }
+    }
+
+    /// Formatting error with no file or location attached.
+    pub fn format(message: impl Into<String>) -> Self {
+        RfmtError::FormatError {
+            message: message.into(),
+            file: None,
+            span: None,
+        }
+    }
+
+    /// Attributes the error to `path`, unless it already names a file.
+    pub fn with_file(mut self, path: &Path) -> Self {
+        match &mut self {
+            RfmtError::PrismError { .. } => {}
+            RfmtError::ParseError { file, .. }
+            | RfmtError::ValidationError { file, .. }
+            | RfmtError::ConfigError { file, .. }
+            | RfmtError::FormatError { file, .. }
+            | RfmtError::IdempotencyError { file, .. }
+            | RfmtError::UnsupportedFeature { file, .. } => {
+                file.get_or_insert_with(|| path.to_path_buf());
+            }
+        }
+        self
+    }
+
+    /// Error code, as listed in docs/error_reference.md.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RfmtError::ParseError { .. } => "E001",
+            RfmtError::ConfigError { .. } => "E002",
+            RfmtError::UnsupportedFeature { .. } => "E006",
+            RfmtError::PrismError { .. } => "E007",
+            RfmtError::FormatError { .. } => "E008",
+            RfmtError::IdempotencyError { .. } => "E009",
+            // Output that fails to parse is a bug in rfmt
+            RfmtError::ValidationError { .. } => "E999",
+        }
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            RfmtError::PrismError { .. } => None,
+            RfmtError::ParseError { file, .. }
+            | RfmtError::ValidationError { file, .. }
+            | RfmtError::ConfigError { file, .. }
+            | RfmtError::FormatError { file, .. }
+            | RfmtError::IdempotencyError { file, .. }
+            | RfmtError::UnsupportedFeature { file, .. } => file.as_deref(),
+        }
+    }
+
+    /// Location of the error; for a parse error, that of the first syntax
+    /// error.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            RfmtError::ParseError { errors, .. } => errors.first().map(|error| &error.span),
+            RfmtError::ValidationError { span, .. }
+            | RfmtError::ConfigError { span, .. }
+            | RfmtError::FormatError { span, .. }
+            | RfmtError::IdempotencyError { span, .. } => span.as_ref(),
+            RfmtError::PrismError { .. } | RfmtError::UnsupportedFeature { .. } => None,
+        }
+    }
+
+    /// Kind of the first syntax error, for parse errors.
+    pub fn kind(&self) -> Option<&str> {
+        match self {
+            RfmtError::ParseError { errors, .. } => errors.first().map(|error| error.kind.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Attributes every `Rfmt::Error` raised from Rust carries; `nil` when they
+/// do not apply.
+#[cfg(feature = "ruby")]
+const EXCEPTION_ATTRIBUTES: [&str; 7] = [
+    "code",
+    "file",
+    "line",
+    "column",
+    "byte_range",
+    "kind",
+    "errors",
+];
+
+/// Defines `Rfmt::Error < StandardError`, `Rfmt::RfmtError < Rfmt::Error`
+/// and one subclass of `Rfmt::RfmtError` per error kind.
+#[cfg(feature = "ruby")]
+pub fn define_exception_classes(
+    ruby: &Ruby,
+    module: RModule,
+) -> std::result::Result<(), MagnusError> {
+    let base = module.define_error("Error", ruby.exception_standard_error())?;
+    for attribute in EXCEPTION_ATTRIBUTES {
+        base.define_attr(attribute, Attr::Read)?;
+    }
+
+    let native = module.define_error("RfmtError", base)?;
+    for name in [
+        "PrismError",
+        "ParseError",
+        "ValidationError",
+        "ConfigError",
+        "FormatError",
+        "IdempotencyError",
+        "UnsupportedFeature",
+    ] {
+        module.define_error(name, native)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ruby")]
 impl RfmtError {
     /// Convert RfmtError to Magnus Error for Ruby interop
     pub fn to_magnus_error(&self, ruby: &Ruby) -> MagnusError {
-        let exception_class = match self {
-            RfmtError::PrismError(_) => "PrismError",
-            RfmtError::ParseError(_) => "ParseError",
-            RfmtError::ValidationError(_) => "ValidationError",
-            RfmtError::FormatError(_) => "FormatError",
+        match self.to_exception(ruby) {
+            Ok(exception) => exception,
+            Err(error) => error,
+        }
+    }
+
+    fn class_name(&self) -> &'static str {
+        match self {
+            RfmtError::PrismError { .. } => "PrismError",
+            RfmtError::ParseError { .. } => "ParseError",
+            RfmtError::ValidationError { .. } => "ValidationError",
+            RfmtError::FormatError { .. } => "FormatError",
+            RfmtError::IdempotencyError { .. } => "IdempotencyError",
             RfmtError::UnsupportedFeature { .. } => "UnsupportedFeature",
             RfmtError::ConfigError { .. } => "ConfigError",
+        }
+    }
+
+    /// Message of the Ruby exception; parse and configuration errors keep
+    /// the wording `Rfmt.format` has always raised.
+    fn ruby_message(&self) -> String {
+        match self {
+            RfmtError::ParseError { .. } => format!("Failed to parse Ruby code: {}", self),
+            RfmtError::ConfigError { .. } => format!("Configuration error: {}", self),
+            _ => self.to_string(),
+        }
+    }
+
+    fn to_exception(&self, ruby: &Ruby) -> std::result::Result<MagnusError, MagnusError> {
+        let module: RModule = ruby.class_object().const_get("Rfmt")?;
+        let class: ExceptionClass = module.const_get(self.class_name())?;
+        let exception = class.new_instance((self.ruby_message(),))?;
+        let object = RObject::from_value(exception.as_value())
+            .ok_or_else(|| MagnusError::new(ruby.exception_type_error(), "not an object"))?;
+
+        object.ivar_set("@code", self.code())?;
+        object.ivar_set(
+            "@file",
+            self.file().map(|path| path.to_string_lossy().into_owned()),
+        )?;
+        if let Some(span) = self.span() {
+            object.ivar_set("@line", span.line)?;
+            object.ivar_set("@column", span.column)?;
+            object.ivar_set("@byte_range", span_range(ruby, span)?)?;
+        }
+        object.ivar_set("@kind", self.kind())?;
+        if let RfmtError::ParseError { errors, .. } = self {
+            let list = ruby.ary_new();
+            for error in errors {
+                let hash = ruby.hash_new();
+                hash.aset(ruby.to_symbol("message"), error.message.as_str())?;
+                hash.aset(ruby.to_symbol("kind"), error.kind.as_str())?;
+                hash.aset(ruby.to_symbol("line"), error.span.line)?;
+                hash.aset(ruby.to_symbol("column"), error.span.column)?;
+                hash.aset(ruby.to_symbol("byte_range"), span_range(ruby, &error.span)?)?;
+                list.push(hash)?;
+            }
+            object.ivar_set("@errors", list)?;
+        }
+        Ok(exception.into())
+    }
+}
+
+/// `start...end` of `span`.
+#[cfg(feature = "ruby")]
+fn span_range(ruby: &Ruby, span: &Span) -> std::result::Result<magnus::Range, MagnusError> {
+    ruby.range_new(span.range.start, span.range.end, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_is_the_leading_clause_of_the_message() {
+        assert_eq!(
+            kind_of("expected an `end` to close the `def` statement"),
+            "expected_an_end_to_close_the_def_statement"
+        );
+        assert_eq!(
+            kind_of("unexpected end-of-input; expected a `)` to close the parameters"),
+            "unexpected_end_of_input"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_display_lists_each_error() {
+        let source = "x = 1\ndef broken(\n";
+        let index = LineIndex::new(source.as_bytes());
+        let error = RfmtError::ParseError {
+            file: None,
+            errors: vec![
+                ParseDiagnostic::new(&index, "first", 6..9),
+                ParseDiagnostic::new(&index, "second", 16..17),
+            ],
         };
 
-        MagnusError::new(
-            ruby.exception_standard_error(),
-            format!("[Rfmt::{}] {}", exception_class, self),
-        )
+        assert_eq!(error.to_string(), "Parse errors:\n2:0: first\n2:10: second");
+        assert_eq!(error.code(), "E001");
+        assert_eq!(
+            error.span(),
+            Some(&Span {
+                line: 2,
+                column: 0,
+                range: 6..9
+            })
+        );
+    }
+
+    #[test]
+    fn test_with_file_keeps_the_first_file() {
+        let error = RfmtError::format("boom")
+            .with_file(Path::new("a.rb"))
+            .with_file(Path::new("b.rb"));
+        assert_eq!(error.file(), Some(Path::new("a.rb")));
+        assert_eq!(error.to_string(), "Format error: boom");
+    }
+
+    #[test]
+    fn test_idempotency_error_shows_the_diff() {
+        let error = RfmtError::IdempotencyError {
+            diff: "--- first pass\n+++ second pass\n".to_string(),
+            file: None,
+            span: None,
+        };
+        assert_eq!(error.code(), "E009");
+        assert!(error
+            .to_string()
+            .ends_with("a second pass changes the output:\n--- first pass\n+++ second pass\n"));
     }
 }
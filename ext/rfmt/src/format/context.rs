@@ -4,9 +4,10 @@
 //! - Source code reference
 //! - Configuration
 //! - Comment tracking and emission
+//! - `# rfmt:disable` / `# rfmt:enable` / `# rfmt:skip` directives
 //! - Group ID generation for Doc IR
 
-use crate::ast::{Comment, Node};
+use crate::ast::{Comment, Node, NodeType};
 use crate::config::Config;
 use std::collections::{BTreeMap, HashSet};
 
@@ -37,8 +38,26 @@ pub struct FormatContext<'a> {
     /// Key: start_line, Value: Vec of comment indices that start on that line
     comments_by_line: BTreeMap<usize, Vec<usize>>,
 
+    /// Lines of `# rfmt:disable` and the matching `# rfmt:enable`
+    /// (`usize::MAX` when the file never re-enables formatting)
+    disabled_regions: Vec<(usize, usize)>,
+
+    /// Lines of `# rfmt:skip` directives
+    skip_lines: Vec<usize>,
+
     /// Counter for generating unique group IDs
     next_group_id: u32,
+
+    /// Plain `"…"` / `'…'` string literals seen in the AST, as
+    /// (double, single). Drives `quote_style: consistent`.
+    quote_tally: (usize, usize),
+
+    /// Source offset of the caret `Formatter::format_with_cursor` tracks
+    cursor: Option<usize>,
+
+    /// Cursor marker of the node being formatted, as (start offset, start
+    /// line), until its leading comments are out of the way
+    pending_cursor: Option<(usize, usize)>,
 }
 
 impl<'a> FormatContext<'a> {
@@ -51,7 +70,12 @@ impl<'a> FormatContext<'a> {
             all_comments: Vec::new(),
             emitted_comment_indices: HashSet::new(),
             comments_by_line: BTreeMap::new(),
+            disabled_regions: Vec::new(),
+            skip_lines: Vec::new(),
             next_group_id: 0,
+            quote_tally: (0, 0),
+            cursor: None,
+            pending_cursor: None,
         }
     }
 
@@ -72,11 +96,47 @@ impl<'a> FormatContext<'a> {
         id
     }
 
+    /// Tracks the caret at source offset `offset` through formatting.
+    pub fn set_cursor(&mut self, offset: usize) {
+        self.cursor = Some(offset);
+    }
+
+    /// Whether `node` gets a cursor marker: with a cursor set, every node
+    /// starting at or before it does, so the closest one can anchor it.
+    pub fn anchors_cursor(&self, node: &Node) -> bool {
+        self.cursor
+            .is_some_and(|cursor| node.location.start_offset <= cursor)
+    }
+
+    /// Holds the cursor marker for `node` until its leading comments are
+    /// emitted, replacing any marker still pending.
+    pub fn set_pending_cursor(&mut self, node: &Node) {
+        self.pending_cursor = Some((node.location.start_offset, node.location.start_line));
+    }
+
+    /// Takes the pending cursor marker's source offset.
+    pub fn take_pending_cursor(&mut self) -> Option<usize> {
+        self.pending_cursor.take().map(|(offset, _)| offset)
+    }
+
+    /// Takes the pending cursor marker if it is for a node starting on
+    /// `line`.
+    pub fn take_pending_cursor_at(&mut self, line: usize) -> Option<usize> {
+        match self.pending_cursor {
+            Some((_, pending_line)) if pending_line == line => self.take_pending_cursor(),
+            _ => None,
+        }
+    }
+
     /// Collects all comments from the AST recursively.
+    ///
+    /// The same walk tallies string literal quotes for `prevailing_quote`,
+    /// and the comments are scanned for formatter directives.
     pub fn collect_comments(&mut self, root: &Node) {
         self.all_comments.clear();
         self.emitted_comment_indices.clear();
         self.comments_by_line.clear();
+        self.quote_tally = (0, 0);
 
         // Use iterative approach with stack to avoid deep recursion
         let mut stack = vec![root];
@@ -86,11 +146,91 @@ impl<'a> FormatContext<'a> {
                 self.all_comments.reserve(node.comments.len() * 4);
             }
             self.all_comments.extend(node.comments.iter().cloned());
+            if matches!(node.node_type, NodeType::StringNode) {
+                match self.source.as_bytes().get(node.location.start_offset) {
+                    Some(b'"') => self.quote_tally.0 += 1,
+                    Some(b'\'') => self.quote_tally.1 += 1,
+                    _ => {}
+                }
+            }
             // Process children in reverse order to maintain order when popping
             stack.extend(node.children.iter().rev());
         }
 
         self.build_comment_index();
+        self.collect_directives();
+    }
+
+    /// Records the regions between standalone `# rfmt:disable` and
+    /// `# rfmt:enable` comments and the lines of `# rfmt:skip` comments.
+    /// A directive may be followed by an explanation
+    /// (`# rfmt:disable aligned table`).
+    fn collect_directives(&mut self) {
+        let mut disabled_regions = Vec::new();
+        let mut skip_lines = Vec::new();
+
+        let mut disabled_at = None;
+        for comment in &self.all_comments {
+            if !self.is_standalone_comment(comment) {
+                continue;
+            }
+            let line = comment.location.start_line;
+            match directive(&comment.text) {
+                Some("rfmt:disable") => {
+                    disabled_at.get_or_insert(line);
+                }
+                Some("rfmt:enable") => {
+                    if let Some(start) = disabled_at.take() {
+                        disabled_regions.push((start, line));
+                    }
+                }
+                Some("rfmt:skip") => skip_lines.push(line),
+                _ => {}
+            }
+        }
+        if let Some(start) = disabled_at {
+            disabled_regions.push((start, usize::MAX));
+        }
+
+        self.disabled_regions = disabled_regions;
+        self.skip_lines = skip_lines;
+    }
+
+    /// Whether `node` is to be emitted as written: it lies between
+    /// `# rfmt:disable` and `# rfmt:enable`, or it is the statement right
+    /// after a `# rfmt:skip` (only blank and comment lines in between).
+    pub fn is_formatting_disabled(&self, node: &Node) -> bool {
+        let (start, end) = (node.location.start_line, node.location.end_line);
+        if self
+            .disabled_regions
+            .iter()
+            .any(|&(disable, enable)| disable < start && end < enable)
+        {
+            return true;
+        }
+
+        // The body around the next statement starts on the same line
+        if matches!(node.node_type, NodeType::StatementsNode) {
+            return false;
+        }
+        self.skip_lines.iter().any(|&skip| {
+            skip < start
+                && self.source_lines[skip..start - 1].iter().all(|line| {
+                    let line = line.trim_start();
+                    line.is_empty() || line.starts_with('#')
+                })
+        })
+    }
+
+    /// Quote character most string literals in the source already use.
+    ///
+    /// Ties (including a file without any plain literals) go to `"`.
+    pub fn prevailing_quote(&self) -> char {
+        if self.quote_tally.1 > self.quote_tally.0 {
+            '\''
+        } else {
+            '"'
+        }
     }
 
     /// Builds the comment index by start line for O(log n) range lookups.
@@ -196,6 +336,13 @@ impl<'a> FormatContext<'a> {
         self.emitted_comment_indices.extend(indices);
     }
 
+    /// Returns comments marked by `mark_comments_emitted` to the unemitted pool.
+    pub fn unmark_comments_emitted(&mut self, indices: impl IntoIterator<Item = usize>) {
+        for idx in indices {
+            self.emitted_comment_indices.remove(&idx);
+        }
+    }
+
     /// Extracts source text for a node.
     pub fn extract_source(&self, node: &Node) -> Option<&str> {
         self.source
@@ -314,6 +461,14 @@ impl<'a> FormatContext<'a> {
     }
 }
 
+/// The directive a comment spells, such as `rfmt:skip`, if any.
+fn directive(text: &str) -> Option<&str> {
+    text.strip_prefix('#')?
+        .split_whitespace()
+        .next()
+        .filter(|word| word.starts_with("rfmt:"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -340,6 +495,63 @@ mod tests {
         }
     }
 
+    fn make_statement(node_type: NodeType, start_line: usize, end_line: usize) -> Node {
+        Node {
+            node_type,
+            location: Location::new(start_line, 0, end_line, 0, 0, 0),
+            children: Vec::new(),
+            metadata: HashMap::new(),
+            comments: Vec::new(),
+            formatting: FormattingInfo::default(),
+        }
+    }
+
+    #[test]
+    fn test_directive() {
+        assert_eq!(directive("# rfmt:disable"), Some("rfmt:disable"));
+        assert_eq!(directive("#rfmt:skip  aligned"), Some("rfmt:skip"));
+        assert_eq!(directive("# rubocop:disable Style"), None);
+        assert_eq!(directive("# see rfmt:skip"), None);
+    }
+
+    #[test]
+    fn test_disabled_region_covers_nodes_inside_it() {
+        let config = Config::default();
+        let source = "a\n# rfmt:disable\nb\nc\n# rfmt:enable\nd\n  # rfmt:disable\ne\n";
+        let mut ctx = FormatContext::new(&config, source);
+        ctx.collect_comments(&make_node_with_comments(vec![
+            make_comment("# rfmt:disable", 2),
+            make_comment("# rfmt:enable", 5),
+            make_comment("# rfmt:disable", 7),
+        ]));
+
+        let disabled = |start, end| {
+            ctx.is_formatting_disabled(&make_statement(NodeType::CallNode, start, end))
+        };
+        assert!(!disabled(1, 1));
+        assert!(disabled(3, 3));
+        assert!(disabled(3, 4));
+        assert!(!disabled(4, 6));
+        assert!(!disabled(6, 6));
+        // Never re-enabled
+        assert!(disabled(8, 8));
+    }
+
+    #[test]
+    fn test_skip_covers_the_next_statement_only() {
+        let config = Config::default();
+        let source = "# rfmt:skip\n\n# why\na\nb\n";
+        let mut ctx = FormatContext::new(&config, source);
+        ctx.collect_comments(&make_node_with_comments(vec![
+            make_comment("# rfmt:skip", 1),
+            make_comment("# why", 3),
+        ]));
+
+        assert!(ctx.is_formatting_disabled(&make_statement(NodeType::CallNode, 4, 4)));
+        assert!(!ctx.is_formatting_disabled(&make_statement(NodeType::StatementsNode, 4, 5)));
+        assert!(!ctx.is_formatting_disabled(&make_statement(NodeType::CallNode, 5, 5)));
+    }
+
     #[test]
     fn test_collect_comments() {
         let config = Config::default();
@@ -8,12 +8,16 @@
 
 use crate::ast::{CommentType, Node, NodeType};
 use crate::config::Config;
-use crate::doc::{concat, hardline, Doc, Printer};
-use crate::error::Result;
+use crate::diff::{self, TextEdit};
+use crate::doc::{concat, hardline, indent, Doc, Printer};
+use crate::error::{Result, RfmtError, Span};
+use crate::line_index::LineIndex;
+use crate::parser::{NativeAdapter, RubyParser};
 
 use super::context::FormatContext;
+use super::range;
 use super::registry::RuleRegistry;
-use super::rule::format_remaining_comments;
+use super::rule::{format_child, format_remaining_comments};
 
 /// Main formatter that coordinates the formatting process.
 ///
@@ -25,17 +29,31 @@ pub struct Formatter {
     config: Config,
     /// Registry of formatting rules (shared: rules are stateless)
     registry: &'static RuleRegistry,
+    /// Whether to format the output again and compare
+    check_idempotency: bool,
 }
 
 impl Formatter {
     /// Creates a new formatter with the given configuration.
+    ///
+    /// The idempotency check starts out as `validation.idempotency` says.
     pub fn new(config: Config) -> Self {
         Self {
+            check_idempotency: config.validation.idempotency,
             config,
             registry: RuleRegistry::shared(),
         }
     }
 
+    /// Makes [`format`](Self::format) and
+    /// [`format_with_cursor`](Self::format_with_cursor) parse and format
+    /// their own output a second time, failing with
+    /// `RfmtError::IdempotencyError` when that changes it.
+    pub fn with_idempotency_check(mut self, enabled: bool) -> Self {
+        self.check_idempotency = enabled;
+        self
+    }
+
     /// Formats Ruby source code.
     ///
     /// # Arguments
@@ -45,8 +63,95 @@ impl Formatter {
     /// # Returns
     /// The formatted source code as a string
     pub fn format(&self, source: &str, ast: &Node) -> Result<String> {
+        let (result, _) = self.render(source, ast, None)?;
+        self.verify_idempotent(&result)?;
+        Ok(result)
+    }
+
+    /// Formats Ruby source code and carries a cursor position over into
+    /// the result.
+    ///
+    /// Every node starting at or before the cursor leaves a `Doc::Cursor`
+    /// marker in the Doc IR. From the output position of the closest one,
+    /// the cursor advances past as many non-whitespace characters as lie
+    /// between that node and the cursor in the source, so it stays on the
+    /// same character whenever formatting only changed whitespace.
+    ///
+    /// # Arguments
+    /// * `source` - The original Ruby source code
+    /// * `ast` - The parsed AST root node
+    /// * `cursor_offset` - Byte offset of the cursor in `source`
+    ///
+    /// # Returns
+    /// The formatted source code and the cursor's byte offset in it
+    pub fn format_with_cursor(
+        &self,
+        source: &str,
+        ast: &Node,
+        cursor_offset: usize,
+    ) -> Result<(String, usize)> {
+        let mut cursor = cursor_offset.min(source.len());
+        while !source.is_char_boundary(cursor) {
+            cursor -= 1;
+        }
+        let (result, markers) = self.render(source, ast, Some(cursor))?;
+        self.verify_idempotent(&result)?;
+
+        // The `__END__` section is copied verbatim behind the code
+        if data_start_offset(ast).is_some_and(|start| cursor >= start) {
+            let position = result.len().saturating_sub(source.len() - cursor);
+            return Ok((result, position));
+        }
+
+        let (from, to) = markers
+            .iter()
+            .filter(|(offset, _)| *offset <= cursor)
+            .max_by_key(|(offset, _)| *offset)
+            .copied()
+            .unwrap_or((0, 0));
+        let position = carry_cursor(&source[from..cursor], &source[cursor..], &result, to);
+        Ok((result, position))
+    }
+
+    /// With the idempotency check on, formats `result` again and fails
+    /// unless the second pass leaves it as it is.
+    fn verify_idempotent(&self, result: &str) -> Result<()> {
+        if !self.check_idempotency {
+            return Ok(());
+        }
+        // Output that does not parse is reported by the caller's
+        // `validation::validate_output`, with a better message
+        let Ok(ast) = NativeAdapter::new().parse(result) else {
+            return Ok(());
+        };
+        let (second, _) = self.render(result, &ast, None)?;
+        if second == result {
+            return Ok(());
+        }
+
+        let span = diff::text_edits(result, &second)
+            .first()
+            .map(|edit| Span::new(&LineIndex::new(result.as_bytes()), edit.range.clone()));
+        Err(RfmtError::IdempotencyError {
+            diff: diff::unified_diff(result, &second, "first pass", "second pass"),
+            file: None,
+            span,
+        })
+    }
+
+    /// Formats `ast` and, when tracking a cursor, returns the printed
+    /// cursor markers.
+    fn render(
+        &self,
+        source: &str,
+        ast: &Node,
+        cursor: Option<usize>,
+    ) -> Result<(String, Vec<(usize, usize)>)> {
         // 1. Initialize context
         let mut ctx = FormatContext::new(&self.config, source);
+        if let Some(cursor) = cursor {
+            ctx.set_cursor(cursor);
+        }
 
         // 2. Collect comments from AST
         ctx.collect_comments(ast);
@@ -71,12 +176,7 @@ impl Formatter {
         // 6. Re-append the `__END__` data section, which the AST excludes.
         // Appended after printing (and its trailing-whitespace strip) so the
         // data content survives byte-for-byte.
-        if let Some(data) = ast
-            .metadata
-            .get("data_start_offset")
-            .and_then(|offset| offset.parse::<usize>().ok())
-            .and_then(|offset| source.get(offset..))
-        {
+        if let Some(data) = data_start_offset(ast).and_then(|offset| source.get(offset..)) {
             if !result.is_empty() {
                 result.truncate(result.trim_end_matches('\n').len());
                 result.push('\n');
@@ -84,7 +184,99 @@ impl Formatter {
             result.push_str(data);
         }
 
-        Ok(result)
+        Ok((result, printer.cursor_positions().to_vec()))
+    }
+
+    /// Formats only the statements covering a byte range of the source.
+    ///
+    /// The range is widened to the smallest run of complete sibling
+    /// statements containing it, narrowing into a method, class or block
+    /// body when the range lies inside one. Those statements are formatted
+    /// at their body's indentation (one level below the line that opens
+    /// it) and the result replaces their whole lines, so code outside the
+    /// selection, including comments above it, is left untouched.
+    ///
+    /// # Arguments
+    /// * `source` - The original Ruby source code
+    /// * `ast` - The parsed AST root node
+    /// * `start_offset` / `end_offset` - Byte range to format; an empty
+    ///   range formats the statement at that position
+    ///
+    /// # Returns
+    /// The edit to apply to `source`, or `None` when the range covers no
+    /// statement or the statements are already formatted
+    pub fn format_range(
+        &self,
+        source: &str,
+        ast: &Node,
+        start_offset: usize,
+        end_offset: usize,
+    ) -> Result<Option<TextEdit>> {
+        if start_offset > end_offset
+            || !source.is_char_boundary(start_offset)
+            || !source.is_char_boundary(end_offset)
+        {
+            return Err(RfmtError::format(format!(
+                "Invalid range {}..{} for source of {} bytes",
+                start_offset,
+                end_offset,
+                source.len()
+            )));
+        }
+        let Some(selection) = range::select(source, ast, start_offset, end_offset) else {
+            return Ok(None);
+        };
+
+        let mut ctx = FormatContext::new(&self.config, source);
+        ctx.collect_comments(ast);
+
+        // Comments outside the replaced lines stay where they are
+        let outside: Vec<usize> = ctx
+            .get_comment_indices_before(selection.start_line)
+            .chain(ctx.get_comment_indices_in_range(selection.end_line + 1, usize::MAX))
+            .collect();
+        ctx.mark_comments_emitted(outside);
+
+        let body = self.format_children_with_spacing(selection.statements, &mut ctx)?;
+        let remaining = format_remaining_comments(&mut ctx, selection.end_line);
+        let mut doc = concat(vec![hardline(), body, remaining]);
+        let levels = selection
+            .owner
+            .map_or(0, |owner| self.indent_levels(source, owner) + 1);
+        for _ in 0..levels {
+            doc = indent(doc);
+        }
+
+        // The leading hardline puts the first statement at the body's
+        // indentation; it and the final newline are outside the range
+        let printed = Printer::new(&self.config).print(&doc);
+        let new_text = printed.strip_prefix('\n').unwrap_or(&printed);
+        let new_text = new_text.strip_suffix('\n').unwrap_or(new_text);
+
+        if new_text == &source[selection.replace.clone()] {
+            return Ok(None);
+        }
+        Ok(Some(TextEdit {
+            range: selection.replace,
+            new_text: new_text.to_string(),
+        }))
+    }
+
+    /// Indentation levels of the source line `node` starts on.
+    fn indent_levels(&self, source: &str, node: &Node) -> usize {
+        let offset = node.location.start_offset;
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let width = self.config.formatting.indent_width.max(1);
+        // A tab counts as one level whatever the indent style
+        let columns: usize = source[line_start..offset]
+            .chars()
+            .map_while(|c| match c {
+                ' ' => Some(1),
+                '\t' => Some(width),
+                _ => None,
+            })
+            .sum();
+        columns / width
     }
 
     /// Formats a single node.
@@ -92,11 +284,8 @@ impl Formatter {
         match &node.node_type {
             NodeType::ProgramNode => self.format_program(node, ctx),
             NodeType::StatementsNode => self.format_statements(node, ctx),
-            _ => {
-                // Use the rule registry for specific node types
-                let rule = self.registry.get_rule(&node.node_type);
-                rule.format(node, ctx, self.registry)
-            }
+            // Use the rule registry for specific node types
+            _ => format_child(node, ctx, self.registry),
         }
     }
 
@@ -173,6 +362,40 @@ impl Formatter {
     }
 }
 
+/// Start of the `__END__` data section, which the AST excludes.
+fn data_start_offset(ast: &Node) -> Option<usize> {
+    ast.metadata
+        .get("data_start_offset")
+        .and_then(|offset| offset.parse().ok())
+}
+
+/// Output offset of a cursor preceded by `before` and followed by `after`
+/// in the source, where the start of `before` printed at `anchor`.
+///
+/// Non-whitespace characters are paired up one for one, which is exact
+/// when formatting only changed whitespace and close otherwise.
+fn carry_cursor(before: &str, after: &str, output: &str, anchor: usize) -> usize {
+    let mut position = anchor;
+    let mut printed = output[anchor..]
+        .char_indices()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(i, c)| anchor + i + c.len_utf8());
+    for _ in before.chars().filter(|c| !c.is_whitespace()) {
+        match printed.next() {
+            Some(end) => position = end,
+            None => return output.len(),
+        }
+    }
+
+    // A cursor right before a token stays right before it
+    let before_token = before.is_empty() || before.ends_with(char::is_whitespace);
+    if before_token && after.starts_with(|c: char| !c.is_whitespace()) {
+        let rest = &output[position..];
+        position += rest.len() - rest.trim_start().len();
+    }
+    position
+}
+
 impl Default for Formatter {
     fn default() -> Self {
         Self::new(Config::default())
@@ -244,6 +467,27 @@ mod tests {
         assert!(result.contains("\n\n")); // blank line preserved
     }
 
+    #[test]
+    fn test_carry_cursor_pairs_non_whitespace() {
+        let output = "x = foo(1, 2)\n";
+        // Inside a token, after whitespace that shrank, at the end
+        assert_eq!(carry_cursor("x  =  fo", "o( 1,2 )", output, 0), 6);
+        assert_eq!(carry_cursor("x  =  foo( ", "1,2 )", output, 0), 8);
+        assert_eq!(carry_cursor("x  =  foo( 1,2 )", "\n", output, 0), 13);
+        // Past the end of the output
+        assert_eq!(
+            carry_cursor("x = foo(1, 2) # gone", "", output, 0),
+            output.len()
+        );
+    }
+
+    #[test]
+    fn test_carry_cursor_starts_at_anchor() {
+        let output = "a = 1\n\nb = 2\n";
+        assert_eq!(carry_cursor("", "b = 2", output, 7), 7);
+        assert_eq!(carry_cursor("b =", " 2", output, 7), 10);
+    }
+
     #[test]
     fn test_formatter_with_custom_config() {
         let mut config = Config::default();
@@ -258,4 +502,18 @@ mod tests {
 
         assert_eq!(result, "class Foo\nend\n");
     }
+
+    #[test]
+    fn test_idempotency_check_follows_config() {
+        assert!(!Formatter::default().check_idempotency);
+
+        let mut config = Config::default();
+        config.validation.idempotency = true;
+        assert!(Formatter::new(config.clone()).check_idempotency);
+        assert!(
+            !Formatter::new(config)
+                .with_idempotency_check(false)
+                .check_idempotency
+        );
+    }
 }
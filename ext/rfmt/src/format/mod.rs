@@ -25,6 +25,7 @@
 
 pub mod context;
 pub mod formatter;
+mod range;
 pub mod registry;
 pub mod rule;
 pub mod rules;
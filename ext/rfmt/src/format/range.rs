@@ -0,0 +1,125 @@
+//! Statement selection for range formatting.
+//!
+//! A byte range of the source is widened to the smallest run of complete
+//! sibling statements covering it, descending into a statement's body when
+//! the range lies inside it, so that only whole lines are rewritten.
+
+use std::ops::Range;
+
+use crate::ast::{Node, NodeType};
+
+/// A run of sibling statements chosen for a range.
+pub(crate) struct Selection<'a> {
+    /// Node whose body the statements are; `None` at the top level.
+    pub owner: Option<&'a Node>,
+    pub statements: &'a [Node],
+    /// Replaced text: from the start of the first statement's line to the
+    /// end of the last statement's line, excluding the newline.
+    pub replace: Range<usize>,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Selects the statements to format for `start..end`, or `None` when the
+/// range touches no statement.
+pub(crate) fn select<'a>(
+    source: &str,
+    ast: &'a Node,
+    start: usize,
+    end: usize,
+) -> Option<Selection<'a>> {
+    let mut best = None;
+    let mut owner = None;
+    let mut list = ast;
+
+    // Each round narrows into the body of a lone statement containing the
+    // range; a body that shares lines with its parent (`def a; b; end`)
+    // cannot be replaced on its own, so the last replaceable round wins.
+    while let Some(selection) = select_in(source, owner, &list.children, start, end) {
+        let lone = match selection.statements {
+            [statement] if contains(statement, start, end) => Some(statement),
+            _ => None,
+        };
+        best = Some(selection);
+        match lone.and_then(|statement| body_containing(statement, start, end)) {
+            Some((parent, body)) => {
+                owner = Some(parent);
+                list = body;
+            }
+            None => break,
+        }
+    }
+    best
+}
+
+fn select_in<'a>(
+    source: &str,
+    owner: Option<&'a Node>,
+    children: &'a [Node],
+    start: usize,
+    end: usize,
+) -> Option<Selection<'a>> {
+    let mut first = children.iter().position(|c| overlaps(c, start, end))?;
+    let mut last = children.iter().rposition(|c| overlaps(c, start, end))?;
+
+    // Statements sharing a line with the selection (`a; b`) go with it
+    while first > 0 && children[first - 1].location.end_line >= children[first].location.start_line
+    {
+        first -= 1;
+    }
+    while last + 1 < children.len()
+        && children[last + 1].location.start_line <= children[last].location.end_line
+    {
+        last += 1;
+    }
+
+    let head = &children[first].location;
+    let tail = &children[last].location;
+    let line_start = source[..head.start_offset].rfind('\n').map_or(0, |i| i + 1);
+    // A statement ending in a heredoc ends after the terminator's newline
+    let line_end = if source[..tail.end_offset].ends_with('\n') {
+        tail.end_offset - 1
+    } else {
+        source[tail.end_offset..]
+            .find('\n')
+            .map_or(source.len(), |i| tail.end_offset + i)
+    };
+
+    let before = &source[line_start..head.start_offset];
+    let after = source[tail.end_offset.min(line_end)..line_end].trim_start();
+    if !before.trim().is_empty() || !(after.is_empty() || after.starts_with('#')) {
+        return None;
+    }
+
+    Some(Selection {
+        owner,
+        statements: &children[first..=last],
+        replace: line_start..line_end,
+        start_line: head.start_line,
+        end_line: tail.end_line,
+    })
+}
+
+/// The innermost statements list under `node` containing the range, with
+/// the node it is the body of.
+fn body_containing(node: &Node, start: usize, end: usize) -> Option<(&Node, &Node)> {
+    let child = node.children.iter().find(|c| contains(c, start, end))?;
+    if child.node_type == NodeType::StatementsNode {
+        Some((node, child))
+    } else {
+        body_containing(child, start, end)
+    }
+}
+
+fn contains(node: &Node, start: usize, end: usize) -> bool {
+    node.location.start_offset <= start && end <= node.location.end_offset
+}
+
+/// An empty range (a cursor) overlaps the statement it touches.
+fn overlaps(node: &Node, start: usize, end: usize) -> bool {
+    if start == end {
+        contains(node, start, end)
+    } else {
+        node.location.start_offset < end && start < node.location.end_offset
+    }
+}
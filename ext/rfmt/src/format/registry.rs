@@ -10,10 +10,10 @@ use std::sync::OnceLock;
 
 use super::rule::{BoxedRule, FormatRule};
 use super::rules::{
-    BeginRule, BlockRule, CallRule, CaseMatchRule, CaseRule, ClassRule, DefRule, EnsureRule,
-    FallbackRule, ForRule, IfRule, InRule, InstanceVariableWriteRule, LambdaRule,
-    LocalVariableWriteRule, ModuleRule, RescueRule, SingletonClassRule, StatementsRule, UnlessRule,
-    UntilRule, WhenRule, WhileRule,
+    ArrayRule, BeginRule, BinaryRule, BlockRule, CallRule, CaseMatchRule, CaseRule, ClassRule,
+    DefRule, EnsureRule, FallbackRule, ForRule, HashRule, IfRule, InRule,
+    InstanceVariableWriteRule, LambdaRule, LocalVariableWriteRule, ModuleRule, RescueRule,
+    SingletonClassRule, StatementsRule, StringRule, UnlessRule, UntilRule, WhenRule, WhileRule,
 };
 
 /// Key type for the registry, derived from NodeType.
@@ -63,6 +63,13 @@ impl From<&NodeType> for NodeTypeKey {
             NodeType::InstanceVariableWriteNode => {
                 Self::from_static("instance_variable_write_node")
             }
+            NodeType::ArrayNode => Self::from_static("array_node"),
+            NodeType::HashNode => Self::from_static("hash_node"),
+            NodeType::KeywordHashNode => Self::from_static("keyword_hash_node"),
+            NodeType::StringNode => Self::from_static("string_node"),
+            NodeType::InterpolatedStringNode => Self::from_static("interpolated_string_node"),
+            NodeType::AndNode => Self::from_static("and_node"),
+            NodeType::OrNode => Self::from_static("or_node"),
             NodeType::Unknown(s) => Self::from_owned(s.clone()),
             // Default for unhandled types
             _ => Self::from_static("unknown"),
@@ -139,6 +146,13 @@ impl RuleRegistry {
                 NodeType::InstanceVariableWriteNode,
                 InstanceVariableWriteRule,
             )
+            .add(NodeType::ArrayNode, ArrayRule)
+            .add(NodeType::HashNode, HashRule)
+            .add(NodeType::KeywordHashNode, HashRule)
+            .add(NodeType::StringNode, StringRule)
+            .add(NodeType::InterpolatedStringNode, StringRule)
+            .add(NodeType::AndNode, BinaryRule)
+            .add(NodeType::OrNode, BinaryRule)
     }
 }
 
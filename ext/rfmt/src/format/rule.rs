@@ -5,14 +5,17 @@
 
 use std::collections::VecDeque;
 
-use crate::ast::{CommentType, Node};
+use crate::ast::{CommentType, Node, NodeType};
+use crate::config::TrailingComma;
 use crate::doc::{
-    concat, hardline, indent, leading_comment, literalline, text, trailing_comment, Doc,
+    concat, cursor, empty, hardline, if_break_with_group, indent, leading_comment, line,
+    literalline, softline, text, trailing_comment, Doc, GroupId,
 };
 use crate::error::Result;
 
 use super::context::FormatContext;
 use super::registry::RuleRegistry;
+use super::rules::{hash_syntax_source, VerbatimRule};
 
 /// Trait for formatting rules.
 ///
@@ -36,9 +39,81 @@ pub trait FormatRule: Send + Sync {
 /// Formats a child node by dispatching to the appropriate rule.
 ///
 /// This is the primary way to recursively format child nodes within rules.
+/// Nodes covered by a `# rfmt:disable` region or a `# rfmt:skip` comment
+/// go to [`VerbatimRule`] instead.
 pub fn format_child(child: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) -> Result<Doc> {
-    let rule = registry.get_rule(&child.node_type);
-    rule.format(child, ctx, registry)
+    let rule: &dyn FormatRule = if ctx.is_formatting_disabled(child) {
+        &VerbatimRule
+    } else {
+        registry.get_rule(&child.node_type)
+    };
+    if !ctx.anchors_cursor(child) {
+        return rule.format(child, ctx, registry);
+    }
+
+    // The marker belongs where the node's own text starts: after the
+    // leading comments its rule emits (see format_leading_comments)
+    ctx.set_pending_cursor(child);
+    let doc = rule.format(child, ctx, registry)?;
+    Ok(match ctx.take_pending_cursor() {
+        Some(offset) => concat(vec![cursor(offset), doc]),
+        None => doc,
+    })
+}
+
+/// Formats a child node embedded in a larger expression that continues
+/// after it, like a list element or an operand.
+///
+/// Comments trailing the child's last line belong to the enclosing
+/// statement, so they are held back from the child's rule instead of
+/// being emitted in the middle of the expression.
+pub fn format_embedded_child(
+    child: &Node,
+    ctx: &mut FormatContext,
+    registry: &RuleRegistry,
+) -> Result<Doc> {
+    let held: Vec<usize> = ctx
+        .get_trailing_comment_indices(child.location.end_line)
+        .collect();
+    ctx.mark_comments_emitted(held.iter().copied());
+    let doc = format_child(child, ctx, registry);
+    ctx.unmark_comments_emitted(held);
+    doc
+}
+
+/// Whether `node` has a rule that lays it out on its own inside a larger
+/// expression: string, array and hash literals, boolean expressions and
+/// block-less calls.
+pub fn has_embedded_layout(node: &Node) -> bool {
+    match node.node_type {
+        NodeType::StringNode
+        | NodeType::InterpolatedStringNode
+        | NodeType::ArrayNode
+        | NodeType::HashNode
+        | NodeType::AndNode
+        | NodeType::OrNode => true,
+        NodeType::CallNode => !node
+            .children
+            .iter()
+            .any(|child| matches!(child.node_type, NodeType::BlockNode)),
+        _ => false,
+    }
+}
+
+/// Formats an argument, operand or other expression embedded in a larger
+/// one: through its rule when [`has_embedded_layout`], otherwise as its
+/// source with hashes normalized to `style.hash_syntax`.
+pub fn format_embedded_expression(
+    node: &Node,
+    ctx: &mut FormatContext,
+    registry: &RuleRegistry,
+) -> Result<Doc> {
+    if has_embedded_layout(node) {
+        return format_embedded_child(node, ctx, registry);
+    }
+    Ok(hash_syntax_source(node, ctx)
+        .map(|source| text(source.into_owned()))
+        .unwrap_or_else(|| text("")))
 }
 
 /// Boxed rule type for dynamic dispatch.
@@ -138,6 +213,10 @@ pub fn format_leading_comments(ctx: &mut FormatContext, line: usize) -> Doc {
         }
     }
 
+    if let Some(offset) = ctx.take_pending_cursor_at(line) {
+        docs.push(cursor(offset));
+    }
+
     concat(docs)
 }
 
@@ -678,13 +757,183 @@ pub fn mark_comments_in_range_emitted(ctx: &mut FormatContext, start_line: usize
     ctx.mark_comments_emitted(indices);
 }
 
+/// Checks if any comment starts inside `node`'s source range.
+pub fn has_comments_within(node: &Node, ctx: &FormatContext) -> bool {
+    ctx.get_comment_indices_in_range(node.location.start_line, node.location.end_line + 1)
+        .any(|idx| {
+            ctx.get_comment(idx)
+                .is_some_and(|comment| comment.location.start_offset < node.location.end_offset)
+        })
+}
+
+/// Checks if `node` or any node inside it is a heredoc.
+///
+/// Heredoc bodies live outside the opener's extent, so rules that move
+/// nodes around emit anything containing one verbatim instead.
+pub fn contains_heredoc(node: &Node, ctx: &FormatContext) -> bool {
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+        let is_string = matches!(
+            current.node_type,
+            NodeType::StringNode
+                | NodeType::InterpolatedStringNode
+                | NodeType::XStringNode
+                | NodeType::InterpolatedXStringNode
+        );
+        if is_string
+            && ctx
+                .extract_source(current)
+                .is_some_and(|s| s.starts_with("<<"))
+        {
+            return true;
+        }
+        stack.extend(current.children.iter());
+    }
+    false
+}
+
+/// Comma to place after the last element of a list laid out by the group
+/// `group_id`, per `style.trailing_comma`.
+///
+/// Callers check `accepts_trailing_comma` on the last element first.
+pub fn trailing_comma(ctx: &FormatContext, group_id: GroupId) -> Doc {
+    match ctx.config().formatting.style.trailing_comma {
+        TrailingComma::Always => text(","),
+        TrailingComma::Never => empty(),
+        TrailingComma::Multiline => if_break_with_group(text(","), empty(), group_id),
+    }
+}
+
+/// Whether Ruby accepts a comma after `node` as the last element of a list.
+///
+/// A block pass (`&blk`) and argument forwarding (`...`, or the anonymous
+/// `*` / `**`) must end the list as written.
+pub fn accepts_trailing_comma(node: &Node) -> bool {
+    match node.node_type {
+        NodeType::BlockArgumentNode | NodeType::ForwardingArgumentsNode => false,
+        NodeType::SplatNode | NodeType::AssocSplatNode => !node.children.is_empty(),
+        _ => true,
+    }
+}
+
+/// Lays out the children of `node` as a comma-separated list between `open`
+/// and `close`: on one line when it fits `line_length`, otherwise one
+/// element per line followed by `trailing_comma`. A `padded` list has a
+/// space inside its delimiters on one line (`{ a: 1 }`).
+///
+/// Comments ahead of an element stay on their own lines before it, and a
+/// comment after an element stays after its comma; either forces the
+/// broken layout. Returns the list and any comments that sat after `close`
+/// on the last element's line, which belong after the whole list.
+pub fn format_delimited_list(
+    node: &Node,
+    ctx: &mut FormatContext,
+    open: &str,
+    close: &str,
+    padded: bool,
+    mut format_element: impl FnMut(&Node, &mut FormatContext) -> Result<Doc>,
+) -> Result<(Doc, Doc)> {
+    let start_line = node.location.start_line;
+    let end_line = node.location.end_line;
+    let count = node.children.len();
+    let id = GroupId(ctx.next_group_id());
+
+    let mut items: Vec<Doc> = Vec::with_capacity(count * 2);
+    let mut after_list: Vec<Doc> = Vec::new();
+    let mut has_comments = false;
+
+    for (i, element) in node.children.iter().enumerate() {
+        let is_last = i + 1 == count;
+
+        // Comments on their own lines ahead of this element
+        let before: Vec<usize> = ctx
+            .get_comment_indices_in_range(start_line, element.location.start_line)
+            .collect();
+        for &idx in &before {
+            if let Some(comment) = ctx.get_comment(idx) {
+                items.push(leading_comment(&comment.text, true));
+            }
+        }
+        has_comments |= !before.is_empty();
+        ctx.mark_comments_emitted(before);
+
+        // A comment after the last element on a line is claimed before the
+        // element is formatted, so its rule cannot print it ahead of the comma.
+        let ends_its_line = node
+            .children
+            .get(i + 1)
+            .is_none_or(|next| next.location.start_line > element.location.end_line);
+        let trailing: Vec<usize> = if ends_its_line {
+            ctx.get_trailing_comment_indices(element.location.end_line)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let trailing_docs: Vec<Doc> = trailing
+            .iter()
+            .filter_map(|&idx| ctx.get_comment(idx))
+            .map(|comment| trailing_comment(&comment.text))
+            .collect();
+        ctx.mark_comments_emitted(trailing);
+
+        let mut item = vec![format_element(element, ctx)?];
+        if !is_last {
+            item.push(text(","));
+        } else if accepts_trailing_comma(element) {
+            item.push(trailing_comma(ctx, id));
+        }
+        if element.location.end_line < end_line {
+            has_comments |= !trailing_docs.is_empty();
+            item.extend(trailing_docs);
+        } else {
+            // Same line as `close`: the comment follows the whole list
+            after_list.extend(trailing_docs);
+        }
+        items.push(concat(item));
+
+        if !is_last {
+            items.push(line());
+        }
+    }
+
+    // Comments on their own lines between the last element and `close`
+    let last_line = node
+        .children
+        .last()
+        .map_or(start_line, |last| last.location.end_line);
+    let dangling: Vec<usize> = ctx
+        .get_comment_indices_in_range(last_line, end_line)
+        .collect();
+    for &idx in &dangling {
+        if let Some(comment) = ctx.get_comment(idx) {
+            items.push(line());
+            items.push(leading_comment(&comment.text, false));
+        }
+    }
+    has_comments |= !dangling.is_empty();
+    ctx.mark_comments_emitted(dangling);
+
+    let inside = if padded { line } else { softline };
+    let contents = concat(vec![
+        text(open),
+        indent(concat(vec![inside(), concat(items)])),
+        inside(),
+        text(close),
+    ]);
+    let list = Doc::Group {
+        contents: Box::new(contents),
+        break_parent: has_comments,
+        id: Some(id),
+    };
+
+    Ok((list, concat(after_list)))
+}
+
 /// Checks if a node is a structural node (part of definition syntax, not body).
 ///
 /// Structural nodes are parts of class/module/method definitions that should
 /// not be emitted as body content (e.g., constant names, parameter nodes).
 pub fn is_structural_node(node: &Node) -> bool {
-    use crate::ast::NodeType;
-
     matches!(
         node.node_type,
         NodeType::ConstantReadNode
@@ -0,0 +1,280 @@
+//! ArrayRule - Formats array literals
+//!
+//! `[a, b, c]` stays on one line when it fits `line_length`, and otherwise
+//! breaks to one element per line:
+//!
+//! ```text
+//! [
+//!   a,
+//!   b,
+//!   c,
+//! ]
+//! ```
+//!
+//! The comma after the last element follows `style.trailing_comma`.
+//!
+//! Word arrays (`%w[]`, `%i[]`, `%W[]`, `%I[]`) fill instead, packing as
+//! many words per line as fit. Comments between elements stay next to the
+//! element they follow and force the broken layout.
+//!
+//! Arrays without brackets (`a = 1, 2`), empty arrays, arrays holding
+//! heredocs and arrays with multi-line elements (other than nested arrays
+//! and hashes that are laid out too) are emitted verbatim.
+
+use crate::ast::{Node, NodeType};
+use crate::doc::{concat, fill, indent, line, softline, text, Doc, GroupId};
+use crate::error::Result;
+use crate::format::context::FormatContext;
+use crate::format::registry::RuleRegistry;
+use crate::format::rule::{
+    contains_heredoc, format_delimited_list, format_embedded_child, format_leading_comments,
+    format_trailing_comment, FormatRule,
+};
+
+use super::{is_laid_out_hash, FallbackRule};
+
+/// Rule for formatting array literals.
+pub struct ArrayRule;
+
+/// Delimiters of an array this rule can lay out.
+enum ArrayStyle {
+    /// `[a, b]`
+    Brackets,
+    /// `%w[a b]` and friends; the delimiters are kept as written.
+    Words { open: String, close: String },
+}
+
+impl FormatRule for ArrayRule {
+    fn format(&self, node: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) -> Result<Doc> {
+        let Some(style) = array_style(node, ctx) else {
+            return FallbackRule.format(node, ctx, registry);
+        };
+
+        let mut docs: Vec<Doc> = Vec::with_capacity(4);
+
+        let leading = format_leading_comments(ctx, node.location.start_line);
+        if !leading.is_empty() {
+            docs.push(leading);
+        }
+
+        match style {
+            ArrayStyle::Brackets => {
+                let (array, after) = format_bracket_array(node, ctx, registry)?;
+                docs.push(array);
+                docs.push(after);
+            }
+            ArrayStyle::Words { open, close } => {
+                docs.push(format_word_array(node, ctx, open, close));
+            }
+        }
+
+        let trailing = format_trailing_comment(ctx, node.location.end_line);
+        if !trailing.is_empty() {
+            docs.push(trailing);
+        }
+
+        Ok(concat(docs))
+    }
+}
+
+/// Decides how `node` can be laid out; `None` means verbatim.
+fn array_style(node: &Node, ctx: &FormatContext) -> Option<ArrayStyle> {
+    let source = ctx.extract_source(node)?;
+    let first = node.children.first()?;
+    let last = node.children.last()?;
+    if contains_heredoc(node, ctx) {
+        return None;
+    }
+
+    if source.starts_with('[') && source.ends_with(']') {
+        let has_verbatim_multiline = node.children.iter().any(|element| {
+            element.location.start_line != element.location.end_line
+                && !is_laid_out_list(element, ctx)
+        });
+        return (!has_verbatim_multiline).then_some(ArrayStyle::Brackets);
+    }
+
+    if source.starts_with('%') {
+        let open = ctx
+            .extract_source_range(node.location.start_offset, first.location.start_offset)?
+            .trim_end();
+        let close = ctx
+            .extract_source_range(last.location.end_offset, node.location.end_offset)?
+            .trim_start();
+        return Some(ArrayStyle::Words {
+            open: open.to_string(),
+            close: close.to_string(),
+        });
+    }
+
+    None
+}
+
+/// Whether ArrayRule or HashRule lays `node` out as a delimited list
+/// rather than keeping its source.
+pub(crate) fn is_laid_out_list(node: &Node, ctx: &FormatContext) -> bool {
+    match node.node_type {
+        NodeType::ArrayNode => array_style(node, ctx).is_some(),
+        NodeType::HashNode => is_laid_out_hash(node, ctx),
+        _ => false,
+    }
+}
+
+/// Formats `[a, b, c]`. Returns the array and any comments that sat after
+/// its closing bracket on the last element's line.
+fn format_bracket_array(
+    node: &Node,
+    ctx: &mut FormatContext,
+    registry: &RuleRegistry,
+) -> Result<(Doc, Doc)> {
+    format_delimited_list(node, ctx, "[", "]", false, |element, ctx| {
+        format_embedded_child(element, ctx, registry)
+    })
+}
+
+/// Formats `%w[a b c]`, filling lines with as many words as fit.
+fn format_word_array(node: &Node, ctx: &mut FormatContext, open: String, close: String) -> Doc {
+    let mut parts: Vec<Doc> = Vec::with_capacity(node.children.len() * 2);
+    for (i, element) in node.children.iter().enumerate() {
+        if i > 0 {
+            parts.push(line());
+        }
+        parts.push(text(ctx.extract_source(element).unwrap_or_default()));
+    }
+
+    let contents = concat(vec![
+        text(open),
+        indent(concat(vec![softline(), fill(parts)])),
+        softline(),
+        text(close),
+    ]);
+    Doc::Group {
+        contents: Box::new(contents),
+        break_parent: false,
+        id: Some(GroupId(ctx.next_group_id())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Comment, CommentPosition, CommentType};
+    use crate::config::Config;
+    use crate::format::rules::test_support::{format_node, location, node};
+
+    /// An array spanning all of `source` whose elements are the given
+    /// substrings (each found after the previous one) and whose comments
+    /// are every `#` to the end of its line.
+    fn array(source: &str, element_type: NodeType, elements: &[&str]) -> Node {
+        let mut children = Vec::with_capacity(elements.len());
+        let mut offset = 0;
+        for element in elements {
+            let start = offset + source[offset..].find(element).unwrap();
+            offset = start + element.len();
+            children.push(node(element_type.clone(), start, offset, Vec::new()));
+        }
+        let mut array = node(NodeType::ArrayNode, 0, source.len(), children);
+        let mut line_start = 0;
+        for line in source.split('\n') {
+            if let Some(hash) = line.find('#') {
+                let start = line_start + hash;
+                array.comments.push(Comment {
+                    text: line[hash..].to_string(),
+                    location: location(source, start, line_start + line.len()),
+                    comment_type: CommentType::Line,
+                    position: CommentPosition::Trailing,
+                });
+            }
+            line_start += line.len() + 1;
+        }
+        array
+    }
+
+    #[test]
+    fn test_short_array_stays_flat() {
+        let config = Config::default();
+        let source = "[1,2,\n  3]";
+        let node = array(source, NodeType::IntegerNode, &["1", "2", "3"]);
+        assert_eq!(format_node(&config, source, &node), "[1, 2, 3]\n");
+    }
+
+    #[test]
+    fn test_long_array_breaks_one_element_per_line() {
+        let mut config = Config::default();
+        config.formatting.line_length = 40;
+        let elements: Vec<String> = (100000..100008).map(|n| n.to_string()).collect();
+        let refs: Vec<&str> = elements.iter().map(String::as_str).collect();
+        let source = format!("[{}]", refs.join(", "));
+        let node = array(&source, NodeType::IntegerNode, &refs);
+        assert_eq!(
+            format_node(&config, &source, &node),
+            format!("[\n  {},\n]\n", refs.join(",\n  "))
+        );
+    }
+
+    #[test]
+    fn test_trailing_comma_styles() {
+        use crate::config::TrailingComma;
+
+        let mut config = Config::default();
+        config.formatting.line_length = 12;
+        let source = "[1, 22, 333, 4444,]";
+        let node = array(source, NodeType::IntegerNode, &["1", "22", "333", "4444"]);
+
+        config.formatting.style.trailing_comma = TrailingComma::Never;
+        assert_eq!(
+            format_node(&config, source, &node),
+            "[\n  1,\n  22,\n  333,\n  4444\n]\n"
+        );
+
+        config.formatting.style.trailing_comma = TrailingComma::Always;
+        config.formatting.line_length = 100;
+        assert_eq!(format_node(&config, source, &node), "[1, 22, 333, 4444,]\n");
+
+        config.formatting.style.trailing_comma = TrailingComma::Multiline;
+        assert_eq!(format_node(&config, source, &node), "[1, 22, 333, 4444]\n");
+    }
+
+    #[test]
+    fn test_comments_between_elements_are_kept() {
+        let config = Config::default();
+        let source = "[\n  1, # one\n  # before two\n  2\n  # after two\n]";
+        let node = array(source, NodeType::IntegerNode, &["1", "2"]);
+        assert_eq!(
+            format_node(&config, source, &node),
+            "[\n  1, # one\n  # before two\n  2,\n  # after two\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_comment_after_closing_bracket_stays_outside() {
+        let config = Config::default();
+        let source = "[1, 2] # note";
+        let mut node = array(source, NodeType::IntegerNode, &["1", "2"]);
+        // The statement, not the array, ends at the comment
+        node.location = location(source, 0, 6);
+        assert_eq!(format_node(&config, source, &node), "[1, 2] # note\n");
+    }
+
+    #[test]
+    fn test_word_array_fills_lines() {
+        let mut config = Config::default();
+        config.formatting.line_length = 40;
+        let words: Vec<String> = (0..12).map(|i| format!("word{:02}", i)).collect();
+        let refs: Vec<&str> = words.iter().map(String::as_str).collect();
+        let source = format!("%w[{}]", refs.join(" "));
+        let node = array(&source, NodeType::StringNode, &refs);
+        assert_eq!(
+            format_node(&config, &source, &node),
+            "%w[\n  word00 word01 word02 word03 word04\n  word05 word06 word07 word08 word09\n  word10 word11\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_bracketless_array_is_verbatim() {
+        let config = Config::default();
+        let source = "1,  2";
+        let node = array(source, NodeType::IntegerNode, &["1", "2"]);
+        assert_eq!(format_node(&config, source, &node), "1,  2\n");
+    }
+}
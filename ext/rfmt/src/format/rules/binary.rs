@@ -0,0 +1,363 @@
+//! BinaryRule - Formats boolean and binary operator expressions
+//!
+//! Handles:
+//! - Boolean operators: `a && b`, `a || b`, `a and b`, `a or b`
+//! - Binary operator calls: `a + b`, `a == b`, `a << b`, ...
+//!
+//! A chain of operators at the same precedence level is flattened into one
+//! group with single spaces around each operator. When the group does not
+//! fit `line_length` it breaks after every operator, continuing one indent
+//! deeper:
+//!
+//! ```text
+//! valid = name.present? &&
+//!   email.present? &&
+//!   terms_accepted?
+//! ```
+//!
+//! Operands at a different precedence level get their own group, and
+//! parenthesized subexpressions keep their parentheses, so the parse never
+//! changes; only the whitespace between tokens does.
+
+use crate::ast::{Node, NodeType};
+use crate::doc::{concat, group, indent, line, text, Doc};
+use crate::error::Result;
+use crate::format::context::FormatContext;
+use crate::format::registry::RuleRegistry;
+use crate::format::rule::{
+    contains_heredoc, format_embedded_expression, format_leading_comments, format_trailing_comment,
+    has_comments_within, mark_comments_in_range_emitted, FormatRule,
+};
+
+use super::FallbackRule;
+
+/// Rule for formatting `AndNode` and `OrNode` expressions.
+pub struct BinaryRule;
+
+/// Binary operators and their precedence, tighter binding first.
+const OPERATORS: [(&str, u8); 25] = [
+    ("**", 11),
+    ("*", 10),
+    ("/", 10),
+    ("%", 10),
+    ("+", 9),
+    ("-", 9),
+    ("<<", 8),
+    (">>", 8),
+    ("&", 7),
+    ("|", 6),
+    ("^", 6),
+    (">", 5),
+    (">=", 5),
+    ("<", 5),
+    ("<=", 5),
+    ("<=>", 4),
+    ("==", 4),
+    ("===", 4),
+    ("!=", 4),
+    ("=~", 4),
+    ("!~", 4),
+    ("&&", 3),
+    ("||", 2),
+    ("and", 1),
+    ("or", 1),
+];
+
+impl FormatRule for BinaryRule {
+    fn format(&self, node: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) -> Result<Doc> {
+        let Some(binary_doc) = format_binary(node, ctx, registry)? else {
+            return FallbackRule.format(node, ctx, registry);
+        };
+
+        let mut docs: Vec<Doc> = Vec::with_capacity(3);
+
+        let leading = format_leading_comments(ctx, node.location.start_line);
+        if !leading.is_empty() {
+            docs.push(leading);
+        }
+
+        docs.push(binary_doc);
+        mark_comments_in_range_emitted(ctx, node.location.start_line, node.location.end_line);
+
+        let trailing = format_trailing_comment(ctx, node.location.end_line);
+        if !trailing.is_empty() {
+            docs.push(trailing);
+        }
+
+        Ok(concat(docs))
+    }
+}
+
+/// A flattened run of operators at one precedence level:
+/// `operands[0] operators[0] operands[1] operators[1] ...`.
+struct Chain<'n> {
+    operands: Vec<&'n Node>,
+    operators: Vec<&'static str>,
+}
+
+/// Lays out `node` as a binary expression, without its comments.
+///
+/// Returns `None` for anything that is not a binary expression, and for
+/// expressions left verbatim: comments or heredocs inside, or an operand
+/// spanning lines.
+pub(crate) fn format_binary(
+    node: &Node,
+    ctx: &mut FormatContext,
+    registry: &RuleRegistry,
+) -> Result<Option<Doc>> {
+    let Some(chain) = flatten(node, ctx) else {
+        return Ok(None);
+    };
+    if has_comments_within(node, ctx) || contains_heredoc(node, ctx) || !can_layout(&chain, ctx) {
+        return Ok(None);
+    }
+    chain_doc(chain, ctx, registry).map(Some)
+}
+
+/// Whether `node` is an expression `format_binary` may lay out.
+pub(crate) fn is_binary_expression(node: &Node, ctx: &FormatContext) -> bool {
+    binary_parts(node, ctx).is_some()
+}
+
+fn chain_doc(chain: Chain, ctx: &mut FormatContext, registry: &RuleRegistry) -> Result<Doc> {
+    let mut operands = chain.operands.into_iter();
+    let mut docs = Vec::with_capacity(2);
+    if let Some(first) = operands.next() {
+        docs.push(format_operand(first, ctx, registry)?);
+    }
+
+    let mut rest = Vec::with_capacity(chain.operators.len() * 3);
+    for (operator, operand) in chain.operators.into_iter().zip(operands) {
+        rest.push(text(format!(" {}", operator)));
+        rest.push(line());
+        rest.push(format_operand(operand, ctx, registry)?);
+    }
+    docs.push(indent(concat(rest)));
+
+    Ok(group(concat(docs)))
+}
+
+/// Formats one operand of a chain: nested binary expressions (bare or in
+/// parentheses) get their own group, anything else goes through
+/// `format_embedded_expression`.
+fn format_operand(operand: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) -> Result<Doc> {
+    if let Some(nested) = flatten(operand, ctx) {
+        return chain_doc(nested, ctx, registry);
+    }
+    if let Some(nested) = parenthesized_chain(operand, ctx) {
+        return Ok(concat(vec![
+            text("("),
+            chain_doc(nested, ctx, registry)?,
+            text(")"),
+        ]));
+    }
+
+    format_embedded_expression(operand, ctx, registry)
+}
+
+/// Checks that every operand in the chain, nested ones included, sits on a
+/// single line.
+fn can_layout(chain: &Chain, ctx: &FormatContext) -> bool {
+    chain.operands.iter().all(|operand| {
+        match flatten(operand, ctx).or_else(|| parenthesized_chain(operand, ctx)) {
+            Some(nested) => can_layout(&nested, ctx),
+            None => operand.location.start_line == operand.location.end_line,
+        }
+    })
+}
+
+/// Flattens `node` and the same-precedence expressions nested on its left
+/// (`a + b - c` parses as `(a + b) - c`) into one chain.
+fn flatten<'n>(node: &'n Node, ctx: &FormatContext) -> Option<Chain<'n>> {
+    let (left, operator, right) = binary_parts(node, ctx)?;
+    let level = precedence(operator);
+    let mut chain = match binary_parts(left, ctx) {
+        Some((_, left_operator, _)) if precedence(left_operator) == level => flatten(left, ctx)?,
+        _ => Chain {
+            operands: vec![left],
+            operators: Vec::new(),
+        },
+    };
+    chain.operators.push(operator);
+    chain.operands.push(right);
+    Some(chain)
+}
+
+/// The chain inside `( ... )` when a parenthesized operand holds exactly
+/// one binary expression.
+fn parenthesized_chain<'n>(node: &'n Node, ctx: &FormatContext) -> Option<Chain<'n>> {
+    if !matches!(node.node_type, NodeType::ParenthesesNode) {
+        return None;
+    }
+    let [body] = node.children.as_slice() else {
+        return None;
+    };
+    let inner = match body.node_type {
+        NodeType::StatementsNode => match body.children.as_slice() {
+            [inner] => inner,
+            _ => return None,
+        },
+        _ => body,
+    };
+    let before =
+        ctx.extract_source_range(node.location.start_offset, inner.location.start_offset)?;
+    let after = ctx.extract_source_range(inner.location.end_offset, node.location.end_offset)?;
+    if before.trim_end() != "(" || after.trim_start() != ")" {
+        return None;
+    }
+    flatten(inner, ctx)
+}
+
+/// Splits a binary expression into `(left, operator, right)`, or returns
+/// `None` when `node` is not one written in infix form.
+fn binary_parts<'n>(
+    node: &'n Node,
+    ctx: &FormatContext,
+) -> Option<(&'n Node, &'static str, &'n Node)> {
+    let (left, right) = match node.node_type {
+        NodeType::AndNode | NodeType::OrNode => match node.children.as_slice() {
+            [left, right] => (left, right),
+            _ => return None,
+        },
+        NodeType::CallNode => {
+            let [receiver, arguments] = node.children.as_slice() else {
+                return None;
+            };
+            if !matches!(arguments.node_type, NodeType::ArgumentsNode) {
+                return None;
+            }
+            let [argument] = arguments.children.as_slice() else {
+                return None;
+            };
+            (receiver, argument)
+        }
+        _ => return None,
+    };
+
+    // The operator as written: `a.+(b)` is a call, not an infix expression
+    let written = ctx
+        .extract_source_range(left.location.end_offset, right.location.start_offset)?
+        .trim();
+    let operator = match node.node_type {
+        NodeType::AndNode => ["&&", "and"].into_iter().find(|op| *op == written)?,
+        NodeType::OrNode => ["||", "or"].into_iter().find(|op| *op == written)?,
+        _ => {
+            let name = node.metadata.get("name")?;
+            OPERATORS
+                .iter()
+                .map(|(op, _)| *op)
+                .find(|op| *op == name.as_str() && *op == written)?
+        }
+    };
+    Some((left, operator, right))
+}
+
+fn precedence(operator: &str) -> u8 {
+    OPERATORS
+        .iter()
+        .find(|(op, _)| *op == operator)
+        .map_or(0, |(_, level)| *level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::format::rules::test_support::{format_with_width, node};
+
+    fn var(start: usize, end: usize) -> Node {
+        node(NodeType::LocalVariableReadNode, start, end, Vec::new())
+    }
+
+    fn boolean(node_type: NodeType, left: Node, right: Node) -> Node {
+        let (start, end) = (left.location.start_offset, right.location.end_offset);
+        node(node_type, start, end, vec![left, right])
+    }
+
+    fn operator_call(name: &str, left: Node, right: Node) -> Node {
+        let (start, end) = (left.location.start_offset, right.location.end_offset);
+        let arguments = node(
+            NodeType::ArgumentsNode,
+            right.location.start_offset,
+            end,
+            vec![right],
+        );
+        let mut call = node(NodeType::CallNode, start, end, vec![left, arguments]);
+        call.metadata.insert("name".to_string(), name.to_string());
+        call
+    }
+
+    #[test]
+    fn test_spacing_is_normalized() {
+        // (alpha && beta) || gamma
+        let source = "alpha&&beta  ||gamma";
+        let expr = boolean(
+            NodeType::OrNode,
+            boolean(NodeType::AndNode, var(0, 5), var(7, 11)),
+            var(15, 20),
+        );
+        assert_eq!(
+            format_with_width(source, &expr, 100),
+            "alpha && beta || gamma\n"
+        );
+    }
+
+    #[test]
+    fn test_same_precedence_chain_breaks_after_each_operator() {
+        // (first_condition && second_condition) && third_condition
+        let source = "first_condition && second_condition && third_condition";
+        let expr = boolean(
+            NodeType::AndNode,
+            boolean(NodeType::AndNode, var(0, 15), var(19, 35)),
+            var(39, 54),
+        );
+        assert_eq!(
+            format_with_width(source, &expr, 30),
+            "first_condition &&\n  second_condition &&\n  third_condition\n"
+        );
+    }
+
+    #[test]
+    fn test_lower_precedence_breaks_first() {
+        // alpha || (beta && gamma)
+        let source = "alpha || beta && gamma";
+        let expr = boolean(
+            NodeType::OrNode,
+            var(0, 5),
+            boolean(NodeType::AndNode, var(9, 13), var(17, 22)),
+        );
+        assert_eq!(
+            format_with_width(source, &expr, 16),
+            "alpha ||\n  beta && gamma\n"
+        );
+    }
+
+    #[test]
+    fn test_operator_calls_keep_precedence() {
+        // a + (b * c), from `a+b*c`
+        let source = "a+b*c";
+        let expr = operator_call("+", var(0, 1), operator_call("*", var(2, 3), var(4, 5)));
+        assert_eq!(format_with_width(source, &expr, 100), "a + b * c\n");
+    }
+
+    #[test]
+    fn test_parentheses_are_kept() {
+        // (a+b) * c
+        let source = "(a+b) * c";
+        let sum = operator_call("+", var(1, 2), var(3, 4));
+        let statements = node(NodeType::StatementsNode, 1, 4, vec![sum]);
+        let parens = node(NodeType::ParenthesesNode, 0, 5, vec![statements]);
+        let expr = operator_call("*", parens, var(8, 9));
+        assert_eq!(format_with_width(source, &expr, 100), "(a + b) * c\n");
+    }
+
+    #[test]
+    fn test_method_call_syntax_is_not_an_operator() {
+        let source = "a.+(b)";
+        let expr = operator_call("+", var(0, 1), var(4, 5));
+        assert!(!is_binary_expression(
+            &expr,
+            &FormatContext::new(&Config::default(), source)
+        ));
+    }
+}
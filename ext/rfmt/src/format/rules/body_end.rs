@@ -25,6 +25,9 @@ pub struct BodyEndConfig<'a> {
     pub header_builder: Box<dyn Fn(&'a Node) -> Vec<Doc> + 'a>,
     /// Optional filter for which children are considered structural (skipped in body)
     pub skip_same_line_children: bool,
+    /// Set when `header_builder` lays the parameters out itself, so a
+    /// multi-line parameter list is re-wrapped rather than copied verbatim.
+    pub rebuilds_parameters: bool,
 }
 
 /// Formats a body-with-end construct (class, module, def).
@@ -96,7 +99,8 @@ pub fn format_body_end(
     // relocated comments keep accumulating every format pass. Detect this
     // case early and emit the header verbatim from source.
     let params_text = config.node.metadata.get("parameters_text");
-    let header_is_multiline = params_text.is_some_and(|t| t.contains('\n'));
+    let header_is_multiline =
+        !config.rebuilds_parameters && params_text.is_some_and(|t| t.contains('\n'));
     if header_is_multiline {
         let header_end_line = body_children
             .first()
@@ -2,20 +2,27 @@
 //!
 //! Handles:
 //! - Simple calls: `foo.bar`
+//! - Argument lists that break when they exceed `line_length`: `foo(a, b)`
+//! - Binary operator calls: `a + b` (see `binary.rs`)
 //! - Calls with blocks: `foo.bar do ... end` or `foo.bar { ... }`
 //! - Method chains: `foo.bar.baz`
 
 use crate::ast::{Node, NodeType};
-use crate::doc::{align, concat, hardline, indent, text, Doc};
+use crate::doc::{
+    align, concat, group, group_with_id, hardline, indent, join, line, softline, text, Doc, GroupId,
+};
 use crate::error::Result;
 use crate::format::context::FormatContext;
 use crate::format::registry::RuleRegistry;
 use crate::format::rule::{
-    chain_doc_or_verbatim, format_child, format_comments_before_end, format_leading_comments,
-    format_statements, format_trailing_comment, mark_comments_in_range_emitted, reformat_chain_doc,
-    FormatRule,
+    accepts_trailing_comma, chain_doc_or_verbatim, contains_heredoc, format_child,
+    format_comments_before_end, format_embedded_expression, format_leading_comments,
+    format_statements, format_trailing_comment, has_comments_within,
+    mark_comments_in_range_emitted, reformat_chain_doc, trailing_comma, FormatRule,
 };
 
+use super::{format_binary, hash_syntax_source, hash_syntax_source_in};
+
 /// Rule for formatting method calls.
 pub struct CallRule;
 
@@ -111,8 +118,12 @@ fn format_call(node: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) ->
         // Strip at most one trailing newline so this doesn't happen; using
         // the full `trim_end` here would instead eat a blank separator line
         // that legitimately belongs between statements.
-        if let Some(source_text) = ctx.extract_source(node) {
-            docs.push(chain_doc_or_verbatim(source_text));
+        if let Some(binary_doc) = format_binary(node, ctx, registry)? {
+            docs.push(binary_doc);
+        } else if let Some(call_doc) = format_call_arguments(node, ctx, registry)? {
+            docs.push(call_doc);
+        } else if let Some(source_text) = hash_syntax_source(node, ctx) {
+            docs.push(chain_doc_or_verbatim(&source_text));
         }
 
         // Mark comments in this range as emitted (they're in source extraction)
@@ -181,6 +192,181 @@ fn format_call(node: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) ->
     Ok(concat(docs))
 }
 
+/// One entry of a call's argument list.
+enum Argument<'n> {
+    Node(&'n Node),
+    /// A `key: value` / `**opts` pair of a keyword argument list, already
+    /// normalized to `style.hash_syntax`.
+    Pair(String),
+}
+
+/// The pieces of a block-less call that `format_call_arguments` lays out.
+struct ArgumentList<'n> {
+    /// Everything ahead of the first argument: `foo(` or `foo `.
+    prefix: String,
+    parenthesized: bool,
+    arguments: Vec<Argument<'n>>,
+    /// The last argument node, which decides whether a trailing comma is
+    /// allowed.
+    last: &'n Node,
+}
+
+/// Lays out a block-less call's arguments so that they break when the call
+/// does not fit `line_length`, and re-join when it does:
+///
+/// ```text
+/// foo(                     validates :name,
+///   first,                   presence: true,
+///   second,                  length: { maximum: 50 }
+/// )
+/// ```
+///
+/// With parentheses every argument goes on its own line; without them the
+/// first argument stays on the call line, as DSL calls are written. Each
+/// keyword argument counts as one argument.
+///
+/// Returns `None` for calls left verbatim: operator calls (laid out by
+/// `format_binary` instead), index and attribute write calls, receivers or
+/// arguments spanning lines, heredocs, and comments anywhere inside the
+/// call.
+fn format_call_arguments(
+    node: &Node,
+    ctx: &mut FormatContext,
+    registry: &RuleRegistry,
+) -> Result<Option<Doc>> {
+    let Some(list) = argument_list(node, ctx) else {
+        return Ok(None);
+    };
+
+    let mut arguments: Vec<Doc> = Vec::with_capacity(list.arguments.len());
+    for argument in &list.arguments {
+        arguments.push(match argument {
+            Argument::Node(arg) => format_embedded_expression(arg, ctx, registry)?,
+            Argument::Pair(source) => text(source.as_str()),
+        });
+    }
+
+    if list.parenthesized {
+        let id = GroupId(ctx.next_group_id());
+        let mut contents = vec![softline(), join(concat(vec![text(","), line()]), arguments)];
+        if accepts_trailing_comma(list.last) {
+            contents.push(trailing_comma(ctx, id));
+        }
+        return Ok(Some(group_with_id(
+            concat(vec![
+                text(list.prefix),
+                indent(concat(contents)),
+                softline(),
+                text(")"),
+            ]),
+            id,
+        )));
+    }
+
+    let mut arguments = arguments.into_iter();
+    let mut docs = vec![text(list.prefix)];
+    docs.extend(arguments.next());
+    let rest: Vec<Doc> = arguments
+        .flat_map(|argument| [text(","), line(), argument])
+        .collect();
+    docs.push(indent(concat(rest)));
+    Ok(Some(group(concat(docs))))
+}
+
+/// Collects the argument list of `node`, or `None` when the call has to
+/// stay verbatim (see `format_call_arguments`).
+fn argument_list<'n>(node: &'n Node, ctx: &FormatContext) -> Option<ArgumentList<'n>> {
+    // Method names only: operators (`a + b`), index calls (`a[1]`) and
+    // attribute writes (`a.b = 1`) keep their source
+    let name = node.metadata.get("name")?;
+    let is_method_name = name
+        .chars()
+        .next()
+        .is_some_and(|c| c == '_' || c.is_alphabetic() || !c.is_ascii());
+    if !is_method_name || name.ends_with('=') {
+        return None;
+    }
+
+    let args = node
+        .children
+        .iter()
+        .filter(|child| matches!(child.node_type, NodeType::ArgumentsNode))
+        .flat_map(|args| args.children.iter());
+    let block_arg = node
+        .children
+        .iter()
+        .filter(|child| matches!(child.node_type, NodeType::BlockArgumentNode));
+    let nodes: Vec<&Node> = args.chain(block_arg).collect();
+    let (first, last) = (*nodes.first()?, *nodes.last()?);
+
+    if has_comments_within(node, ctx) || contains_heredoc(node, ctx) {
+        return None;
+    }
+
+    let prefix = hash_syntax_source_in(
+        node,
+        node.location.start_offset,
+        first.location.start_offset,
+        ctx,
+    )?;
+    if prefix.contains('\n') {
+        return None;
+    }
+    let suffix = ctx.extract_source_range(last.location.end_offset, node.location.end_offset)?;
+    let trimmed = prefix.trim_end();
+    let parenthesized = trimmed.ends_with('(');
+    if parenthesized {
+        let suffix = suffix.trim_start();
+        let suffix = suffix.strip_prefix(',').unwrap_or(suffix).trim_start();
+        if suffix != ")" {
+            return None;
+        }
+    } else {
+        // `foo arg`: the name must end right before the space
+        let ends_with_name = trimmed
+            .chars()
+            .last()
+            .is_some_and(|c| c == '_' || c == '?' || c == '!' || c.is_alphanumeric());
+        if !ends_with_name || trimmed.len() == prefix.len() || !suffix.is_empty() {
+            return None;
+        }
+    }
+
+    let mut arguments = Vec::with_capacity(nodes.len());
+    for arg in nodes {
+        if matches!(arg.node_type, NodeType::KeywordHashNode) {
+            for pair in &arg.children {
+                if pair.location.start_line != pair.location.end_line {
+                    return None;
+                }
+                let source = hash_syntax_source_in(
+                    arg,
+                    pair.location.start_offset,
+                    pair.location.end_offset,
+                    ctx,
+                )?;
+                arguments.push(Argument::Pair(source.into_owned()));
+            }
+        } else if arg.location.start_line != arg.location.end_line {
+            return None;
+        } else {
+            arguments.push(Argument::Node(arg));
+        }
+    }
+
+    let prefix = if parenthesized {
+        trimmed.to_string()
+    } else {
+        format!("{} ", trimmed)
+    };
+    Some(ArgumentList {
+        prefix,
+        parenthesized,
+        arguments,
+        last,
+    })
+}
+
 /// Detect whether block uses do...end or { } style
 fn detect_block_style(block_node: &Node, ctx: &FormatContext) -> BlockStyle {
     if let Some(first_char) = ctx
@@ -376,9 +562,10 @@ fn extract_block_parameters(block_node: &Node, ctx: &FormatContext) -> Option<St
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{FormattingInfo, Location};
+    use crate::ast::{Comment, CommentPosition, CommentType, FormattingInfo, Location};
     use crate::config::Config;
     use crate::doc::Printer;
+    use crate::format::rules::test_support::{format_with_width, location, node};
     use std::collections::HashMap;
 
     fn make_call_node(
@@ -398,6 +585,141 @@ mod tests {
         }
     }
 
+    /// A single-line call named `name` over all of `source`, whose
+    /// arguments are the given nodes.
+    fn call_with_args(name: &str, source: &str, args: Vec<Node>, block_arg: Option<Node>) -> Node {
+        let mut call = node(NodeType::CallNode, 0, source.len(), Vec::new());
+        call.metadata.insert("name".to_string(), name.to_string());
+        if let (Some(first), Some(last)) = (args.first(), args.last()) {
+            call.children.push(node(
+                NodeType::ArgumentsNode,
+                first.location.start_offset,
+                last.location.end_offset,
+                args,
+            ));
+        }
+        call.children.extend(block_arg);
+        call
+    }
+
+    #[test]
+    fn test_call_arguments_rejoin_when_they_fit() {
+        let source = "foo(alpha,\n  beta)";
+        let call = call_with_args(
+            "foo",
+            source,
+            vec![
+                node(NodeType::LocalVariableReadNode, 4, 9, Vec::new()),
+                node(NodeType::LocalVariableReadNode, 13, 17, Vec::new()),
+            ],
+            None,
+        );
+        assert_eq!(format_with_width(source, &call, 100), "foo(alpha, beta)\n");
+    }
+
+    #[test]
+    fn test_call_arguments_break_one_per_line() {
+        let source = "foo(alpha, beta)";
+        let call = call_with_args(
+            "foo",
+            source,
+            vec![
+                node(NodeType::LocalVariableReadNode, 4, 9, Vec::new()),
+                node(NodeType::LocalVariableReadNode, 11, 15, Vec::new()),
+            ],
+            None,
+        );
+        assert_eq!(
+            format_with_width(source, &call, 10),
+            "foo(\n  alpha,\n  beta,\n)\n"
+        );
+    }
+
+    #[test]
+    fn test_call_arguments_no_trailing_comma_after_block_pass() {
+        let source = "foo(alpha, &blk)";
+        let call = call_with_args(
+            "foo",
+            source,
+            vec![node(NodeType::LocalVariableReadNode, 4, 9, Vec::new())],
+            Some(node(NodeType::BlockArgumentNode, 11, 15, Vec::new())),
+        );
+        assert_eq!(
+            format_with_width(source, &call, 10),
+            "foo(\n  alpha,\n  &blk\n)\n"
+        );
+    }
+
+    #[test]
+    fn test_parenless_call_keeps_first_argument_on_call_line() {
+        let source = "validates :name, presence: true";
+        let children = vec![
+            node(NodeType::SymbolNode, 17, 26, Vec::new()),
+            node(NodeType::TrueNode, 27, 31, Vec::new()),
+        ];
+        let mut pair = node(NodeType::AssocNode, 17, 31, children);
+        pair.metadata
+            .insert("key_kind".to_string(), "label".to_string());
+        let keywords = node(NodeType::KeywordHashNode, 17, 31, vec![pair]);
+        let call = call_with_args(
+            "validates",
+            source,
+            vec![node(NodeType::SymbolNode, 10, 15, Vec::new()), keywords],
+            None,
+        );
+        assert_eq!(
+            format_with_width(source, &call, 100),
+            format!("{}\n", source)
+        );
+        assert_eq!(
+            format_with_width(source, &call, 20),
+            "validates :name,\n  presence: true\n"
+        );
+    }
+
+    #[test]
+    fn test_operator_call_is_laid_out_as_binary_expression() {
+        let source = "alpha +  beta";
+        let mut call = call_with_args(
+            "+",
+            source,
+            vec![node(NodeType::LocalVariableReadNode, 9, 13, Vec::new())],
+            None,
+        );
+        call.children
+            .insert(0, node(NodeType::LocalVariableReadNode, 0, 5, Vec::new()));
+        assert_eq!(format_with_width(source, &call, 100), "alpha + beta\n");
+        assert_eq!(format_with_width(source, &call, 5), "alpha +\n  beta\n");
+    }
+
+    #[test]
+    fn test_argument_does_not_claim_statement_trailing_comment() {
+        let source = "foo(bar(1), 2) # note";
+        let mut inner = call_with_args(
+            "bar",
+            "bar(1)",
+            vec![node(NodeType::IntegerNode, 8, 9, Vec::new())],
+            None,
+        );
+        inner.location = location(source, 4, 10);
+        let mut call = call_with_args(
+            "foo",
+            "foo(bar(1), 2)",
+            vec![inner, node(NodeType::IntegerNode, 12, 13, Vec::new())],
+            None,
+        );
+        call.comments.push(Comment {
+            text: "# note".to_string(),
+            location: location(source, 15, 21),
+            comment_type: CommentType::Line,
+            position: CommentPosition::Trailing,
+        });
+        assert_eq!(
+            format_with_width(source, &call, 100),
+            "foo(bar(1), 2) # note\n"
+        );
+    }
+
     #[test]
     fn test_simple_call() {
         let config = Config::default();
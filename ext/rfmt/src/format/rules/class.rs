@@ -28,6 +28,7 @@ impl FormatRule for ClassRule {
                 node,
                 header_builder: Box::new(build_class_header),
                 skip_same_line_children: true,
+                rebuilds_parameters: false,
             },
         )
     }
@@ -5,30 +5,44 @@
 //! - Class methods: `def self.foo`
 //! - Methods with parameters: `def foo(x, y)` or `def foo x, y`
 //! - Method bodies
+//! - Endless methods: `def foo = expr` (kept as written)
 //! - Leading and trailing comments
+//!
+//! A parenthesized parameter list is laid out from the parameter children
+//! and breaks to one parameter per line when the header exceeds
+//! `line_length`. Lists with comments inside, parameters spanning lines or
+//! destructuring parameters keep their source text.
 
 use crate::ast::Node;
-use crate::doc::{text, Doc};
+use crate::doc::{concat, group, indent, join, line, softline, text, Doc};
 use crate::error::Result;
 use crate::format::context::FormatContext;
 use crate::format::registry::RuleRegistry;
-use crate::format::rule::FormatRule;
+use crate::format::rule::{contains_heredoc, is_structural_node, FormatRule};
 
 use super::body_end::{format_body_end, BodyEndConfig};
+use super::FallbackRule;
 
 /// Rule for formatting method definitions.
 pub struct DefRule;
 
 impl FormatRule for DefRule {
     fn format(&self, node: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) -> Result<Doc> {
+        if node.metadata.get("endless").is_some_and(|v| v == "true") {
+            return FallbackRule.format(node, ctx, registry);
+        }
+
+        let parameters = parameter_list(node, ctx).map(parameters_doc);
+        let rebuilds_parameters = parameters.is_some();
         format_body_end(
             ctx,
             registry,
             BodyEndConfig {
                 keyword: "def",
                 node,
-                header_builder: Box::new(build_def_header),
+                header_builder: Box::new(move |node| build_def_header(node, parameters.clone())),
                 skip_same_line_children: false,
+                rebuilds_parameters,
             },
         )
     }
@@ -36,8 +50,9 @@ impl FormatRule for DefRule {
 
 /// Builds the header portion for a method definition.
 ///
-/// Returns: `[receiver.]name[(params)]`
-fn build_def_header(node: &Node) -> Vec<Doc> {
+/// Returns: `[receiver.]name[(params)]`, with `parameters` (from
+/// `parameters_doc`) in place of the source parameter text when given.
+fn build_def_header(node: &Node, parameters: Option<Doc>) -> Vec<Doc> {
     let mut parts: Vec<Doc> = Vec::with_capacity(6);
 
     // Get receiver if class method (def self.method_name)
@@ -51,6 +66,11 @@ fn build_def_header(node: &Node) -> Vec<Doc> {
         parts.push(text(name));
     }
 
+    if let Some(parameters) = parameters {
+        parts.push(parameters);
+        return parts;
+    }
+
     // Get parameters from metadata
     if let Some(params_text) = node.metadata.get("parameters_text") {
         let has_parens = node.metadata.get("has_parens").is_some_and(|v| v == "true");
@@ -68,10 +88,90 @@ fn build_def_header(node: &Node) -> Vec<Doc> {
     parts
 }
 
+/// `(a, b)` that breaks to one parameter per line when it does not fit.
+/// No trailing comma: Ruby rejects one after the last parameter.
+fn parameters_doc(parameters: Vec<String>) -> Doc {
+    let parameters = parameters.into_iter().map(text).collect();
+    group(concat(vec![
+        text("("),
+        indent(concat(vec![
+            softline(),
+            join(concat(vec![text(","), line()]), parameters),
+        ])),
+        softline(),
+        text(")"),
+    ]))
+}
+
+/// Source text of each parameter of a parenthesized list, or `None` when
+/// the list has to keep its source text: something other than commas and
+/// whitespace between the parameters (comments), a parameter spanning
+/// lines or holding a heredoc, a destructuring parameter, or a comment
+/// after `)` on a header that spans lines.
+fn parameter_list(node: &Node, ctx: &FormatContext) -> Option<Vec<String>> {
+    if node.metadata.get("has_parens").is_none_or(|v| v != "true") {
+        return None;
+    }
+
+    let parameters: Vec<&Node> = node
+        .children
+        .iter()
+        .filter(|child| is_structural_node(child))
+        .collect();
+    let (first, last) = (*parameters.first()?, *parameters.last()?);
+    // `def foo((a, b))` yields a MultiTargetNode among the parameters
+    let has_other_parameter = node.children.iter().any(|child| {
+        !is_structural_node(child) && child.location.start_offset < last.location.end_offset
+    });
+    if has_other_parameter {
+        return None;
+    }
+
+    let source = ctx.source();
+    let before = source.get(node.location.start_offset..first.location.start_offset)?;
+    if !before.trim_end().ends_with('(') {
+        return None;
+    }
+
+    let mut texts = Vec::with_capacity(parameters.len());
+    for (i, parameter) in parameters.iter().enumerate() {
+        if parameter.location.start_line != parameter.location.end_line
+            || contains_heredoc(parameter, ctx)
+        {
+            return None;
+        }
+        if let Some(next) = parameters.get(i + 1) {
+            let between = source.get(parameter.location.end_offset..next.location.start_offset)?;
+            if between.trim() != "," {
+                return None;
+            }
+        }
+        texts.push(ctx.extract_source(parameter)?.to_string());
+    }
+
+    let after = source.get(last.location.end_offset..)?;
+    let rest = after.trim_start();
+    if !rest.starts_with(')') {
+        return None;
+    }
+    let paren_line =
+        last.location.end_line + after[..after.len() - rest.len()].matches('\n').count();
+    if paren_line > node.location.start_line
+        && ctx
+            .get_comment_indices_in_range(node.location.start_line, paren_line + 1)
+            .next()
+            .is_some()
+    {
+        return None;
+    }
+
+    Some(texts)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{FormattingInfo, Location, NodeType};
+    use crate::ast::{Comment, CommentPosition, CommentType, FormattingInfo, Location, NodeType};
     use crate::config::Config;
     use crate::doc::Printer;
     use std::collections::HashMap;
@@ -106,6 +206,118 @@ mod tests {
         }
     }
 
+    /// A `def foo(...)` over all of `source` whose parameter children are
+    /// the given substrings, with `parameters_text` sliced from `(` to `)`
+    /// as the adapter does.
+    fn def_with_parameters(source: &str, parameters: &[(&str, NodeType)]) -> Node {
+        let open = source.find('(').unwrap();
+        let close = source.find(')').unwrap();
+        let end_line = source.matches('\n').count() + 1;
+        let mut node = make_def_node(
+            "foo",
+            None,
+            Some((&source[open + 1..close], true)),
+            Vec::new(),
+            1,
+            end_line,
+        );
+        node.location.end_offset = source.len();
+
+        let mut offset = open;
+        for (parameter, node_type) in parameters {
+            let start = offset + source[offset..].find(parameter).unwrap();
+            offset = start + parameter.len();
+            let line = source[..start].matches('\n').count() + 1;
+            node.children.push(Node::new(
+                node_type.clone(),
+                Location::new(line, 0, line, 0, start, offset),
+            ));
+        }
+        node
+    }
+
+    fn format_def(source: &str, node: &Node, line_length: usize) -> String {
+        let mut config = Config::default();
+        config.formatting.line_length = line_length;
+        let mut ctx = FormatContext::new(&config, source);
+        let registry = RuleRegistry::default_registry();
+        ctx.collect_comments(node);
+        let doc = DefRule.format(node, &mut ctx, &registry).unwrap();
+        Printer::new(&config).print(&doc)
+    }
+
+    #[test]
+    fn test_long_parameter_list_breaks_one_per_line() {
+        let source = "def foo(alpha, beta = 1, *rest, gamma:, **opts, &blk)\nend";
+        let node = def_with_parameters(
+            source,
+            &[
+                ("alpha", NodeType::RequiredParameterNode),
+                ("beta = 1", NodeType::OptionalParameterNode),
+                ("*rest", NodeType::RestParameterNode),
+                ("gamma:", NodeType::RequiredKeywordParameterNode),
+                ("**opts", NodeType::KeywordRestParameterNode),
+                ("&blk", NodeType::BlockParameterNode),
+            ],
+        );
+        assert_eq!(format_def(source, &node, 100), format!("{}\n", source));
+        assert_eq!(
+            format_def(source, &node, 30),
+            "def foo(\n  alpha,\n  beta = 1,\n  *rest,\n  gamma:,\n  **opts,\n  &blk\n)\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_misindented_parameters_rejoin() {
+        let source = "def foo(a,\n        b)\nend";
+        let node = def_with_parameters(
+            source,
+            &[
+                ("a", NodeType::RequiredParameterNode),
+                ("b", NodeType::RequiredParameterNode),
+            ],
+        );
+        assert_eq!(format_def(source, &node, 100), "def foo(a, b)\nend\n");
+    }
+
+    #[test]
+    fn test_parameters_with_comments_keep_source() {
+        let source = "def foo(a, # note\n  b)\n  a\nend";
+        let mut node = def_with_parameters(
+            source,
+            &[
+                ("a", NodeType::RequiredParameterNode),
+                ("b", NodeType::RequiredParameterNode),
+            ],
+        );
+        let body_start = source.rfind("  a").unwrap() + 2;
+        let mut body = Node::new(
+            NodeType::StatementsNode,
+            Location::new(3, 2, 3, 3, body_start, body_start + 1),
+        );
+        body.children.push(Node::new(
+            NodeType::LocalVariableReadNode,
+            Location::new(3, 2, 3, 3, body_start, body_start + 1),
+        ));
+        node.children.push(body);
+        node.comments.push(Comment {
+            text: "# note".to_string(),
+            location: Location::new(1, 11, 1, 17, 11, 17),
+            comment_type: CommentType::Line,
+            position: CommentPosition::Trailing,
+        });
+        assert_eq!(format_def(source, &node, 100), format!("{}\n", source));
+    }
+
+    #[test]
+    fn test_endless_def_is_kept() {
+        let source = "def foo(a) =\n  a + 1";
+        let mut node = def_with_parameters(source, &[("a", NodeType::RequiredParameterNode)]);
+        node.metadata
+            .insert("endless".to_string(), "true".to_string());
+        assert_eq!(format_def(source, &node, 100), format!("{}\n", source));
+    }
+
     #[test]
     fn test_simple_def() {
         let config = Config::default();
@@ -3,17 +3,22 @@
 //! The fallback rule extracts source text directly, similar to the
 //! existing Emitter's emit_generic function. This provides a safety
 //! net for node types that haven't been implemented yet.
+//!
+//! VerbatimRule emits nodes covered by `# rfmt:disable` / `# rfmt:skip`
+//! exactly as written.
 
 use crate::ast::Node;
-use crate::doc::{concat, Doc};
+use crate::doc::{concat, text, Doc};
 use crate::error::Result;
 use crate::format::context::FormatContext;
 use crate::format::registry::RuleRegistry;
 use crate::format::rule::{
     chain_doc_or_verbatim, format_leading_comments, format_trailing_comment,
-    mark_comments_in_range_emitted, FormatRule,
+    mark_comments_in_range_emitted, strip_one_trailing_newline, FormatRule,
 };
 
+use super::hash_syntax_source;
+
 /// Fallback rule that extracts source text directly.
 ///
 /// This rule is used when no specific rule is registered for a node type.
@@ -45,9 +50,11 @@ impl FormatRule for FallbackRule {
         // blank line before the following statement or `end`. Strip at
         // most one trailing newline (not all trailing whitespace, which
         // could swallow an intentional blank line captured by the node's
-        // extent).
-        if let Some(source_text) = ctx.extract_source(node) {
-            docs.push(chain_doc_or_verbatim(source_text));
+        // extent). Hashes inside still get `style.hash_syntax` applied.
+        let source_doc =
+            hash_syntax_source(node, ctx).map(|source_text| chain_doc_or_verbatim(&source_text));
+        if let Some(source_doc) = source_doc {
+            docs.push(source_doc);
 
             // Mark any comments within this node's range as emitted
             // (they are included in the source extraction)
@@ -64,6 +71,42 @@ impl FormatRule for FallbackRule {
     }
 }
 
+/// Rule for nodes formatting is disabled for
+/// (`FormatContext::is_formatting_disabled`).
+///
+/// Unlike FallbackRule, the source is not touched at all: no chain
+/// reformatting, hash syntax or trailing commas. Comments inside the node
+/// come out with it; those before and after it are placed as usual.
+pub struct VerbatimRule;
+
+impl FormatRule for VerbatimRule {
+    fn format(
+        &self,
+        node: &Node,
+        ctx: &mut FormatContext,
+        _registry: &RuleRegistry,
+    ) -> Result<Doc> {
+        let mut docs: Vec<Doc> = Vec::with_capacity(3);
+
+        let leading = format_leading_comments(ctx, node.location.start_line);
+        if !leading.is_empty() {
+            docs.push(leading);
+        }
+
+        if let Some(source_text) = ctx.extract_source(node) {
+            docs.push(text(strip_one_trailing_newline(source_text).to_string()));
+            mark_comments_in_range_emitted(ctx, node.location.start_line, node.location.end_line);
+        }
+
+        let trailing = format_trailing_comment(ctx, node.location.end_line);
+        if !trailing.is_empty() {
+            docs.push(trailing);
+        }
+
+        Ok(concat(docs))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
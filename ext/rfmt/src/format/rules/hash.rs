@@ -0,0 +1,589 @@
+//! HashRule - Formats hash literals and normalizes hash key syntax
+//!
+//! A braced hash stays on one line when it fits `line_length`, and
+//! otherwise breaks to one pair per line:
+//!
+//! ```text
+//! {
+//!   name: "rfmt",
+//!   version: 1,
+//! }
+//! ```
+//!
+//! The comma after the last pair follows `style.trailing_comma`.
+//!
+//! Applies `style.hash_syntax` to hash literals and keyword arguments:
+//! - `ruby19`: `:key => v` → `key: v` where the symbol is a valid label
+//! - `hash_rockets`: `key: v` → `:key => v`
+//! - `consistent`: per literal, every pair as a label when all keys allow
+//!   it, otherwise every pair as a rocket; a literal that fits neither
+//!   (e.g. `{ "a" => 1, b: }`) is left as written
+//!
+//! Only the span between a key and its value is rewritten; values go
+//! through their own rules. Keyword argument lists, empty hashes, hashes
+//! holding heredocs and hashes with multi-line pairs (other than nested
+//! arrays and hashes that are laid out too) keep their layout. Those, and
+//! hashes nested inside code that other rules emit verbatim, get their keys
+//! rewritten through `hash_syntax_source`.
+
+use std::borrow::Cow;
+
+use crate::ast::{Node, NodeType};
+use crate::config::HashSyntax;
+use crate::doc::{concat, text, Doc};
+use crate::error::Result;
+use crate::format::context::FormatContext;
+use crate::format::registry::RuleRegistry;
+use crate::format::rule::{
+    chain_doc_or_verbatim, contains_heredoc, format_delimited_list, format_embedded_child,
+    format_leading_comments, format_trailing_comment, mark_comments_in_range_emitted, FormatRule,
+};
+
+use super::is_laid_out_list;
+
+/// Rule for HashNode and KeywordHashNode.
+pub struct HashRule;
+
+impl FormatRule for HashRule {
+    fn format(&self, node: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) -> Result<Doc> {
+        let mut docs: Vec<Doc> = Vec::with_capacity(4);
+
+        let leading = format_leading_comments(ctx, node.location.start_line);
+        if !leading.is_empty() {
+            docs.push(leading);
+        }
+
+        if is_laid_out_hash(node, ctx) {
+            let (hash, after) = format_braced_hash(node, ctx, registry)?;
+            docs.push(hash);
+            docs.push(after);
+        } else {
+            if let Some(source_text) = hash_syntax_source(node, ctx) {
+                docs.push(chain_doc_or_verbatim(&source_text));
+            }
+            mark_comments_in_range_emitted(ctx, node.location.start_line, node.location.end_line);
+        }
+
+        let trailing = format_trailing_comment(ctx, node.location.end_line);
+        if !trailing.is_empty() {
+            docs.push(trailing);
+        }
+
+        Ok(concat(docs))
+    }
+}
+
+/// Whether HashRule lays `node` out as a list of pairs rather than keeping
+/// its source (see the module docs).
+pub(crate) fn is_laid_out_hash(node: &Node, ctx: &FormatContext) -> bool {
+    let Some(source) = ctx.extract_source(node) else {
+        return false;
+    };
+    if !matches!(node.node_type, NodeType::HashNode)
+        || !source.starts_with('{')
+        || !source.ends_with('}')
+        || node.children.is_empty()
+        || contains_heredoc(node, ctx)
+    {
+        return false;
+    }
+
+    node.children.iter().all(|pair| {
+        if pair.location.start_line == pair.location.end_line {
+            return true;
+        }
+        // Only through a nested array or hash that is laid out too, with
+        // the key on its first line
+        let Some((key, value)) = split_pair(pair) else {
+            return false;
+        };
+        key.location.start_line == value.location.start_line && is_laid_out_list(value, ctx)
+    })
+}
+
+/// Formats `{ a: 1, b: 2 }`. Returns the hash and any comments that sat
+/// after its closing brace on the last pair's line.
+fn format_braced_hash(
+    node: &Node,
+    ctx: &mut FormatContext,
+    registry: &RuleRegistry,
+) -> Result<(Doc, Doc)> {
+    let mut keys: Vec<Edit> = Vec::new();
+    collect_hash_edits(
+        node,
+        ctx,
+        &ctx.config().formatting.style.hash_syntax,
+        &mut keys,
+    );
+
+    // `{a: 1}` stays unpadded when it fits; `{ a: 1 }` and hashes written
+    // across lines get the spaces
+    let padded = ctx
+        .source()
+        .as_bytes()
+        .get(node.location.start_offset + 1)
+        .is_some_and(|b| b.is_ascii_whitespace());
+
+    format_delimited_list(node, ctx, "{", "}", padded, |pair, ctx| {
+        let Some((key, value)) = split_pair(pair) else {
+            // `**opts` and shorthand `x:`
+            return format_embedded_child(pair, ctx, registry);
+        };
+        let start = key.location.start_offset;
+        let key_text = match keys.iter().find(|edit| edit.start == start) {
+            Some(edit) => edit.replacement.clone(),
+            None => ctx
+                .extract_source_range(start, value.location.start_offset)
+                .unwrap_or_default()
+                .to_string(),
+        };
+        Ok(concat(vec![
+            text(key_text),
+            format_embedded_child(value, ctx, registry)?,
+        ]))
+    })
+}
+
+/// The key and value of a `key => value` / `key: value` pair; `None` for
+/// `**opts` and for shorthand `x:`, whose value is the key itself.
+fn split_pair(pair: &Node) -> Option<(&Node, &Node)> {
+    if !matches!(pair.node_type, NodeType::AssocNode) {
+        return None;
+    }
+    let key = pair.children.first()?;
+    let value = pair.children.get(1)?;
+    (value.location.start_offset >= key.location.end_offset).then_some((key, value))
+}
+
+/// Returns the source of `node` with `style.hash_syntax` applied to every
+/// hash literal and keyword argument list inside it, `node` included.
+pub(crate) fn hash_syntax_source<'c>(node: &Node, ctx: &'c FormatContext) -> Option<Cow<'c, str>> {
+    hash_syntax_source_in(
+        node,
+        node.location.start_offset,
+        node.location.end_offset,
+        ctx,
+    )
+}
+
+/// Like `hash_syntax_source`, but returns only the `start..end` part of
+/// `node`'s source, e.g. one pair of a keyword argument list, rewritten as
+/// the whole of `node` would be.
+pub(crate) fn hash_syntax_source_in<'c>(
+    node: &Node,
+    start: usize,
+    end: usize,
+    ctx: &'c FormatContext,
+) -> Option<Cow<'c, str>> {
+    let source = ctx.extract_source_range(start, end)?;
+
+    let syntax = &ctx.config().formatting.style.hash_syntax;
+    let mut edits: Vec<Edit> = Vec::new();
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+        if matches!(
+            current.node_type,
+            NodeType::HashNode | NodeType::KeywordHashNode
+        ) {
+            collect_hash_edits(current, ctx, syntax, &mut edits);
+        }
+        stack.extend(current.children.iter());
+    }
+    edits.retain(|edit| start <= edit.start && edit.end <= end);
+
+    if edits.is_empty() {
+        return Some(Cow::Borrowed(source));
+    }
+
+    edits.sort_by_key(|edit| edit.start);
+    let mut out = String::with_capacity(source.len());
+    let mut cursor = start;
+    for edit in edits {
+        if edit.start < cursor {
+            continue;
+        }
+        out.push_str(&source[cursor - start..edit.start - start]);
+        out.push_str(&edit.replacement);
+        cursor = edit.end;
+    }
+    out.push_str(&source[cursor - start..]);
+
+    Some(Cow::Owned(out))
+}
+
+/// Replacement of the byte range `start..end` of the source.
+struct Edit {
+    start: usize,
+    end: usize,
+    replacement: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyStyle {
+    Label,
+    Rocket,
+}
+
+/// One `key => value` / `key: value` pair and the spellings it allows.
+struct Pair {
+    /// Start of the key through start of the value.
+    start: usize,
+    end: usize,
+    current: KeyStyle,
+    /// Replacement text in label style (`name: `), when allowed.
+    label: Option<String>,
+    /// Replacement text in rocket style (`:name => `), when allowed.
+    rocket: Option<String>,
+}
+
+impl Pair {
+    fn allows(&self, style: KeyStyle) -> bool {
+        style == self.current || self.replacement(style).is_some()
+    }
+
+    fn replacement(&self, style: KeyStyle) -> Option<&String> {
+        match style {
+            KeyStyle::Label => self.label.as_ref(),
+            KeyStyle::Rocket => self.rocket.as_ref(),
+        }
+    }
+}
+
+fn collect_hash_edits(
+    hash: &Node,
+    ctx: &FormatContext,
+    syntax: &HashSyntax,
+    edits: &mut Vec<Edit>,
+) {
+    let pairs: Vec<Pair> = hash
+        .children
+        .iter()
+        .filter(|child| matches!(child.node_type, NodeType::AssocNode))
+        .filter_map(|assoc| pair_for(assoc, ctx))
+        .collect();
+
+    let target = match syntax {
+        HashSyntax::Ruby19 => KeyStyle::Label,
+        HashSyntax::HashRockets => KeyStyle::Rocket,
+        HashSyntax::Consistent => {
+            if pairs.iter().all(|pair| pair.allows(KeyStyle::Label)) {
+                KeyStyle::Label
+            } else if pairs.iter().all(|pair| pair.allows(KeyStyle::Rocket)) {
+                KeyStyle::Rocket
+            } else {
+                return;
+            }
+        }
+    };
+
+    for pair in &pairs {
+        if pair.current == target {
+            continue;
+        }
+        if let Some(replacement) = pair.replacement(target) {
+            edits.push(Edit {
+                start: pair.start,
+                end: pair.end,
+                replacement: replacement.clone(),
+            });
+        }
+    }
+}
+
+/// Builds a Pair from an AssocNode using the adapter's `key_kind` /
+/// `key_name` metadata. Returns None for pairs that cannot be inspected.
+fn pair_for(assoc: &Node, ctx: &FormatContext) -> Option<Pair> {
+    let key = assoc.children.first()?;
+    let value = assoc.children.get(1)?;
+    let start = key.location.start_offset;
+    let end = value.location.start_offset;
+
+    let kind = assoc
+        .metadata
+        .get("key_kind")
+        .map(String::as_str)
+        .unwrap_or("other");
+    let current = match kind {
+        "label" | "quoted_label" => KeyStyle::Label,
+        _ => KeyStyle::Rocket,
+    };
+
+    // Shorthand `{ x: }` (the value is the key itself) and pairs split
+    // across lines keep their spelling.
+    let fixed = end < key.location.end_offset
+        || ctx
+            .extract_source_range(start, end)
+            .is_none_or(|span| span.contains('\n'));
+
+    let mut pair = Pair {
+        start,
+        end,
+        current,
+        label: None,
+        rocket: None,
+    };
+    if fixed {
+        return Some(pair);
+    }
+
+    match kind {
+        "label" | "quoted_label" => {
+            let key_source = ctx.extract_source(key)?;
+            let quoted = key_source.strip_suffix(':')?;
+            pair.rocket = Some(format!(":{} => ", quoted));
+        }
+        "symbol" | "quoted_symbol" => {
+            pair.label = assoc
+                .metadata
+                .get("key_name")
+                .filter(|name| is_label_name(name))
+                .map(|name| format!("{}: ", name));
+        }
+        _ => {}
+    }
+
+    Some(pair)
+}
+
+/// Whether `name` can be written as a bare `name:` label.
+fn is_label_name(name: &str) -> bool {
+    let stem = name.strip_suffix(['?', '!']).unwrap_or(name);
+    let mut chars = stem.chars();
+    let starts_ok = chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic() || !c.is_ascii());
+    starts_ok && chars.all(|c| c == '_' || c.is_ascii_alphanumeric() || !c.is_ascii())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Comment, CommentPosition, CommentType};
+    use crate::config::{Config, TrailingComma};
+    use crate::format::rules::test_support::{format_node, location, node};
+
+    /// Builds an AssocNode from the key/value offsets within the source.
+    fn assoc(kind: &str, name: Option<&str>, key: (usize, usize), value: (usize, usize)) -> Node {
+        let children = vec![
+            node(NodeType::SymbolNode, key.0, key.1, Vec::new()),
+            node(NodeType::IntegerNode, value.0, value.1, Vec::new()),
+        ];
+        let mut pair = node(NodeType::AssocNode, key.0, value.1, children);
+        pair.metadata
+            .insert("key_kind".to_string(), kind.to_string());
+        if let Some(name) = name {
+            pair.metadata
+                .insert("key_name".to_string(), name.to_string());
+        }
+        pair
+    }
+
+    fn format_hash(syntax: HashSyntax, source: &str, pairs: Vec<Node>) -> String {
+        let mut config = Config::default();
+        config.formatting.style.hash_syntax = syntax;
+        let hash = node(NodeType::HashNode, 0, source.len(), pairs);
+        format_node(&config, source, &hash).trim().to_string()
+    }
+
+    #[test]
+    fn test_ruby19_converts_symbol_keys() {
+        // { :a => 1, :b? => 2 }
+        let source = "{ :a => 1, :b? => 2 }";
+        let pairs = vec![
+            assoc("symbol", Some("a"), (2, 4), (8, 9)),
+            assoc("symbol", Some("b?"), (11, 14), (18, 19)),
+        ];
+        assert_eq!(
+            format_hash(HashSyntax::Ruby19, source, pairs),
+            "{ a: 1, b?: 2 }"
+        );
+    }
+
+    #[test]
+    fn test_ruby19_keeps_symbols_that_are_not_labels() {
+        // { :"a-b" => 1, :c= => 2 }
+        let source = "{ :\"a-b\" => 1, :c= => 2 }";
+        let pairs = vec![
+            assoc("quoted_symbol", Some("a-b"), (2, 8), (12, 13)),
+            assoc("symbol", Some("c="), (15, 18), (22, 23)),
+        ];
+        assert_eq!(format_hash(HashSyntax::Ruby19, source, pairs), source);
+    }
+
+    #[test]
+    fn test_hash_rockets_converts_labels() {
+        // { a: 1, "b c": 2 }
+        let source = "{ a: 1, \"b c\": 2 }";
+        let pairs = vec![
+            assoc("label", Some("a"), (2, 4), (5, 6)),
+            assoc("quoted_label", Some("b c"), (8, 14), (15, 16)),
+        ];
+        assert_eq!(
+            format_hash(HashSyntax::HashRockets, source, pairs),
+            "{ :a => 1, :\"b c\" => 2 }"
+        );
+    }
+
+    #[test]
+    fn test_consistent_prefers_labels_when_all_keys_allow() {
+        // { a: 1, :b => 2 }
+        let source = "{ a: 1, :b => 2 }";
+        let pairs = vec![
+            assoc("label", Some("a"), (2, 4), (5, 6)),
+            assoc("symbol", Some("b"), (8, 10), (14, 15)),
+        ];
+        assert_eq!(
+            format_hash(HashSyntax::Consistent, source, pairs),
+            "{ a: 1, b: 2 }"
+        );
+    }
+
+    #[test]
+    fn test_consistent_falls_back_to_rockets_for_the_whole_literal() {
+        // { a: 1, "s" => 2 }
+        let source = "{ a: 1, \"s\" => 2 }";
+        let pairs = vec![
+            assoc("label", Some("a"), (2, 4), (5, 6)),
+            assoc("other", None, (8, 11), (15, 16)),
+        ];
+        assert_eq!(
+            format_hash(HashSyntax::Consistent, source, pairs),
+            "{ :a => 1, \"s\" => 2 }"
+        );
+    }
+
+    #[test]
+    fn test_multiline_pair_keeps_its_spelling() {
+        let source = "{ :a =>\n  1 }";
+        let pairs = vec![assoc("symbol", Some("a"), (2, 4), (10, 11))];
+        assert_eq!(format_hash(HashSyntax::Ruby19, source, pairs), source);
+    }
+
+    /// `format_hash` under the given trailing comma style and line length.
+    fn format_broken(style: TrailingComma, width: usize, source: &str, pairs: Vec<Node>) -> String {
+        let mut config = Config::default();
+        config.formatting.style.trailing_comma = style;
+        config.formatting.line_length = width;
+        let hash = node(NodeType::HashNode, 0, source.len(), pairs);
+        format_node(&config, source, &hash).trim().to_string()
+    }
+
+    fn alpha_beta() -> Vec<Node> {
+        // { alpha: 1, beta: 2 }
+        vec![
+            assoc("label", Some("alpha"), (2, 8), (9, 10)),
+            assoc("label", Some("beta"), (12, 17), (18, 19)),
+        ]
+    }
+
+    #[test]
+    fn test_long_hash_breaks_one_pair_per_line() {
+        let source = "{ alpha: 1, beta: 2 }";
+        assert_eq!(
+            format_broken(TrailingComma::Multiline, 12, source, alpha_beta()),
+            "{\n  alpha: 1,\n  beta: 2,\n}"
+        );
+        assert_eq!(
+            format_broken(TrailingComma::Never, 12, source, alpha_beta()),
+            "{\n  alpha: 1,\n  beta: 2\n}"
+        );
+        assert_eq!(
+            format_broken(TrailingComma::Multiline, 80, source, alpha_beta()),
+            source
+        );
+    }
+
+    #[test]
+    fn test_broken_hash_rejoins_when_it_fits() {
+        let source = "{\n  a: 1,\n  b: 2,\n}";
+        let pairs = vec![
+            assoc("label", Some("a"), (4, 6), (7, 8)),
+            assoc("label", Some("b"), (12, 14), (15, 16)),
+        ];
+        assert_eq!(
+            format_broken(TrailingComma::Multiline, 80, source, pairs),
+            "{ a: 1, b: 2 }"
+        );
+    }
+
+    #[test]
+    fn test_unpadded_hash_keeps_its_braces_tight() {
+        let source = "{a: 1}";
+        let pairs = || vec![assoc("label", Some("a"), (1, 3), (4, 5))];
+        assert_eq!(
+            format_broken(TrailingComma::Multiline, 80, source, pairs()),
+            "{a: 1}"
+        );
+        assert_eq!(
+            format_broken(TrailingComma::Always, 80, source, pairs()),
+            "{a: 1,}"
+        );
+        assert_eq!(
+            format_broken(TrailingComma::Multiline, 4, source, pairs()),
+            "{\n  a: 1,\n}"
+        );
+    }
+
+    #[test]
+    fn test_broken_hash_keeps_rewritten_keys() {
+        // { :alpha => 1, :beta => 2 }
+        let source = "{ :alpha => 1, :beta => 2 }";
+        let pairs = vec![
+            assoc("symbol", Some("alpha"), (2, 8), (12, 13)),
+            assoc("symbol", Some("beta"), (15, 20), (24, 25)),
+        ];
+        assert_eq!(
+            format_broken(TrailingComma::Multiline, 12, source, pairs),
+            "{\n  alpha: 1,\n  beta: 2,\n}"
+        );
+    }
+
+    #[test]
+    fn test_no_trailing_comma_after_anonymous_splat() {
+        // { a: 1, ** }
+        let source = "{ a: 1, ** }";
+        let pairs = vec![
+            assoc("label", Some("a"), (2, 4), (5, 6)),
+            node(NodeType::AssocSplatNode, 8, 10, Vec::new()),
+        ];
+        assert_eq!(
+            format_broken(TrailingComma::Always, 80, source, pairs),
+            source
+        );
+    }
+
+    #[test]
+    fn test_comment_after_a_pair_forces_the_break() {
+        let source = "{ a: 1, # one\n  b: 2 }";
+        let pairs = vec![
+            assoc("label", Some("a"), (2, 4), (5, 6)),
+            assoc("label", Some("b"), (16, 18), (19, 20)),
+        ];
+        let mut hash = node(NodeType::HashNode, 0, source.len(), pairs);
+        hash.comments.push(Comment {
+            text: "# one".to_string(),
+            location: location(source, 8, 13),
+            comment_type: CommentType::Line,
+            position: CommentPosition::Trailing,
+        });
+        assert_eq!(
+            format_node(&Config::default(), source, &hash).trim(),
+            "{\n  a: 1, # one\n  b: 2,\n}"
+        );
+    }
+
+    #[test]
+    fn test_is_label_name() {
+        assert!(is_label_name("foo"));
+        assert!(is_label_name("Foo_1"));
+        assert!(is_label_name("empty?"));
+        assert!(is_label_name("save!"));
+        assert!(is_label_name("名前"));
+        assert!(!is_label_name(""));
+        assert!(!is_label_name("1a"));
+        assert!(!is_label_name("a-b"));
+        assert!(!is_label_name("a="));
+        assert!(!is_label_name("@a"));
+        assert!(!is_label_name("?"));
+    }
+}
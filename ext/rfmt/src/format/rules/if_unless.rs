@@ -13,10 +13,12 @@ use crate::error::Result;
 use crate::format::context::FormatContext;
 use crate::format::registry::RuleRegistry;
 use crate::format::rule::{
-    format_leading_comments, format_statements, format_trailing_comment,
+    format_embedded_child, format_leading_comments, format_statements, format_trailing_comment,
     mark_comments_in_range_emitted, strip_one_trailing_newline, FormatRule,
 };
 
+use super::is_binary_expression;
+
 /// Rule for formatting if conditionals.
 pub struct IfRule;
 
@@ -302,9 +304,12 @@ fn format_normal(
     // nodes' end_offset past the terminator's newline. Leaving that
     // newline in the emitted text combines with our own `hardline` before
     // the then-clause to produce a spurious blank line after the
-    // terminator. Strip at most one trailing newline.
+    // terminator. Strip at most one trailing newline. Boolean and operator
+    // expressions break when they do not fit.
     if let Some(predicate) = node.children.first() {
-        if let Some(source_text) = ctx.extract_source(predicate) {
+        if is_binary_expression(predicate, ctx) {
+            docs.push(format_embedded_child(predicate, ctx, registry)?);
+        } else if let Some(source_text) = ctx.extract_source(predicate) {
             docs.push(text(strip_one_trailing_newline(source_text).to_string()));
         }
     }
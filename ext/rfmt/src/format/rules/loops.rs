@@ -12,10 +12,12 @@ use crate::error::Result;
 use crate::format::context::FormatContext;
 use crate::format::registry::RuleRegistry;
 use crate::format::rule::{
-    format_leading_comments, format_statements, format_trailing_comment,
+    format_embedded_child, format_leading_comments, format_statements, format_trailing_comment,
     mark_comments_in_range_emitted, FormatRule,
 };
 
+use super::is_binary_expression;
+
 /// Rule for formatting while loops.
 pub struct WhileRule;
 
@@ -113,9 +115,12 @@ fn format_normal_while_until(
     docs.push(text(keyword));
     docs.push(text(" "));
 
-    // Emit predicate (condition) - first child
+    // Emit predicate (condition) - first child. Boolean and operator
+    // expressions break when they do not fit.
     if let Some(predicate) = node.children.first() {
-        if let Some(source_text) = ctx.extract_source(predicate) {
+        if is_binary_expression(predicate, ctx) {
+            docs.push(format_embedded_child(predicate, ctx, registry)?);
+        } else if let Some(source_text) = ctx.extract_source(predicate) {
             docs.push(text(source_text));
         }
     }
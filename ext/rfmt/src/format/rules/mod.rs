@@ -3,29 +3,42 @@
 //! This module contains the FormatRule implementations for each supported
 //! node type. Each rule is responsible for converting its node type to Doc IR.
 
+mod array;
 mod begin;
+mod binary;
 mod body_end;
 mod call;
 mod case;
 mod class;
 mod def;
 mod fallback;
+mod hash;
 mod if_unless;
 mod loops;
 mod module;
 mod singleton_class;
 mod statements;
+mod string;
+#[cfg(test)]
+mod test_support;
 mod variable_write;
 
+pub(crate) use array::is_laid_out_list;
+pub use array::ArrayRule;
 pub use begin::{BeginRule, EnsureRule, RescueRule};
+pub use binary::BinaryRule;
+pub(crate) use binary::{format_binary, is_binary_expression};
 pub use call::{BlockRule, CallRule, LambdaRule};
 pub use case::{CaseMatchRule, CaseRule, InRule, WhenRule};
 pub use class::ClassRule;
 pub use def::DefRule;
-pub use fallback::FallbackRule;
+pub use fallback::{FallbackRule, VerbatimRule};
+pub use hash::HashRule;
+pub(crate) use hash::{hash_syntax_source, hash_syntax_source_in, is_laid_out_hash};
 pub use if_unless::{IfRule, UnlessRule};
 pub use loops::{ForRule, UntilRule, WhileRule};
 pub use module::ModuleRule;
 pub use singleton_class::SingletonClassRule;
 pub use statements::StatementsRule;
+pub use string::StringRule;
 pub use variable_write::{InstanceVariableWriteRule, LocalVariableWriteRule};
@@ -28,6 +28,7 @@ impl FormatRule for ModuleRule {
                 node,
                 header_builder: Box::new(build_module_header),
                 skip_same_line_children: false,
+                rebuilds_parameters: false,
             },
         )
     }
@@ -0,0 +1,240 @@
+//! StringRule - Normalizes string literal quotes
+//!
+//! Rewrites plain `"…"` / `'…'` literals to the configured quote style
+//! (`formatting.quote_style`):
+//! - `double`: `'foo'` → `"foo"`
+//! - `single`: `"foo"` → `'foo'`
+//! - `consistent`: whichever quote most literals in the file already use
+//!
+//! A literal is only rewritten when the result means exactly the same
+//! string. Anything containing backslashes, the target quote character, or
+//! text that would start an interpolation stays as written, as do `%q`/`%Q`
+//! literals, heredocs, character literals and interpolated strings (whose
+//! `#{}` requires double quotes).
+
+use crate::ast::{Node, NodeType};
+use crate::config::QuoteStyle;
+use crate::doc::{concat, text, Doc};
+use crate::error::Result;
+use crate::format::context::FormatContext;
+use crate::format::registry::RuleRegistry;
+use crate::format::rule::{
+    format_leading_comments, format_trailing_comment, mark_comments_in_range_emitted, FormatRule,
+};
+
+use super::FallbackRule;
+
+/// Rule for StringNode and InterpolatedStringNode literals.
+pub struct StringRule;
+
+impl FormatRule for StringRule {
+    fn format(&self, node: &Node, ctx: &mut FormatContext, registry: &RuleRegistry) -> Result<Doc> {
+        let Some(rewritten) = rewrite_literal(node, ctx) else {
+            return FallbackRule.format(node, ctx, registry);
+        };
+
+        let mut docs: Vec<Doc> = Vec::with_capacity(3);
+
+        let leading = format_leading_comments(ctx, node.location.start_line);
+        if !leading.is_empty() {
+            docs.push(leading);
+        }
+
+        docs.push(text(rewritten));
+        mark_comments_in_range_emitted(ctx, node.location.start_line, node.location.end_line);
+
+        let trailing = format_trailing_comment(ctx, node.location.end_line);
+        if !trailing.is_empty() {
+            docs.push(trailing);
+        }
+
+        Ok(concat(docs))
+    }
+}
+
+/// Returns the literal's source with quotes normalized, or `None` when the
+/// node must be emitted verbatim.
+fn rewrite_literal(node: &Node, ctx: &FormatContext) -> Option<String> {
+    let target = match ctx.config().formatting.quote_style {
+        QuoteStyle::Double => '"',
+        QuoteStyle::Single => '\'',
+        QuoteStyle::Consistent => ctx.prevailing_quote(),
+    };
+    let source = ctx.extract_source(node)?;
+
+    match node.node_type {
+        NodeType::StringNode => requote(source, target),
+        NodeType::InterpolatedStringNode => requote_concatenation(node, source, ctx, target),
+        _ => None,
+    }
+}
+
+/// Requotes a single plain literal. Returns the source unchanged when it is
+/// already in the target style or cannot be converted safely.
+fn requote(source: &str, target: char) -> Option<String> {
+    let quote = source.chars().next()?;
+    if !matches!(quote, '"' | '\'') || source.len() < 2 || !source.ends_with(quote) {
+        return None;
+    }
+    if quote == target {
+        return Some(source.to_string());
+    }
+
+    let content = &source[1..source.len() - 1];
+    if can_requote(content, target) {
+        Some(format!("{target}{content}{target}"))
+    } else {
+        Some(source.to_string())
+    }
+}
+
+/// Whether `content` means the same string between `target` quotes.
+///
+/// Backslashes are rejected outright: `'\n'` and `"\n"` differ, and
+/// re-escaping is not worth the risk. Going to double quotes additionally
+/// rules out `#{`, `#@` and `#$`, which would start an interpolation.
+fn can_requote(content: &str, target: char) -> bool {
+    if content.contains('\\') || content.contains(target) {
+        return false;
+    }
+    if target == '"' {
+        let bytes = content.as_bytes();
+        let starts_interpolation = bytes
+            .windows(2)
+            .any(|w| w[0] == b'#' && matches!(w[1], b'{' | b'@' | b'$'));
+        if starts_interpolation {
+            return false;
+        }
+    }
+    true
+}
+
+/// Handles adjacent literal concatenation (`'foo' 'bar'`), which prism
+/// reports as an InterpolatedStringNode without delimiters of its own.
+/// Each plain part is requoted; interpolated parts and the whitespace
+/// between parts are kept verbatim. A real `"…#{}…"` string has its own
+/// opening quote before the first part and is left alone.
+fn requote_concatenation(
+    node: &Node,
+    source: &str,
+    ctx: &FormatContext,
+    target: char,
+) -> Option<String> {
+    let base = node.location.start_offset;
+    let first = node.children.first()?;
+    if first.location.start_offset != base {
+        return None;
+    }
+
+    let mut out = String::with_capacity(source.len());
+    let mut cursor = base;
+    for part in &node.children {
+        let part_source = ctx.extract_source(part)?;
+        let gap = ctx.extract_source_range(cursor, part.location.start_offset)?;
+        out.push_str(gap);
+
+        let rewritten = match part.node_type {
+            NodeType::StringNode => requote(part_source, target),
+            _ => None,
+        };
+        out.push_str(rewritten.as_deref().unwrap_or(part_source));
+        cursor = part.location.end_offset;
+    }
+    out.push_str(ctx.extract_source_range(cursor, node.location.end_offset)?);
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::format::rules::test_support::{format_node, node};
+
+    fn config_with(style: QuoteStyle) -> Config {
+        let mut config = Config::default();
+        config.formatting.quote_style = style;
+        config
+    }
+
+    fn format_string(style: QuoteStyle, source: &str) -> String {
+        let node = node(NodeType::StringNode, 0, source.len(), Vec::new());
+        format_node(&config_with(style), source, &node)
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn test_single_to_double() {
+        assert_eq!(format_string(QuoteStyle::Double, "'hello'"), "\"hello\"");
+    }
+
+    #[test]
+    fn test_double_to_single() {
+        assert_eq!(format_string(QuoteStyle::Single, "\"hello\""), "'hello'");
+    }
+
+    #[test]
+    fn test_keeps_literals_that_need_their_quotes() {
+        // Escapes mean different things in each quote style
+        assert_eq!(format_string(QuoteStyle::Single, r#""a\nb""#), r#""a\nb""#);
+        assert_eq!(format_string(QuoteStyle::Double, r"'a\nb'"), r"'a\nb'");
+        // The target quote would need escaping
+        assert_eq!(format_string(QuoteStyle::Single, r#""it's""#), r#""it's""#);
+        assert_eq!(
+            format_string(QuoteStyle::Double, r#"'say "hi"'"#),
+            r#"'say "hi"'"#
+        );
+        // Double quotes would turn these into interpolation
+        assert_eq!(format_string(QuoteStyle::Double, "'#{x}'"), "'#{x}'");
+        assert_eq!(format_string(QuoteStyle::Double, "'#@x'"), "'#@x'");
+        assert_eq!(format_string(QuoteStyle::Double, "'#$x'"), "'#$x'");
+        // A lone `#` is harmless
+        assert_eq!(format_string(QuoteStyle::Double, "'#1'"), "\"#1\"");
+    }
+
+    #[test]
+    fn test_leaves_percent_literals_and_heredocs() {
+        assert_eq!(format_string(QuoteStyle::Double, "%q(hi)"), "%q(hi)");
+        assert_eq!(format_string(QuoteStyle::Single, "%Q(hi)"), "%Q(hi)");
+        assert_eq!(format_string(QuoteStyle::Single, "<<~EOS"), "<<~EOS");
+        assert_eq!(format_string(QuoteStyle::Single, "?a"), "?a");
+    }
+
+    #[test]
+    fn test_consistent_follows_prevailing_quote() {
+        // Two single-quoted parts outvote one double-quoted part
+        let source = "'a' \"b\" 'c'";
+        let parts = vec![
+            node(NodeType::StringNode, 0, 3, Vec::new()),
+            node(NodeType::StringNode, 4, 7, Vec::new()),
+            node(NodeType::StringNode, 8, 11, Vec::new()),
+        ];
+        let node = node(NodeType::InterpolatedStringNode, 0, source.len(), parts);
+        let result = format_node(&config_with(QuoteStyle::Consistent), source, &node);
+        assert_eq!(result.trim(), "'a' 'b' 'c'");
+    }
+
+    #[test]
+    fn test_interpolated_string_kept_verbatim() {
+        // `"a#{b}"`: the node opens with its own quote, not with a part
+        let source = "\"a#{b}\"";
+        let parts = vec![
+            node(NodeType::StringNode, 1, 2, Vec::new()),
+            node(NodeType::EmbeddedStatementsNode, 2, 6, Vec::new()),
+        ];
+        let node = node(NodeType::InterpolatedStringNode, 0, source.len(), parts);
+        let result = format_node(&config_with(QuoteStyle::Single), source, &node);
+        assert_eq!(result.trim(), "\"a#{b}\"");
+    }
+
+    #[test]
+    fn test_concatenation_requotes_plain_parts_only() {
+        let source = "'a' \\\n  \"b#{c}\"";
+        let inner = node(NodeType::InterpolatedStringNode, 8, 15, Vec::new());
+        let parts = vec![node(NodeType::StringNode, 0, 3, Vec::new()), inner];
+        let node = node(NodeType::InterpolatedStringNode, 0, source.len(), parts);
+        let result = format_node(&config_with(QuoteStyle::Double), source, &node);
+        assert_eq!(result.trim(), "\"a\" \\\n  \"b#{c}\"");
+    }
+}
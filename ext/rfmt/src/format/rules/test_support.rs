@@ -0,0 +1,57 @@
+//! Node builders and a format harness shared by the rule tests
+//!
+//! Builders only take byte offsets; `format_node` derives every node's
+//! lines and columns from the source before formatting it.
+
+use crate::ast::{Location, Node, NodeType};
+use crate::config::Config;
+use crate::doc::Printer;
+use crate::format::context::FormatContext;
+use crate::format::registry::RuleRegistry;
+use crate::format::rule::format_child;
+
+/// Location of `start..end` in `source`, with lines and columns derived
+/// from the offsets.
+pub(super) fn location(source: &str, start: usize, end: usize) -> Location {
+    let line_col = |offset: usize| {
+        let before = &source[..offset];
+        let column = offset - before.rfind('\n').map_or(0, |i| i + 1);
+        (before.matches('\n').count() + 1, column)
+    };
+    let (start_line, start_column) = line_col(start);
+    let (end_line, end_column) = line_col(end);
+    Location::new(start_line, start_column, end_line, end_column, start, end)
+}
+
+/// A node over the byte range `start..end`.
+pub(super) fn node(node_type: NodeType, start: usize, end: usize, children: Vec<Node>) -> Node {
+    let mut node = Node::new(node_type, Location::new(1, 0, 1, 0, start, end));
+    node.children = children;
+    node
+}
+
+/// Formats `node` with the rule registered for its type and prints it.
+pub(super) fn format_node(config: &Config, source: &str, node: &Node) -> String {
+    let mut node = node.clone();
+    relocate(source, &mut node);
+
+    let mut ctx = FormatContext::new(config, source);
+    let registry = RuleRegistry::default_registry();
+    ctx.collect_comments(&node);
+    let doc = format_child(&node, &mut ctx, &registry).unwrap();
+    Printer::new(config).print(&doc)
+}
+
+/// `format_node` under the default config with `line_length` changed.
+pub(super) fn format_with_width(source: &str, node: &Node, line_length: usize) -> String {
+    let mut config = Config::default();
+    config.formatting.line_length = line_length;
+    format_node(&config, source, node)
+}
+
+fn relocate(source: &str, node: &mut Node) {
+    node.location = location(source, node.location.start_offset, node.location.end_offset);
+    for child in &mut node.children {
+        relocate(source, child);
+    }
+}
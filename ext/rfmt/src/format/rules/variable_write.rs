@@ -13,9 +13,11 @@ use crate::format::context::FormatContext;
 use crate::format::registry::RuleRegistry;
 use crate::format::rule::{
     chain_doc_or_verbatim, format_child, format_leading_comments, format_trailing_comment,
-    mark_comments_in_range_emitted, FormatRule,
+    has_embedded_layout, mark_comments_in_range_emitted, FormatRule,
 };
 
+use super::{hash_syntax_source, is_binary_expression};
+
 /// Rule for formatting local variable write expressions.
 pub struct LocalVariableWriteRule;
 
@@ -113,9 +115,11 @@ fn format_variable_write(
         docs.push(text(format!("{} = ", name)));
         docs.push(format_child(value, ctx, registry)?);
     } else {
-        // Check for multiline method chain
+        // Check for multiline method chain (operator calls such as
+        // `a +\n  b` are re-laid out by CallRule instead)
         let is_multiline_call = matches!(value.node_type, NodeType::CallNode)
-            && value.location.start_line != value.location.end_line;
+            && value.location.start_line != value.location.end_line
+            && !is_binary_expression(value, ctx);
 
         docs.push(text(format!("{} = ", name)));
 
@@ -135,10 +139,18 @@ fn format_variable_write(
                     value.location.end_line,
                 );
             }
+        } else if has_embedded_layout(value) {
+            // String, array or hash literal, boolean expression or
+            // block-less call: its rule applies the quote style and breaks
+            // it when it does not fit
+            docs.push(format_child(value, ctx, registry)?);
         } else {
-            // Simple value: extract from source trimmed
-            if let Some(source_text) = ctx.extract_source(value) {
-                docs.push(text(source_text.trim().to_string()));
+            // Simple value: extract from source trimmed, with any hashes
+            // inside normalized to `style.hash_syntax`
+            let value_text =
+                hash_syntax_source(value, ctx).map(|source_text| source_text.trim().to_string());
+            if let Some(value_text) = value_text {
+                docs.push(text(value_text));
                 mark_comments_in_range_emitted(
                     ctx,
                     value.location.start_line,
@@ -258,6 +270,33 @@ mod tests {
         assert_eq!(result.trim(), "@value = 42");
     }
 
+    #[test]
+    fn test_string_value_uses_quote_style() {
+        let config = Config::default();
+        let source = "x = 'hi'";
+        let mut ctx = FormatContext::new(&config, source);
+        let registry = RuleRegistry::default_registry();
+
+        let value_node = Node {
+            node_type: NodeType::StringNode,
+            location: Location::new(1, 4, 1, 8, 4, 8),
+            children: Vec::new(),
+            metadata: HashMap::new(),
+            comments: Vec::new(),
+            formatting: FormattingInfo::default(),
+        };
+        let node = make_local_var_write_node("x", vec![value_node], 1, 1, 0, 8);
+        ctx.collect_comments(&node);
+
+        let rule = LocalVariableWriteRule;
+        let doc = rule.format(&node, &mut ctx, &registry).unwrap();
+
+        let mut printer = Printer::new(&config);
+        let result = printer.print(&doc);
+
+        assert_eq!(result.trim(), "x = \"hi\"");
+    }
+
     #[test]
     fn test_block_value_assignment() {
         let config = Config::default();
@@ -1,94 +1,185 @@
 pub mod ast;
 pub mod config;
+pub mod diff;
 pub mod doc;
 pub mod error;
 pub mod format;
+pub mod line_index;
+#[cfg(feature = "ruby")]
 mod logging;
+pub mod lsp;
 pub mod parser;
 mod policy;
+pub mod report;
+#[cfg(feature = "ruby")]
+mod ruby;
 pub mod validation;
 
+use std::ops::Range;
+
 use policy::SecurityPolicy;
 
+use ast::Node;
 use config::Config;
+use diff::TextEdit;
+use error::{Result, RfmtError};
 use format::Formatter;
-use magnus::{function, prelude::*, Error, Ruby};
 use parser::{NativeAdapter, RubyParser};
 
-fn format_ruby_code(ruby: &Ruby, source: String) -> Result<String, Error> {
-    format_impl(ruby, source, None)
+/// Formats Ruby `source` with `config`.
+///
+/// Runs the same pipeline as the gem's `Rfmt.format`: the source size
+/// limit, the native parse, formatting, and validation of the output.
+/// With `parser.error_tolerance` enabled, source that does not parse is
+/// formatted as far as it can be (see [`format_source_tolerant`]) instead
+/// of failing.
+///
+/// ```
+/// let config = rfmt::config::Config::default();
+/// let formatted = rfmt::format_source("x  =  1\n", &config)?;
+/// assert_eq!(formatted, "x = 1\n");
+/// # Ok::<(), rfmt::error::RfmtError>(())
+/// ```
+pub fn format_source(source: &str, config: &Config) -> Result<String> {
+    if config.parser.error_tolerance {
+        return format_source_tolerant(source, config).map(|(formatted, _)| formatted);
+    }
+
+    SecurityPolicy::default().validate_source_size(source)?;
+    format_parsed(source, config)
 }
 
-// Separate fixed-arity export instead of a variadic `format_code`: magnus
-// handles fixed signatures natively, so no scan_args parsing to get wrong.
-fn format_ruby_code_with_config(
-    ruby: &Ruby,
-    source: String,
-    config_path: Option<String>,
-) -> Result<String, Error> {
-    format_impl(ruby, source, config_path)
+/// Formats Ruby `source` like [`format_source`], except that top-level
+/// statements with parse errors are copied verbatim while the code between
+/// them is formatted, whatever `parser.error_tolerance` says. Returns the
+/// output and the byte ranges of `source` that were left as they were,
+/// in order; they are empty when the whole source parses.
+///
+/// The code between broken statements is formatted on its own, so a blank
+/// line next to a broken statement is kept as one blank line. A stretch
+/// that does not parse on its own either (the body of a broken heredoc)
+/// is copied verbatim too.
+pub fn format_source_tolerant(
+    source: &str,
+    config: &Config,
+) -> Result<(String, Vec<Range<usize>>)> {
+    SecurityPolicy::default().validate_source_size(source)?;
+
+    let error = match format_parsed(source, config) {
+        Err(error @ RfmtError::ParseError { .. }) => error,
+        result => return result.map(|formatted| (formatted, Vec::new())),
+    };
+    let broken = parser::recovery::broken_ranges(source);
+    if broken.is_empty() {
+        return Err(error);
+    }
+
+    let mut output = String::new();
+    let mut skipped = Vec::new();
+    let mut start = 0;
+    let end_of_input = source.len()..source.len();
+    for range in broken.into_iter().chain([end_of_input]) {
+        let code = &source[start..range.start];
+        let mut lines = code.split_inclusive('\n');
+        let (first, last) = (lines.next(), lines.next_back());
+        let blank = |line: Option<&str>| line.is_some_and(|line| line.trim().is_empty());
+        let (blank_before, blank_after) = (blank(first), blank(last.or(first)));
+
+        if code.trim().is_empty() {
+            if blank_before && !output.is_empty() && !range.is_empty() {
+                output.push('\n');
+            }
+        } else {
+            if blank_before && !output.is_empty() {
+                output.push('\n');
+            }
+            match format_parsed(code, config) {
+                Ok(formatted) => output.push_str(&formatted),
+                Err(_) => {
+                    output.push_str(code.trim_matches('\n'));
+                    output.push('\n');
+                    skipped.push(start..range.start);
+                }
+            }
+            if blank_after && !range.is_empty() {
+                output.push('\n');
+            }
+        }
+
+        output.push_str(&source[range.clone()]);
+        if !range.is_empty() {
+            skipped.push(range.clone());
+        }
+        start = range.end;
+    }
+
+    Ok((output, skipped))
 }
 
-fn format_impl(ruby: &Ruby, source: String, config_path: Option<String>) -> Result<String, Error> {
-    let policy = SecurityPolicy::default();
-
-    policy
-        .validate_source_size(&source)
-        .map_err(|e| e.to_magnus_error(ruby))?;
-
-    let parser = NativeAdapter::new();
-    let ast = parser.parse(&source).map_err(|e| e.to_magnus_error(ruby))?;
-
-    let config = Config::resolve(config_path.as_deref().map(std::path::Path::new))
-        .map_err(|e| e.to_magnus_error(ruby))?;
-    let formatter = Formatter::new(config);
+/// The parse, format and validate steps of [`format_source`].
+fn format_parsed(source: &str, config: &Config) -> Result<String> {
+    let ast = NativeAdapter::new().parse(source)?;
+    let formatted = Formatter::new(config.clone()).format(source, &ast)?;
 
-    let formatted = formatter
-        .format(&source, &ast)
-        .map_err(|e| e.to_magnus_error(ruby))?;
-
-    validation::validate_output(&formatted).map_err(|e| e.to_magnus_error(ruby))?;
+    validate(source, &ast, &formatted, config)?;
 
     Ok(formatted)
 }
 
-/// Serialize the effective configuration so Ruby can display exactly what
-/// the formatter will use (CLI `config` command, --config fail-fast check)
-fn resolved_config_yaml(ruby: &Ruby, config_path: Option<String>) -> Result<String, Error> {
-    let config = Config::resolve(config_path.as_deref().map(std::path::Path::new))
-        .map_err(|e| e.to_magnus_error(ruby))?;
-
-    serde_yaml::to_string(&config)
-        .map_err(|e| Error::new(ruby.exception_standard_error(), e.to_string()))
-}
-
-/// Parse Ruby source code and return the internal AST representation
-/// This is useful for debugging and integration testing
-fn parse_to_json(ruby: &Ruby, source: String) -> Result<String, Error> {
-    let parser = NativeAdapter::new();
-    let ast = parser.parse(&source).map_err(|e| e.to_magnus_error(ruby))?;
-
-    Ok(format!("{:#?}", ast))
+/// Checks formatted output: that it parses, that it keeps every comment
+/// and, with `validation.ast_equivalence`, that it means the same as
+/// `source`.
+fn validate(source: &str, ast: &Node, formatted: &str, config: &Config) -> Result<()> {
+    // Parsed once for every check
+    let parse_result = ruby_prism::parse(formatted.as_bytes());
+    validation::check_syntax(formatted, &parse_result)?;
+    validation::verify_comments(ast, formatted, &parse_result)?;
+    if config.validation.ast_equivalence {
+        validation::verify_equivalence(source, ast, formatted, &parse_result)?;
+    }
+    Ok(())
 }
 
-fn rust_version() -> String {
-    format!("{} (Rust)", env!("CARGO_PKG_VERSION"))
-}
+/// Formats Ruby `source` like [`format_source`], also returning where the
+/// cursor at byte offset `cursor_offset` ends up in the result (see
+/// [`Formatter::format_with_cursor`]).
+pub fn format_source_with_cursor(
+    source: &str,
+    config: &Config,
+    cursor_offset: usize,
+) -> Result<(String, usize)> {
+    SecurityPolicy::default().validate_source_size(source)?;
 
-#[magnus::init]
-fn init(ruby: &Ruby) -> Result<(), Error> {
-    logging::RfmtLogger::init();
+    let ast = NativeAdapter::new().parse(source)?;
+    let (formatted, cursor) =
+        Formatter::new(config.clone()).format_with_cursor(source, &ast, cursor_offset)?;
 
-    let module = ruby.define_module("Rfmt")?;
+    validate(source, &ast, &formatted, config)?;
 
-    module.define_singleton_method("format_code", function!(format_ruby_code, 1))?;
-    module.define_singleton_method(
-        "format_code_with_config",
-        function!(format_ruby_code_with_config, 2),
-    )?;
-    module.define_singleton_method("parse_to_json", function!(parse_to_json, 1))?;
-    module.define_singleton_method("resolved_config_yaml", function!(resolved_config_yaml, 1))?;
-    module.define_singleton_method("rust_version", function!(rust_version, 0))?;
+    Ok((formatted, cursor))
+}
 
-    Ok(())
+/// Formats the statements of Ruby `source` covering the byte range
+/// `start_offset..end_offset` (see [`Formatter::format_range`]), returning
+/// the edit to apply, or `None` when there is nothing to change.
+///
+/// The whole source must parse, and the edited source is validated like
+/// [`format_source`]'s output.
+pub fn format_source_range(
+    source: &str,
+    config: &Config,
+    start_offset: usize,
+    end_offset: usize,
+) -> Result<Option<TextEdit>> {
+    SecurityPolicy::default().validate_source_size(source)?;
+
+    let ast = NativeAdapter::new().parse(source)?;
+    let edit =
+        Formatter::new(config.clone()).format_range(source, &ast, start_offset, end_offset)?;
+
+    if let Some(edit) = &edit {
+        validate(source, &ast, &edit.apply(source), config)?;
+    }
+
+    Ok(edit)
 }
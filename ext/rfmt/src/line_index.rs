@@ -0,0 +1,113 @@
+//! Conversions from byte offsets to line/column positions.
+
+/// A zero-based line and UTF-16 code unit column, the way LSP positions
+/// count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// Line-start byte offsets over a source, for deriving lines and columns
+/// from byte offsets.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &[u8]) -> Self {
+        let mut line_starts = vec![0];
+        for (i, byte) in source.iter().enumerate() {
+            if *byte == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        Self { line_starts }
+    }
+
+    /// 1-based line and 0-based byte column of `offset`.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        (line, offset - self.line_starts[line - 1])
+    }
+
+    /// LSP position of `offset` in `source`, the text the index was built
+    /// from. `offset` must be a char boundary.
+    pub fn position(&self, source: &str, offset: usize) -> Position {
+        let (line, column) = self.line_column(offset);
+        Position {
+            line: line - 1,
+            character: source[offset - column..offset].encode_utf16().count(),
+        }
+    }
+
+    /// Byte offset of LSP `position` in `source`, the text the index was
+    /// built from. A line past the end maps to the end of `source`, a
+    /// column past the end of its line to the line's end.
+    pub fn offset(&self, source: &str, position: Position) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line) else {
+            return source.len();
+        };
+        let line_end = self
+            .line_starts
+            .get(position.line + 1)
+            .map_or(source.len(), |&next| next - 1);
+
+        let mut units = 0;
+        for (i, c) in source[line_start..line_end].char_indices() {
+            if units >= position.character {
+                return line_start + i;
+            }
+            units += c.len_utf16();
+        }
+        line_end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_column() {
+        let index = LineIndex::new(b"ab\ncd\n");
+        assert_eq!(index.line_column(0), (1, 0));
+        assert_eq!(index.line_column(4), (2, 1));
+        assert_eq!(index.line_column(6), (3, 0));
+    }
+
+    #[test]
+    fn test_position_counts_utf16_units() {
+        let source = "x = 1\ns = \"é😀\" # ok\n";
+        let index = LineIndex::new(source.as_bytes());
+        let comment = source.find('#').unwrap();
+        // é is one UTF-16 unit, 😀 a surrogate pair
+        assert_eq!(
+            index.position(source, comment),
+            Position {
+                line: 1,
+                character: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_offset_inverts_position() {
+        let source = "x = 1\ns = \"é😀\" # ok\n";
+        let index = LineIndex::new(source.as_bytes());
+        for (offset, _) in source.char_indices() {
+            assert_eq!(index.offset(source, index.position(source, offset)), offset);
+        }
+    }
+
+    #[test]
+    fn test_offset_clamps_past_the_end() {
+        let source = "ab\ncd\n";
+        let index = LineIndex::new(source.as_bytes());
+        let offset = |line, character| index.offset(source, Position { line, character });
+        assert_eq!(offset(0, 9), 2);
+        assert_eq!(offset(2, 0), 6);
+        assert_eq!(offset(7, 0), 6);
+    }
+}
@@ -0,0 +1,47 @@
+//! Native language server, served over stdio by the `rfmt-lsp` binary.
+//!
+//! Besides whole-document formatting like the gem's lib/rfmt/lsp, it
+//! handles range formatting and on-type formatting after `end`, re-reads
+//! `.rfmt.yml` when the client reports a change to it, and publishes parse
+//! and configuration errors as diagnostics.
+//!
+//! [`Server`] works on decoded JSON-RPC messages, so tests drive it in
+//! process; [`run`] frames it over a reader and a writer.
+
+mod server;
+mod transport;
+mod uri;
+mod workspace;
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+pub use server::Server;
+pub use transport::{read_message, write_message};
+pub use uri::{file_uri_to_path, path_to_file_uri};
+
+/// Serves the messages read from `input` until an `exit` notification or
+/// the end of input, returning the process exit code.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server::new();
+
+    while let Some(body) = read_message(&mut input)? {
+        let outgoing = match serde_json::from_str::<Value>(&body) {
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![server::error_response(
+                &Value::Null,
+                server::PARSE_ERROR,
+                &e.to_string(),
+            )],
+        };
+        for message in &outgoing {
+            write_message(&mut output, message)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+
+    Ok(0)
+}
@@ -0,0 +1,553 @@
+//! Message dispatch, mirroring lib/rfmt/lsp/server.rb.
+
+use std::collections::{BTreeSet, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use super::uri::file_uri_to_path;
+use super::workspace::Workspace;
+use crate::config::{Config, CONFIG_FILE_NAMES, IMPORTED_FILE_NAMES};
+use crate::error::{Result, RfmtError};
+use crate::line_index::{LineIndex, Position};
+
+const TEXT_DOCUMENT_SYNC_FULL: u8 = 1;
+const DIAGNOSTIC_SEVERITY_ERROR: u8 = 1;
+
+pub(super) const PARSE_ERROR: i64 = -32_700;
+const INVALID_REQUEST: i64 = -32_600;
+const METHOD_NOT_FOUND: i64 = -32_601;
+const INVALID_PARAMS: i64 = -32_602;
+const INTERNAL_ERROR: i64 = -32_603;
+const REQUEST_FAILED: i64 = -32_803;
+
+/// Id of the file watcher registered for configuration files; watchers
+/// for files found later (`inherit_from`) get a numbered suffix.
+const CONFIG_WATCHER_ID: &str = "rfmt-config-files";
+
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+impl ResponseError {
+    fn invalid_params(message: &str) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.to_string(),
+        }
+    }
+}
+
+type Response = std::result::Result<Value, ResponseError>;
+
+/// An open document as the client last sent it.
+#[derive(Debug)]
+struct Document {
+    text: String,
+    /// The client's version number, if it sent one.
+    version: Option<i64>,
+}
+
+/// Language server state: open documents and the workspace they belong
+/// to.
+///
+/// [`Server::handle`] takes decoded JSON-RPC messages and returns the ones
+/// to send back, so tests can drive the server in process;
+/// [`super::run`] frames it over stdio.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    workspace: Workspace,
+    /// The client can watch files for us (dynamic registration of
+    /// `workspace/didChangeWatchedFiles`).
+    watch_config_files: bool,
+    /// Files outside the `initialized` watcher's patterns that watchers
+    /// have been registered for since.
+    watched_files: BTreeSet<PathBuf>,
+    /// Number of watchers registered so far.
+    watchers: usize,
+    shutdown_requested: bool,
+    exit_code: Option<i32>,
+    next_request_id: u64,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles one message from the client, returning the messages to
+    /// send: the response to a request, or the diagnostics and requests a
+    /// notification gives rise to.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            // A response to one of our requests; nothing waits on it
+            return Vec::new();
+        };
+        let params = &message["params"];
+
+        // A formatter bug must not take the editor's server down with it
+        let mut messages = match message.get("id") {
+            Some(id) => {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| self.request(method, params)))
+                    .unwrap_or_else(|_| {
+                        Err(ResponseError {
+                            code: INTERNAL_ERROR,
+                            message: format!("Internal error while handling {}", method),
+                        })
+                    });
+                vec![response(id, result)]
+            }
+            None => panic::catch_unwind(AssertUnwindSafe(|| self.notification(method, params)))
+                .unwrap_or_default(),
+        };
+        messages.extend(self.watch_read_config_files());
+        messages
+    }
+
+    /// Process exit code once `exit` has been received: 0 after a
+    /// `shutdown` request, 1 otherwise.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Response {
+        if self.shutdown_requested {
+            return Err(ResponseError {
+                code: INVALID_REQUEST,
+                message: "Server is shutting down".to_string(),
+            });
+        }
+
+        match method {
+            "initialize" => Ok(self.initialize(params)),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            "textDocument/formatting" => self.formatting(params),
+            "textDocument/rangeFormatting" => self.range_formatting(params),
+            "textDocument/onTypeFormatting" => self.on_type_formatting(params),
+            _ => Err(ResponseError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Method not found: {}", method),
+            }),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str();
+
+        match (method, uri) {
+            ("initialized", _) if self.watch_config_files => vec![self.register_config_watcher()],
+            ("textDocument/didOpen", Some(uri)) => {
+                let Some(text) = params["textDocument"]["text"].as_str() else {
+                    return Vec::new();
+                };
+                let document = Document {
+                    text: text.to_string(),
+                    version: params["textDocument"]["version"].as_i64(),
+                };
+                self.documents.insert(uri.to_string(), document);
+                vec![self.publish_diagnostics(uri)]
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                let changes = params["contentChanges"].as_array().into_iter().flatten();
+                let Some(text) = changes.filter_map(|c| c["text"].as_str()).next_back() else {
+                    return Vec::new();
+                };
+                let version = params["textDocument"]["version"].as_i64();
+                // Already checked at this version
+                if version.is_some()
+                    && self
+                        .documents
+                        .get(uri)
+                        .and_then(|document| document.version)
+                        == version
+                {
+                    return Vec::new();
+                }
+                let document = Document {
+                    text: text.to_string(),
+                    version,
+                };
+                self.documents.insert(uri.to_string(), document);
+                vec![self.publish_diagnostics(uri)]
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(uri);
+                vec![publish_diagnostics(uri, Vec::new())]
+            }
+            ("workspace/didChangeWatchedFiles", _) => {
+                let changes = params["changes"].as_array().into_iter().flatten();
+                let touches_config = changes
+                    .filter_map(|change| change["uri"].as_str())
+                    .filter_map(file_uri_to_path)
+                    .any(|path| is_config_file(&path) || self.workspace.reads(&path));
+                if touches_config {
+                    self.reload()
+                } else {
+                    Vec::new()
+                }
+            }
+            ("workspace/didChangeConfiguration", _) => self.reload(),
+            ("exit", _) => {
+                self.exit_code = Some(if self.shutdown_requested { 0 } else { 1 });
+                Vec::new()
+            }
+            // `initialized`, `$/` notifications and anything unknown
+            _ => Vec::new(),
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        self.workspace.configure(params);
+        self.watch_config_files = params["capabilities"]["workspace"]["didChangeWatchedFiles"]
+            ["dynamicRegistration"]
+            .as_bool()
+            .unwrap_or(false);
+
+        json!({
+            "capabilities": {
+                "documentFormattingProvider": true,
+                "documentRangeFormattingProvider": true,
+                // Formats the construct an `end` closes as it is typed
+                "documentOnTypeFormattingProvider": { "firstTriggerCharacter": "d" },
+                "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL,
+            },
+            "serverInfo": {
+                "name": "rfmt",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        })
+    }
+
+    fn register_config_watcher(&mut self) -> Value {
+        let patterns = CONFIG_FILE_NAMES
+            .iter()
+            .chain(&IMPORTED_FILE_NAMES)
+            .map(|name| format!("**/{}", name))
+            .collect();
+        self.register_watcher(patterns)
+    }
+
+    /// Watches the files configurations were read from that the patterns
+    /// registered on `initialized` miss, like `inherit_from: ../shared.yml`.
+    fn watch_read_config_files(&mut self) -> Option<Value> {
+        if self.watchers == 0 {
+            return None;
+        }
+        let unwatched: Vec<PathBuf> = self
+            .workspace
+            .config_files()
+            .into_iter()
+            .filter(|path| !is_config_file(path) && !self.watched_files.contains(path))
+            .collect();
+        if unwatched.is_empty() {
+            return None;
+        }
+
+        let patterns = unwatched
+            .iter()
+            .map(|path| path.to_string_lossy().replace('\\', "/"))
+            .collect();
+        self.watched_files.extend(unwatched);
+        Some(self.register_watcher(patterns))
+    }
+
+    fn register_watcher(&mut self, patterns: Vec<String>) -> Value {
+        self.next_request_id += 1;
+        let id = match self.watchers {
+            0 => CONFIG_WATCHER_ID.to_string(),
+            n => format!("{}-{}", CONFIG_WATCHER_ID, n),
+        };
+        self.watchers += 1;
+        let watchers: Vec<Value> = patterns
+            .into_iter()
+            .map(|pattern| json!({ "globPattern": pattern }))
+            .collect();
+
+        json!({
+            "jsonrpc": "2.0",
+            "id": self.next_request_id,
+            "method": "client/registerCapability",
+            "params": {
+                "registrations": [{
+                    "id": id,
+                    "method": "workspace/didChangeWatchedFiles",
+                    "registerOptions": { "watchers": watchers },
+                }],
+            },
+        })
+    }
+
+    /// Drops cached configurations and re-checks every open document
+    /// against the new ones.
+    fn reload(&mut self) -> Vec<Value> {
+        self.workspace.reload();
+        let mut uris: Vec<String> = self.documents.keys().cloned().collect();
+        uris.sort();
+        uris.iter()
+            .map(|uri| self.publish_diagnostics(uri))
+            .collect()
+    }
+
+    fn formatting(&mut self, params: &Value) -> Response {
+        let uri = document_uri(params)?;
+        let Some((source, config)) = self.document(uri) else {
+            return Ok(json!([]));
+        };
+
+        let formatted = if source.is_empty() {
+            "\n".to_string()
+        } else {
+            match crate::format_source(&source, &config) {
+                Ok(formatted) => formatted,
+                // Reported through diagnostics instead
+                Err(RfmtError::ParseError { .. }) => return Ok(json!([])),
+                Err(error) => {
+                    return Err(ResponseError {
+                        code: REQUEST_FAILED,
+                        message: error.to_string(),
+                    })
+                }
+            }
+        };
+
+        let edits = crate::diff::lsp_text_edits(&source, &formatted)
+            .into_iter()
+            .map(|(range, new_text)| text_edit(range.start, range.end, &new_text))
+            .collect();
+        Ok(Value::Array(edits))
+    }
+
+    fn range_formatting(&mut self, params: &Value) -> Response {
+        let uri = document_uri(params)?;
+        let start = position(&params["range"]["start"])?;
+        let end = position(&params["range"]["end"])?;
+        let Some((source, config)) = self.document(uri) else {
+            return Ok(json!([]));
+        };
+
+        let index = LineIndex::new(source.as_bytes());
+        let start = index.offset(&source, start);
+        let end = index.offset(&source, end);
+        Ok(range_edits(
+            &source,
+            &config,
+            start.min(end),
+            start.max(end),
+        ))
+    }
+
+    fn on_type_formatting(&mut self, params: &Value) -> Response {
+        let uri = document_uri(params)?;
+        let position = position(&params["position"])?;
+        if params["ch"].as_str() != Some("d") {
+            return Ok(json!([]));
+        }
+        let Some((source, config)) = self.document(uri) else {
+            return Ok(json!([]));
+        };
+
+        let offset = LineIndex::new(source.as_bytes()).offset(&source, position);
+        if !ends_with_end_keyword(&source[..offset]) {
+            return Ok(json!([]));
+        }
+        // The range formatter widens the keyword to the statement it closes
+        Ok(range_edits(&source, &config, offset - "end".len(), offset))
+    }
+
+    /// Text of the document at `uri`, from the client or else from disk,
+    /// with its configuration.
+    fn document(&mut self, uri: &str) -> Option<(String, Config)> {
+        let source = match self.documents.get(uri) {
+            Some(document) => document.text.clone(),
+            None => {
+                let path = file_uri_to_path(uri).filter(|path| path.is_file())?;
+                std::fs::read_to_string(path).ok()?
+            }
+        };
+        let config = self.workspace.config_for(uri).ok()?;
+        Some((source, config))
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> Value {
+        let config = self.workspace.config_for(uri);
+        let diagnostics = match self.documents.get(uri) {
+            Some(document) => diagnostics(&document.text, config),
+            None => Vec::new(),
+        };
+        publish_diagnostics(uri, diagnostics)
+    }
+}
+
+fn response(id: &Value, result: Response) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_response(id, error.code, &error.message),
+    }
+}
+
+pub(super) fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn document_uri(params: &Value) -> std::result::Result<&str, ResponseError> {
+    params["textDocument"]["uri"]
+        .as_str()
+        .ok_or_else(|| ResponseError::invalid_params("Missing textDocument.uri"))
+}
+
+fn position(value: &Value) -> std::result::Result<Position, ResponseError> {
+    let field = |name: &str| value[name].as_u64().map(|n| n as usize);
+    match (field("line"), field("character")) {
+        (Some(line), Some(character)) => Ok(Position { line, character }),
+        _ => Err(ResponseError::invalid_params("Invalid position")),
+    }
+}
+
+fn range(start: Position, end: Position) -> Value {
+    json!({
+        "start": { "line": start.line, "character": start.character },
+        "end": { "line": end.line, "character": end.character },
+    })
+}
+
+fn text_edit(start: Position, end: Position, new_text: &str) -> Value {
+    json!({ "range": range(start, end), "newText": new_text })
+}
+
+/// Edits formatting the statements covering `start..end`, as minimal
+/// edits within the lines the range formatter replaces.
+fn range_edits(source: &str, config: &Config, start: usize, end: usize) -> Value {
+    let Ok(Some(edit)) = crate::format_source_range(source, config, start, end) else {
+        return json!([]);
+    };
+
+    let origin = LineIndex::new(source.as_bytes()).position(source, edit.range.start);
+    let shift = |position: Position| match position.line {
+        0 => Position {
+            line: origin.line,
+            character: origin.character + position.character,
+        },
+        line => Position {
+            line: origin.line + line,
+            character: position.character,
+        },
+    };
+    let edits = crate::diff::lsp_text_edits(&source[edit.range.clone()], &edit.new_text)
+        .into_iter()
+        .map(|(range, new_text)| text_edit(shift(range.start), shift(range.end), &new_text))
+        .collect();
+    Value::Array(edits)
+}
+
+/// Whether the text before the cursor ends with the keyword `end`, rather
+/// than a word ending in it (`append`) or a call or symbol (`.end`, `:end`).
+fn ends_with_end_keyword(before: &str) -> bool {
+    let line = before.rsplit('\n').next().unwrap_or(before);
+    line.strip_suffix("end").is_some_and(|rest| {
+        rest.is_empty() || rest.ends_with(|c: char| c.is_whitespace() || c == ';')
+    })
+}
+
+/// Whether `path` is named like a file configuration is discovered or
+/// imported from.
+fn is_config_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            CONFIG_FILE_NAMES.contains(&name) || IMPORTED_FILE_NAMES.contains(&name)
+        })
+}
+
+/// Parse errors at their locations, or a configuration error at the start
+/// of the document. Only parsing runs on every change; a formatting failure
+/// is the formatting request's error.
+fn diagnostics(source: &str, config: Result<Config>) -> Vec<Value> {
+    let index = LineIndex::new(source.as_bytes());
+    let parse_result = ruby_prism::parse(source.as_bytes());
+    let parse_errors: Vec<Value> = parse_result
+        .errors()
+        .map(|error| {
+            let location = error.location();
+            let start = char_boundary(source, location.start_offset());
+            let end = char_boundary(source, location.end_offset());
+            diagnostic(
+                index.position(source, start),
+                index.position(source, end),
+                error.message(),
+            )
+        })
+        .collect();
+    if !parse_errors.is_empty() {
+        return parse_errors;
+    }
+
+    let start = Position {
+        line: 0,
+        character: 0,
+    };
+    config
+        .err()
+        .map(|error| diagnostic(start, start, &error.to_string()))
+        .into_iter()
+        .collect()
+}
+
+fn diagnostic(start: Position, end: Position, message: &str) -> Value {
+    json!({
+        "range": range(start, end),
+        "severity": DIAGNOSTIC_SEVERITY_ERROR,
+        "source": "rfmt",
+        "message": message,
+    })
+}
+
+/// `offset` clamped into `source` and moved back to a char boundary.
+fn char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ends_with_end_keyword() {
+        assert!(ends_with_end_keyword("def a\n  1\nend"));
+        assert!(ends_with_end_keyword("  if a; b; end"));
+        assert!(!ends_with_end_keyword("list.append"));
+        assert!(!ends_with_end_keyword("range.end"));
+        assert!(!ends_with_end_keyword("x = :end"));
+        assert!(!ends_with_end_keyword("end\n"));
+    }
+
+    #[test]
+    fn test_is_config_file() {
+        assert!(is_config_file(Path::new("/app/.rfmt.yml")));
+        assert!(is_config_file(Path::new("/app/rfmt.yaml")));
+        assert!(is_config_file(Path::new("/app/.rubocop.yml")));
+        assert!(is_config_file(Path::new("/app/.editorconfig")));
+        assert!(!is_config_file(Path::new("/app/shared.yml")));
+    }
+}
@@ -0,0 +1,106 @@
+//! LSP base protocol framing: a `Content-Length` header block, a blank
+//! line, then the JSON body.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+/// Reads the next message body, or `None` at end of input.
+///
+/// Headers other than `Content-Length` (such as `Content-Type`) are
+/// skipped. A header block without `Content-Length` cannot be resynced
+/// past, so it is an error.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length_header = None;
+    let mut seen_header = false;
+    let mut line = String::new();
+
+    let content_length = loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            // Blank lines before a header block are tolerated
+            match length_header {
+                Some(length) => break length,
+                None if seen_header => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Missing Content-Length header",
+                    ))
+                }
+                None => continue,
+            }
+        }
+        seen_header = true;
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let length = value.trim().parse::<usize>().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid Content-Length: {}", value.trim()),
+                    )
+                })?;
+                length_header = Some(length);
+            }
+        }
+    };
+
+    let mut body = vec![0; content_length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes `message` with its header and flushes, so the client sees it
+/// before the next request is read.
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip() {
+        let mut framed = Vec::new();
+        write_message(&mut framed, &json!({"id": 1, "method": "é"})).unwrap();
+        write_message(&mut framed, &json!({"id": 2})).unwrap();
+
+        let mut input = Cursor::new(framed);
+        let first = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(first, r#"{"id":1,"method":"é"}"#);
+        let second = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(second, r#"{"id":2}"#);
+        assert!(read_message(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_skips_other_headers() {
+        let framed = "content-length: 2\r\nContent-Type: application/vscode-jsonrpc\r\n\r\n{}";
+        let mut input = Cursor::new(framed.as_bytes());
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_missing_content_length() {
+        let mut input = Cursor::new(&b"Content-Type: text/plain\r\n\r\n{}"[..]);
+        let err = read_message(&mut input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_invalid_content_length() {
+        let mut input = Cursor::new(&b"Content-Length: lots\r\n\r\n{}"[..]);
+        let err = read_message(&mut input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,82 @@
+//! `file://` URI conversions, matching lib/rfmt/lsp/uri.rb.
+
+use std::path::{Path, PathBuf};
+
+/// Path of a `file://` URI, or `None` for other schemes and URIs that do
+/// not decode to UTF-8.
+pub fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // An authority (`file://host/path`) is dropped; only its path is local
+    let path = &rest[rest.find('/')?..];
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    percent_decode(path).map(PathBuf::from)
+}
+
+/// `file://` URI of `path`, made absolute against the cwd.
+pub fn path_to_file_uri(path: &Path) -> String {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    format!("file://{}", percent_encode(&absolute.to_string_lossy()))
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = match bytes[i] {
+            b'%' => value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"._~/-".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_uri_to_path() {
+        assert_eq!(
+            file_uri_to_path("file:///tmp/my%20app/caf%C3%A9.rb"),
+            Some(PathBuf::from("/tmp/my app/café.rb"))
+        );
+        assert_eq!(
+            file_uri_to_path("file://localhost/tmp/a.rb"),
+            Some(PathBuf::from("/tmp/a.rb"))
+        );
+        assert_eq!(file_uri_to_path("untitled:Untitled-1"), None);
+    }
+
+    #[test]
+    fn test_path_to_file_uri_round_trips() {
+        let path = Path::new("/tmp/my app/café.rb");
+        let uri = path_to_file_uri(path);
+        assert_eq!(uri, "file:///tmp/my%20app/caf%C3%A9.rb");
+        assert_eq!(file_uri_to_path(&uri).as_deref(), Some(path));
+    }
+}
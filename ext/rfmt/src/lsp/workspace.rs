@@ -0,0 +1,240 @@
+//! Workspace roots and the configuration each document formats with.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::uri::file_uri_to_path;
+use crate::config::Config;
+use crate::error::Result;
+
+/// A document's configuration is discovered from its own directory, like
+/// the command line does for each file. Results are kept per directory
+/// until [`Workspace::reload`].
+#[derive(Debug, Default)]
+pub struct Workspace {
+    roots: Vec<PathBuf>,
+    /// `initializationOptions.configPath`: one file for every document.
+    config_path: Option<PathBuf>,
+    /// Discovered configurations by directory, with the files each was
+    /// read from.
+    configs: HashMap<PathBuf, (Config, Vec<PathBuf>)>,
+}
+
+impl Workspace {
+    /// Takes the roots and options of an `initialize` request.
+    pub fn configure(&mut self, params: &Value) {
+        let folders = params["workspaceFolders"].as_array().into_iter().flatten();
+        let uris = folders
+            .filter_map(|folder| folder["uri"].as_str())
+            .chain(params["rootUri"].as_str());
+        self.roots.clear();
+        for root in uris.filter_map(file_uri_to_path) {
+            if !self.roots.contains(&root) {
+                self.roots.push(root);
+            }
+        }
+
+        self.config_path = params["initializationOptions"]["configPath"]
+            .as_str()
+            .map(PathBuf::from);
+        self.configs.clear();
+    }
+
+    /// Innermost workspace folder containing `uri`, falling back to the
+    /// document's own directory, like lib/rfmt/lsp/workspace.rb.
+    pub fn root_for(&self, uri: &str) -> Option<PathBuf> {
+        let Some(path) = file_uri_to_path(uri) else {
+            return self.roots.first().filter(|root| root.is_dir()).cloned();
+        };
+
+        let root = self
+            .roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.as_os_str().len())
+            .cloned()
+            .or_else(|| path.parent().map(Path::to_path_buf));
+        root.filter(|root| root.is_dir())
+    }
+
+    /// Configuration for the document at `uri`: discovered from the
+    /// document's directory (the root for documents without a path), with
+    /// the overrides matching its path applied. Only an explicit
+    /// `configPath` can fail; a broken discovered file falls back to the
+    /// defaults, as on the command line.
+    pub fn config_for(&mut self, uri: &str) -> Result<Config> {
+        let path = file_uri_to_path(uri);
+        let config = if let Some(config_path) = &self.config_path {
+            Config::resolve(Some(config_path), None)?
+        } else {
+            let dir = path
+                .as_deref()
+                .and_then(Path::parent)
+                .filter(|dir| dir.is_dir())
+                .map(Path::to_path_buf)
+                .or_else(|| self.root_for(uri));
+            let Some(dir) = dir else {
+                return Config::resolve(None, None);
+            };
+            self.configs
+                .entry(dir)
+                .or_insert_with_key(|dir| Config::discover_reading(dir))
+                .0
+                .clone()
+        };
+
+        let Some(path) = path else {
+            return Ok(config);
+        };
+        // Explicit configurations match relative to the workspace root.
+        let base_dir = config.base_dir.clone().or_else(|| self.root_for(uri));
+        let relative = base_dir
+            .as_deref()
+            .and_then(|dir| path.strip_prefix(dir).ok())
+            .unwrap_or(&path);
+        Ok(config.for_file(relative))
+    }
+
+    /// Forgets discovered configurations, so the next request reads the
+    /// files again.
+    pub fn reload(&mut self) {
+        self.configs.clear();
+    }
+
+    /// Files the configurations in use were read from, `configPath` and
+    /// `inherit_from` files included.
+    pub fn config_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self
+            .configs
+            .values()
+            .flat_map(|(_, files)| files.iter().map(|file| canonical(file)))
+            .chain(self.config_path.as_deref().map(canonical))
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Whether a configuration in use was read from `path`.
+    pub fn reads(&self, path: &Path) -> bool {
+        self.config_files().contains(&canonical(path))
+    }
+}
+
+/// `path` with its directory resolved, so `app/../shared.yml` matches
+/// `shared.yml`; the file itself may have been deleted.
+fn canonical(path: &Path) -> PathBuf {
+    match (path.parent().map(Path::canonicalize), path.file_name()) {
+        (Some(Ok(dir)), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp::uri::path_to_file_uri;
+    use serde_json::json;
+
+    #[test]
+    fn test_root_for_prefers_innermost_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = dir.path().join("gems/inner");
+        std::fs::create_dir_all(inner.join("lib")).unwrap();
+
+        let mut workspace = Workspace::default();
+        workspace.configure(&json!({
+            "rootUri": path_to_file_uri(dir.path()),
+            "workspaceFolders": [{"uri": path_to_file_uri(&inner), "name": "inner"}],
+        }));
+
+        let nested = path_to_file_uri(&inner.join("lib/a.rb"));
+        assert_eq!(workspace.root_for(&nested), Some(inner));
+        let top = path_to_file_uri(&dir.path().join("a.rb"));
+        assert_eq!(workspace.root_for(&top), Some(dir.path().to_path_buf()));
+    }
+
+    #[test]
+    fn test_config_for_applies_overrides_below_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(".rfmt.yml"),
+            "overrides:\n  - files: [\"spec/**\"]\n    formatting:\n      line_length: 120\n",
+        )
+        .unwrap();
+
+        let mut workspace = Workspace::default();
+        workspace.configure(&json!({"rootUri": path_to_file_uri(dir.path())}));
+
+        let spec = path_to_file_uri(&dir.path().join("spec/a_spec.rb"));
+        let config = workspace.config_for(&spec).unwrap();
+        assert_eq!(config.formatting.line_length, 120);
+        let lib = path_to_file_uri(&dir.path().join("lib/a.rb"));
+        let config = workspace.config_for(&lib).unwrap();
+        assert_eq!(config.formatting.line_length, 100);
+    }
+
+    #[test]
+    fn test_config_for_discovers_from_document_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = dir.path().join("engines/billing");
+        std::fs::create_dir_all(engine.join("lib")).unwrap();
+        std::fs::write(
+            dir.path().join(".rfmt.yml"),
+            "formatting:\n  indent_width: 4\n",
+        )
+        .unwrap();
+        std::fs::write(engine.join(".rfmt.yml"), "formatting:\n  line_length: 80\n").unwrap();
+
+        let mut workspace = Workspace::default();
+        workspace.configure(&json!({"rootUri": path_to_file_uri(dir.path())}));
+
+        let config = workspace
+            .config_for(&path_to_file_uri(&engine.join("lib/invoice.rb")))
+            .unwrap();
+        assert_eq!(config.formatting.line_length, 80);
+        assert_eq!(config.formatting.indent_width, 4);
+        let config = workspace
+            .config_for(&path_to_file_uri(&dir.path().join("a.rb")))
+            .unwrap();
+        assert_eq!(config.formatting.line_length, 100);
+    }
+
+    #[test]
+    fn test_reads_inherited_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("app");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(
+            dir.path().join("shared.yml"),
+            "formatting:\n  indent_width: 4\n",
+        )
+        .unwrap();
+        std::fs::write(app.join(".rfmt.yml"), "inherit_from: ../shared.yml\n").unwrap();
+
+        let mut workspace = Workspace::default();
+        workspace.configure(&json!({"rootUri": path_to_file_uri(dir.path())}));
+        assert!(!workspace.reads(&dir.path().join("shared.yml")));
+
+        let config = workspace
+            .config_for(&path_to_file_uri(&app.join("a.rb")))
+            .unwrap();
+        assert_eq!(config.formatting.indent_width, 4);
+        assert!(workspace.reads(&dir.path().join("shared.yml")));
+        assert!(workspace.reads(&app.join(".rfmt.yml")));
+        assert!(!workspace.reads(&dir.path().join("other.yml")));
+
+        workspace.reload();
+        assert!(!workspace.reads(&dir.path().join("shared.yml")));
+    }
+
+    #[test]
+    fn test_root_for_falls_back_to_document_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = Workspace::default();
+        let uri = path_to_file_uri(&dir.path().join("script.rb"));
+        assert_eq!(workspace.root_for(&uri), Some(dir.path().to_path_buf()));
+    }
+}
@@ -3,6 +3,7 @@ use crate::error::Result;
 
 pub mod native_adapter;
 pub mod prism_adapter;
+pub mod recovery;
 pub use native_adapter::NativeAdapter;
 pub use prism_adapter::PrismAdapter;
 
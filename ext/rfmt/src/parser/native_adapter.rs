@@ -9,7 +9,8 @@
 use crate::ast::{
     Comment, CommentPosition, CommentType, FormattingInfo, Location, Node as AstNode, NodeType,
 };
-use crate::error::{Result, RfmtError};
+use crate::error::{ParseDiagnostic, Result, RfmtError};
+use crate::line_index::LineIndex;
 use crate::parser::RubyParser;
 use ruby_prism::{
     ArgumentsNode, ConstantId, Location as PrismLocation, Node as PrismNode, ParseResult, Visit,
@@ -24,40 +25,34 @@ impl NativeAdapter {
     pub fn new() -> Self {
         Self
     }
-}
-
-impl Default for NativeAdapter {
-    fn default() -> Self {
-        Self::new()
-    }
-}
 
-impl RubyParser for NativeAdapter {
-    fn parse(&self, source: &str) -> Result<AstNode> {
-        let parse_result = ruby_prism::parse(source.as_bytes());
+    /// Converts `parse_result`, prism's parse of `source`, to the internal
+    /// tree, for callers that have already parsed the source themselves.
+    pub fn convert(&self, source: &str, parse_result: &ParseResult<'_>) -> Result<AstNode> {
         let index = LineIndex::new(source.as_bytes());
 
-        let errors: Vec<String> = parse_result
+        let errors: Vec<ParseDiagnostic> = parse_result
             .errors()
             .map(|error| {
-                let (line, column) = index.line_column(error.location().start_offset());
-                format!("{}:{}: {}", line, column, error.message())
+                let location = error.location();
+                ParseDiagnostic::new(
+                    &index,
+                    error.message(),
+                    location.start_offset()..location.end_offset(),
+                )
             })
             .collect();
         if !errors.is_empty() {
-            // ParseError so lib/rfmt.rb surfaces it as Rfmt::Error
-            // "Failed to parse Ruby code: ..." (same shape the pre-migration Ruby bridge raised).
-            return Err(RfmtError::ParseError(format!(
-                "Parse errors:\n{}",
-                errors.join("\n")
-            )));
+            // Raised in Ruby as Rfmt::ParseError "Failed to parse Ruby code: ..."
+            // (same message the pre-migration Ruby bridge raised).
+            return Err(RfmtError::ParseError { file: None, errors });
         }
 
         let converter = Converter { index: &index };
         let mut root = converter.convert(&parse_result.node()).node;
         // As in the bridge/PrismAdapter pipeline, all comments live in a flat
         // list on the root node; per-node comments stay empty.
-        root.comments = root_comments(&parse_result, &index);
+        root.comments = root_comments(parse_result, &index);
         // The AST has no node for the `__END__` data section; record where it
         // starts so the formatter can re-append the source slice verbatim.
         if let Some(data_loc) = parse_result.data_loc() {
@@ -70,6 +65,18 @@ impl RubyParser for NativeAdapter {
     }
 }
 
+impl Default for NativeAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RubyParser for NativeAdapter {
+    fn parse(&self, source: &str) -> Result<AstNode> {
+        self.convert(source, &ruby_prism::parse(source.as_bytes()))
+    }
+}
+
 /// The bridge's serialize_ast_with_comments: every comment with its source
 /// slice as text, always Leading (refined later by the formatter).
 fn root_comments(result: &ParseResult<'_>, index: &LineIndex) -> Vec<Comment> {
@@ -106,29 +113,6 @@ fn root_comments(result: &ParseResult<'_>, index: &LineIndex) -> Vec<Comment> {
         .collect()
 }
 
-/// Line-start byte offsets over the source, for deriving 1-based lines and
-/// 0-based byte columns from prism's byte offsets.
-struct LineIndex {
-    line_starts: Vec<usize>,
-}
-
-impl LineIndex {
-    fn new(source: &[u8]) -> Self {
-        let mut line_starts = vec![0];
-        for (i, byte) in source.iter().enumerate() {
-            if *byte == b'\n' {
-                line_starts.push(i + 1);
-            }
-        }
-        Self { line_starts }
-    }
-
-    fn line_column(&self, offset: usize) -> (usize, usize) {
-        let line = self.line_starts.partition_point(|&start| start <= offset);
-        (line, offset - self.line_starts[line - 1])
-    }
-}
-
 /// A widening candidate derived from some node's `closing_loc`, with the
 /// heredoc terminator-line snap already applied to `end_line`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -876,6 +860,10 @@ fn extract_metadata(node: &PrismNode<'_>) -> HashMap<String, String> {
                     n.lparen_loc().is_some().to_string(),
                 );
             }
+            // Endless defs (`def x = 1`) have no `end` to rebuild
+            if n.equal_loc().is_some() {
+                metadata.insert("endless".to_string(), "true".to_string());
+            }
             if let Some(receiver) = n.receiver() {
                 let value = if receiver.as_self_node().is_some() {
                     "self".to_string()
@@ -897,6 +885,14 @@ fn extract_metadata(node: &PrismNode<'_>) -> HashMap<String, String> {
             let n = node.as_instance_variable_write_node().unwrap();
             metadata.insert("name".to_string(), constant_id_string(&n.name()));
         }
+        PrismNode::AssocNode { .. } => {
+            let n = node.as_assoc_node().unwrap();
+            let (kind, name) = assoc_key_kind(&n.key());
+            metadata.insert("key_kind".to_string(), kind.to_string());
+            if let Some(name) = name {
+                metadata.insert("key_name".to_string(), name);
+            }
+        }
         // UnlessNode gets no is_ternary key: the bridge guards on
         // respond_to?(:if_keyword_loc), which UnlessNode lacks.
         PrismNode::IfNode { .. } => {
@@ -912,6 +908,35 @@ fn extract_metadata(node: &PrismNode<'_>) -> HashMap<String, String> {
     metadata
 }
 
+/// How a hash key is spelled, so hash-syntax normalization can tell `key:`
+/// from `:key =>` without re-lexing: `label` / `quoted_label` (`key:` /
+/// `"key":`), `symbol` / `quoted_symbol` (`:key` / `:"key"`), or `other`.
+/// Plain symbol values ride along when they are valid UTF-8. Native-only:
+/// the bridge never emitted these keys.
+fn assoc_key_kind(key: &PrismNode<'_>) -> (&'static str, Option<String>) {
+    let (opening, closing, name) = if let Some(symbol) = key.as_symbol_node() {
+        let name = std::str::from_utf8(symbol.unescaped())
+            .ok()
+            .map(str::to_string);
+        (symbol.opening_loc(), symbol.closing_loc(), name)
+    } else if let Some(symbol) = key.as_interpolated_symbol_node() {
+        (symbol.opening_loc(), symbol.closing_loc(), None)
+    } else {
+        return ("other", None);
+    };
+
+    let opening = opening.map(|loc| loc.as_slice()).unwrap_or_default();
+    let closing = closing.map(|loc| loc.as_slice()).unwrap_or_default();
+    let kind = match (opening, closing) {
+        (b"", b":") => "label",
+        (b"\"" | b"'", b"\":" | b"':") => "quoted_label",
+        (b":", b"") => "symbol",
+        (b":\"" | b":'", _) => "quoted_symbol",
+        _ => "other",
+    };
+    (kind, name)
+}
+
 fn slice_string(loc: &PrismLocation<'_>) -> String {
     String::from_utf8_lossy(loc.as_slice()).into_owned()
 }
@@ -27,8 +27,9 @@ impl PrismAdapter {
         }
 
         // Fall back to old format (single node without comments)
-        let node: PrismNode = serde_json::from_str(json)
-            .map_err(|e| RfmtError::PrismError(format!("Failed to parse Prism JSON: {}", e)))?;
+        let node: PrismNode = serde_json::from_str(json).map_err(|e| RfmtError::PrismError {
+            message: format!("Failed to parse Prism JSON: {}", e),
+        })?;
         Ok((node, Vec::new()))
     }
 
@@ -428,8 +429,8 @@ mod tests {
         assert!(result.is_err());
 
         match result {
-            Err(RfmtError::PrismError(msg)) => {
-                assert!(msg.contains("Failed to parse Prism JSON"));
+            Err(RfmtError::PrismError { message }) => {
+                assert!(message.contains("Failed to parse Prism JSON"));
             }
             _ => panic!("Expected PrismError"),
         }
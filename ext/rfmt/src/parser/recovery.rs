@@ -0,0 +1,95 @@
+//! Locating the code a parse error breaks, for error-tolerant formatting
+//! (`parser.error_tolerance`).
+
+use std::ops::Range;
+
+/// Byte ranges of the lines holding top-level statements with parse
+/// errors, in order and merged; empty when `source` parses.
+///
+/// Each range runs from the start of a statement's first line through the
+/// newline ending its last line, so the code between ranges is whole
+/// lines. An error between statements, such as a missing `end` reported at
+/// the end of input, belongs to the statement before it.
+pub fn broken_ranges(source: &str) -> Vec<Range<usize>> {
+    let result = ruby_prism::parse(source.as_bytes());
+    let errors: Vec<usize> = result
+        .errors()
+        .map(|error| error.location().start_offset())
+        .collect();
+    if errors.is_empty() {
+        return Vec::new();
+    }
+
+    let statements: Vec<Range<usize>> = match result.node().as_program_node() {
+        Some(program) => program
+            .statements()
+            .body()
+            .iter()
+            .map(|node| node.location().start_offset()..node.location().end_offset())
+            .collect(),
+        None => Vec::new(),
+    };
+    if statements.is_empty() {
+        return vec![0..source.len()];
+    }
+
+    let mut broken: Vec<Range<usize>> = Vec::new();
+    for error in errors {
+        let index = statements
+            .iter()
+            .rposition(|statement| statement.start <= error)
+            .unwrap_or(0);
+        let statement = &statements[index];
+        let range = line_start(source, statement.start)..line_end(source, statement.end);
+        broken.push(range);
+    }
+
+    broken.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(broken.len());
+    for range in broken {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Offset after the newline ending the line `offset` is on; a statement
+/// ending in a heredoc already ends after its terminator's newline.
+fn line_end(source: &str, offset: usize) -> usize {
+    if offset > 0 && source.as_bytes()[offset - 1] == b'\n' {
+        return offset;
+    }
+    source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_source_has_no_broken_ranges() {
+        assert!(broken_ranges("x = 1\ny = 2\n").is_empty());
+    }
+
+    #[test]
+    fn test_only_the_broken_statement_is_reported() {
+        // `**` with no `**` parameter to forward is an error inside `foo`
+        let source = "x = 1\ndef foo\n  bar(**)\nend\ny = 2\n";
+        assert_eq!(broken_ranges(source), vec![6..28]);
+    }
+
+    #[test]
+    fn test_missing_end_at_end_of_input_belongs_to_the_last_statement() {
+        let source = "x = 1\ndef foo\n  1\n";
+        let ranges = broken_ranges(source);
+        assert_eq!(ranges, vec![6..source.len()]);
+    }
+}
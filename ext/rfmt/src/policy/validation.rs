@@ -11,6 +11,7 @@ pub fn validate_source_size(source: &str, max_size: u64) -> Result<()> {
                 "Source code is too large ({} bytes, max {} bytes)",
                 size, max_size
             ),
+            file: None,
         });
     }
 
@@ -0,0 +1,568 @@
+//! Machine-readable results of formatting files, for CI.
+//!
+//! A [`FileReport`] records what formatting one file came to: unchanged,
+//! changed (with the text a diff is made from), or failed with the error
+//! that stopped it. [`render`] writes a run's reports as JSON lines, a
+//! SARIF 2.1.0 log, or GitHub Actions `::error` workflow commands. Lines
+//! are 1-based and columns 1-based UTF-16 code units, as SARIF counts
+//! them.
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+
+use crate::config::Config;
+use crate::diff::{text_edits, unified_diff};
+use crate::error::{RfmtError, Span};
+use crate::line_index::LineIndex;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/fs0414/rfmt";
+/// SARIF rule of files that formatting changes; failures use their error
+/// code as the rule.
+const NEEDS_FORMATTING_RULE: &str = "needs-formatting";
+/// Error code of files that cannot be read (docs/error_reference.md).
+const IO_ERROR_CODE: &str = "E003";
+
+/// How formatting one file turned out.
+#[derive(Debug)]
+pub enum Outcome {
+    Unchanged,
+    /// Formatting changes the file.
+    Changed {
+        original: String,
+        formatted: String,
+    },
+    /// Formatting failed. Parse errors locate their syntax errors in
+    /// `source`; a validation error (`validation::validate_output`) is a
+    /// bug in rfmt and has no location in it.
+    Failed {
+        source: String,
+        error: RfmtError,
+    },
+    /// The file could not be read or written.
+    IoError(String),
+}
+
+impl Outcome {
+    /// Formats `source` without writing anything. A formatter panic is
+    /// reported as an error for this file instead of taking down the run.
+    pub fn new(source: String, config: &Config) -> Self {
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| crate::format_source(&source, config)))
+                .unwrap_or_else(|_| Err(RfmtError::format("internal error")));
+        match result {
+            Ok(formatted) if formatted == source => Outcome::Unchanged,
+            Ok(formatted) => Outcome::Changed {
+                original: source,
+                formatted,
+            },
+            Err(error) => Outcome::Failed { source, error },
+        }
+    }
+
+    /// Whether the file is formatted already; anything else fails a check.
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, Outcome::Unchanged)
+    }
+}
+
+/// The result for one file.
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub outcome: Outcome,
+}
+
+impl FileReport {
+    /// Reads and formats the file at `path` without writing it.
+    pub fn check(path: &Path, config: &Config) -> Self {
+        let outcome = match std::fs::read_to_string(path) {
+            Ok(source) => Outcome::new(source, config),
+            Err(e) => Outcome::IoError(e.to_string()),
+        };
+        Self {
+            path: path.to_path_buf(),
+            outcome,
+        }
+    }
+
+    fn name(&self) -> String {
+        self.path.to_string_lossy().replace('\\', "/")
+    }
+
+    /// `status` field of the JSON line.
+    fn status(&self) -> &'static str {
+        match &self.outcome {
+            Outcome::Unchanged => "unchanged",
+            Outcome::Changed { .. } => "changed",
+            Outcome::Failed {
+                error: RfmtError::ParseError { .. },
+                ..
+            } => "parse_error",
+            Outcome::Failed {
+                error: RfmtError::ValidationError { .. },
+                ..
+            } => "validation_error",
+            Outcome::Failed { .. } | Outcome::IoError(_) => "error",
+        }
+    }
+
+    /// What to tell the user, with its rule (error code) and location;
+    /// `None` for unchanged files.
+    fn problem(&self) -> Option<Problem> {
+        match &self.outcome {
+            Outcome::Unchanged => None,
+            Outcome::Changed {
+                original,
+                formatted,
+            } => {
+                let start = text_edits(original, formatted)
+                    .first()
+                    .map_or(0, |edit| edit.range.start);
+                Some(Problem {
+                    rule: NEEDS_FORMATTING_RULE,
+                    message: "File needs formatting".to_string(),
+                    region: Some(Region::new(original, start..start)),
+                })
+            }
+            Outcome::Failed { source, error } => {
+                let region = match error {
+                    // Points into the formatted output, not this file
+                    RfmtError::ValidationError { .. } | RfmtError::IdempotencyError { .. } => None,
+                    _ => error.span().map(|span| Region::of_span(source, span)),
+                };
+                Some(Problem {
+                    rule: error.code(),
+                    message: error.to_string(),
+                    region,
+                })
+            }
+            Outcome::IoError(message) => Some(Problem {
+                rule: IO_ERROR_CODE,
+                message: message.clone(),
+                region: None,
+            }),
+        }
+    }
+}
+
+struct Problem {
+    rule: &'static str,
+    message: String,
+    region: Option<Region>,
+}
+
+/// A located stretch of a file: 1-based lines and UTF-16 columns, and the
+/// byte range it covers.
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+    range: std::ops::Range<usize>,
+}
+
+impl Region {
+    fn new(source: &str, range: std::ops::Range<usize>) -> Self {
+        let index = LineIndex::new(source.as_bytes());
+        let clamp = |offset: usize| {
+            let mut offset = offset.min(source.len());
+            while !source.is_char_boundary(offset) {
+                offset -= 1;
+            }
+            offset
+        };
+        let start = index.position(source, clamp(range.start));
+        let end = index.position(source, clamp(range.end));
+        Self {
+            start_line: start.line + 1,
+            start_column: start.character + 1,
+            end_line: end.line + 1,
+            end_column: end.character + 1,
+            range,
+        }
+    }
+
+    fn of_span(source: &str, span: &Span) -> Self {
+        Self::new(source, span.range.clone())
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "line": self.start_line,
+            "column": self.start_column,
+            "end_line": self.end_line,
+            "end_column": self.end_column,
+            "byte_range": [self.range.start, self.range.end],
+        })
+    }
+}
+
+/// Output formats of [`render`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// One JSON object per file, unchanged files included.
+    JsonLines,
+    /// A SARIF 2.1.0 log, for code scanning uploads.
+    Sarif,
+    /// GitHub Actions `::error` workflow commands, which annotate the
+    /// pull request diff.
+    Github,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "json" => Ok(ReportFormat::JsonLines),
+            "sarif" => Ok(ReportFormat::Sarif),
+            "github" => Ok(ReportFormat::Github),
+            _ => Err(format!(
+                "Unknown output format: {} (expected json, sarif or github)",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReportFormat::JsonLines => "json",
+            ReportFormat::Sarif => "sarif",
+            ReportFormat::Github => "github",
+        })
+    }
+}
+
+/// Renders `reports` in `format`, ending with a newline unless empty.
+pub fn render(format: ReportFormat, reports: &[FileReport]) -> String {
+    match format {
+        ReportFormat::JsonLines => json_lines(reports),
+        ReportFormat::Sarif => sarif(reports),
+        ReportFormat::Github => github(reports),
+    }
+}
+
+/// One line per file:
+///
+/// ```text
+/// {"file":"a.rb","status":"changed","code":"needs-formatting","message":"File needs formatting","line":2,"column":1,...,"diff":"--- a/a.rb\n..."}
+/// ```
+///
+/// `status` is `unchanged`, `changed`, `parse_error`, `validation_error`
+/// or `error`. Parse errors also list every syntax error under `errors`.
+pub fn json_lines(reports: &[FileReport]) -> String {
+    let mut output = String::new();
+    for report in reports {
+        let mut line = Map::new();
+        line.insert("file".to_string(), json!(report.name()));
+        line.insert("status".to_string(), json!(report.status()));
+
+        if let Some(problem) = report.problem() {
+            line.insert("code".to_string(), json!(problem.rule));
+            line.insert("message".to_string(), json!(problem.message));
+            if let Some(Value::Object(region)) = problem.region.as_ref().map(Region::to_json) {
+                line.extend(region);
+            }
+        }
+        match &report.outcome {
+            Outcome::Changed {
+                original,
+                formatted,
+            } => {
+                let name = report.name();
+                let diff = unified_diff(
+                    original,
+                    formatted,
+                    &format!("a/{}", name),
+                    &format!("b/{}", name),
+                );
+                line.insert("diff".to_string(), json!(diff));
+            }
+            Outcome::Failed {
+                source,
+                error: RfmtError::ParseError { errors, .. },
+            } => {
+                let errors: Vec<Value> = errors
+                    .iter()
+                    .map(|error| {
+                        let mut entry = Region::of_span(source, &error.span).to_json();
+                        entry["message"] = json!(error.message);
+                        entry["kind"] = json!(error.kind);
+                        entry
+                    })
+                    .collect();
+                line.insert("errors".to_string(), json!(errors));
+            }
+            _ => {}
+        }
+
+        output.push_str(&Value::Object(line).to_string());
+        output.push('\n');
+    }
+    output
+}
+
+/// A SARIF 2.1.0 log with one run and one result per file that is not
+/// formatted already. Each error code used is listed as a rule.
+pub fn sarif(reports: &[FileReport]) -> String {
+    let mut rules: Vec<&'static str> = Vec::new();
+    let mut results = Vec::new();
+    for report in reports {
+        let Some(problem) = report.problem() else {
+            continue;
+        };
+        if !rules.contains(&problem.rule) {
+            rules.push(problem.rule);
+        }
+
+        let mut location = json!({
+            "physicalLocation": {
+                "artifactLocation": { "uri": report.name() },
+            },
+        });
+        if let Some(region) = &problem.region {
+            location["physicalLocation"]["region"] = json!({
+                "startLine": region.start_line,
+                "startColumn": region.start_column,
+                "endLine": region.end_line,
+                "endColumn": region.end_column,
+                "byteOffset": region.range.start,
+                "byteLength": region.range.len(),
+            });
+        }
+        results.push(json!({
+            "ruleId": problem.rule,
+            "level": "error",
+            "message": { "text": problem.message },
+            "locations": [location],
+        }));
+    }
+
+    let rules: Vec<Value> = rules
+        .into_iter()
+        .map(|rule| json!({ "id": rule, "shortDescription": { "text": rule_description(rule) } }))
+        .collect();
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "rfmt",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": INFORMATION_URI,
+                    "rules": rules,
+                },
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }],
+    });
+    let mut output = serde_json::to_string_pretty(&log).unwrap_or_default();
+    output.push('\n');
+    output
+}
+
+fn rule_description(rule: &str) -> &'static str {
+    match rule {
+        NEEDS_FORMATTING_RULE => "File is not formatted",
+        "E001" => "Ruby syntax error",
+        "E002" => "Invalid configuration",
+        IO_ERROR_CODE => "File could not be read",
+        "E006" => "Unsupported input",
+        "E007" => "Prism parser integration error",
+        "E008" => "Formatting error",
+        "E009" => "Formatting is not idempotent",
+        _ => "Internal error",
+    }
+}
+
+/// One `::error` workflow command per file that is not formatted already:
+///
+/// ```text
+/// ::error file=app/a.rb,line=2,col=1,title=rfmt (needs-formatting)::File needs formatting
+/// ```
+pub fn github(reports: &[FileReport]) -> String {
+    let mut output = String::new();
+    for report in reports {
+        let Some(problem) = report.problem() else {
+            continue;
+        };
+        let mut properties = vec![format!("file={}", escape_property(&report.name()))];
+        if let Some(region) = &problem.region {
+            properties.push(format!("line={}", region.start_line));
+            properties.push(format!("col={}", region.start_column));
+            properties.push(format!("endLine={}", region.end_line));
+            properties.push(format!("endColumn={}", region.end_column));
+        }
+        properties.push(format!(
+            "title={}",
+            escape_property(&format!("rfmt ({})", problem.rule))
+        ));
+        output.push_str(&format!(
+            "::error {}::{}\n",
+            properties.join(","),
+            escape_data(&problem.message)
+        ));
+    }
+    output
+}
+
+/// Workflow command message escaping.
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Workflow command property escaping: also `:` and `,`, which delimit
+/// properties.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(path: &str, source: &str) -> FileReport {
+        FileReport {
+            path: PathBuf::from(path),
+            outcome: Outcome::new(source.to_string(), &Config::default()),
+        }
+    }
+
+    fn lines(output: &str) -> Vec<Value> {
+        output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_json_lines_reports_every_file() {
+        let output = json_lines(&[
+            report("ok.rb", "x = 1\n"),
+            report("messy.rb", "x = 1\ny  =  2\n"),
+            report("broken.rb", "x = 1\ndef broken(\n"),
+        ]);
+        let lines = lines(&output);
+
+        assert_eq!(lines[0], json!({ "file": "ok.rb", "status": "unchanged" }));
+
+        assert_eq!(lines[1]["status"], "changed");
+        assert_eq!(lines[1]["code"], NEEDS_FORMATTING_RULE);
+        assert_eq!(lines[1]["line"], 2);
+        assert_eq!(lines[1]["column"], 3);
+        assert!(lines[1]["diff"]
+            .as_str()
+            .unwrap()
+            .starts_with("--- a/messy.rb\n+++ b/messy.rb\n"));
+
+        assert_eq!(lines[2]["status"], "parse_error");
+        assert_eq!(lines[2]["code"], "E001");
+        assert_eq!(lines[2]["line"], 2);
+        assert_eq!(lines[2]["errors"][0]["line"], 2);
+        assert!(lines[2]["errors"][0]["kind"].is_string());
+    }
+
+    #[test]
+    fn test_validation_errors_have_no_location() {
+        let file = FileReport {
+            path: PathBuf::from("a.rb"),
+            outcome: Outcome::Failed {
+                source: "x = 1\n".to_string(),
+                error: crate::validation::validate_output("def broken(\n").unwrap_err(),
+            },
+        };
+        let line = &lines(&json_lines(&[file]))[0];
+
+        assert_eq!(line["status"], "validation_error");
+        assert_eq!(line["code"], "E999");
+        assert!(line.get("line").is_none());
+    }
+
+    #[test]
+    fn test_sarif_lists_results_and_their_rules() {
+        let output = sarif(&[
+            report("ok.rb", "x = 1\n"),
+            report("dir/messy.rb", "y  =  2\n"),
+            FileReport {
+                path: PathBuf::from("missing.rb"),
+                outcome: Outcome::IoError("No such file or directory".to_string()),
+            },
+        ]);
+        let log: Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "rfmt");
+        let rules: Vec<&str> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|rule| rule["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(rules, vec![NEEDS_FORMATTING_RULE, IO_ERROR_CODE]);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "dir/messy.rb");
+        assert_eq!(location["region"]["startLine"], 1);
+        assert_eq!(location["region"]["startColumn"], 3);
+        assert!(results[1]["locations"][0]["physicalLocation"]
+            .get("region")
+            .is_none());
+    }
+
+    #[test]
+    fn test_sarif_describes_idempotency_failures() {
+        let output = sarif(&[FileReport {
+            path: PathBuf::from("a.rb"),
+            outcome: Outcome::Failed {
+                source: "x = 1\n".to_string(),
+                error: RfmtError::IdempotencyError {
+                    diff: "-x = 1\n+x  = 1\n".to_string(),
+                    file: None,
+                    span: None,
+                },
+            },
+        }]);
+        let log: Value = serde_json::from_str(&output).unwrap();
+
+        let rule = &log["runs"][0]["tool"]["driver"]["rules"][0];
+        assert_eq!(rule["id"], "E009");
+        assert_eq!(
+            rule["shortDescription"]["text"],
+            "Formatting is not idempotent"
+        );
+    }
+
+    #[test]
+    fn test_github_annotations_are_escaped() {
+        let output = github(&[
+            report("ok.rb", "x = 1\n"),
+            report("a,b.rb", "x = 1\ndef broken(\n"),
+        ]);
+
+        assert_eq!(output.lines().count(), 1);
+        assert!(output.starts_with("::error file=a%2Cb.rb,line=2,col="));
+        assert!(output.contains(",title=rfmt (E001)::Parse errors:%0A2:"));
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!("sarif".parse(), Ok(ReportFormat::Sarif));
+        assert_eq!(ReportFormat::JsonLines.to_string(), "json");
+        assert!("xml".parse::<ReportFormat>().is_err());
+    }
+}
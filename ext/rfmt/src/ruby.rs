@@ -0,0 +1,198 @@
+//! Ruby bindings: the `Rfmt` module functions the gem calls into.
+//!
+//! Only built with the `ruby` feature (on by default). Rust callers that
+//! have no Ruby toolchain depend on the crate with `default-features =
+//! false` and use `rfmt::format_source` instead.
+
+use magnus::{function, prelude::*, Error, Ruby};
+
+use crate::config::Config;
+use crate::logging;
+use crate::parser::{NativeAdapter, RubyParser};
+use crate::report::{self, FileReport, ReportFormat};
+
+fn format_ruby_code(ruby: &Ruby, source: String) -> Result<String, Error> {
+    format_impl(ruby, source, None, None)
+}
+
+// Separate fixed-arity export instead of a variadic `format_code`: magnus
+// handles fixed signatures natively, so no scan_args parsing to get wrong.
+fn format_ruby_code_with_config(
+    ruby: &Ruby,
+    source: String,
+    config_path: Option<String>,
+    file_path: Option<String>,
+) -> Result<String, Error> {
+    format_impl(ruby, source, config_path, file_path)
+}
+
+/// `file_path` is the file `source` was read from: configuration is
+/// discovered from its directory and the overrides matching it are applied
+fn format_impl(
+    ruby: &Ruby,
+    source: String,
+    config_path: Option<String>,
+    file_path: Option<String>,
+) -> Result<String, Error> {
+    let config = resolve_config(ruby, config_path.as_deref(), file_path.as_deref())?;
+
+    crate::format_source(&source, &config).map_err(|e| e.to_magnus_error(ruby))
+}
+
+/// Formats and maps the byte offset `cursor` into the result, for editors
+/// restoring the caret after replacing the whole document
+fn format_code_with_cursor(
+    ruby: &Ruby,
+    source: String,
+    cursor: usize,
+    config_path: Option<String>,
+) -> Result<(String, usize), Error> {
+    let config = resolve_config(ruby, config_path.as_deref(), None)?;
+
+    crate::format_source_with_cursor(&source, &config, cursor).map_err(|e| e.to_magnus_error(ruby))
+}
+
+/// Formats around top-level statements that do not parse, returning the
+/// output and the [start, end) byte ranges copied verbatim
+fn format_code_tolerant(
+    ruby: &Ruby,
+    source: String,
+    config_path: Option<String>,
+) -> Result<(String, Vec<(usize, usize)>), Error> {
+    let config = resolve_config(ruby, config_path.as_deref(), None)?;
+
+    let (formatted, skipped) =
+        crate::format_source_tolerant(&source, &config).map_err(|e| e.to_magnus_error(ruby))?;
+    let skipped = skipped
+        .into_iter()
+        .map(|range| (range.start, range.end))
+        .collect();
+    Ok((formatted, skipped))
+}
+
+/// Minimal edits turning `original` into `formatted` for LSP responses, as
+/// [start_line, start_character, end_line, end_character, new_text] with
+/// zero-based lines and UTF-16 columns
+fn lsp_text_edits(
+    original: String,
+    formatted: String,
+) -> Vec<(usize, usize, usize, usize, String)> {
+    crate::diff::lsp_text_edits(&original, &formatted)
+        .into_iter()
+        .map(|(range, new_text)| {
+            (
+                range.start.line,
+                range.start.character,
+                range.end.line,
+                range.end.character,
+                new_text,
+            )
+        })
+        .collect()
+}
+
+/// Checks `paths` without writing them and renders the results in
+/// `format` (json, sarif or github), with whether every file is formatted
+fn report_files(
+    ruby: &Ruby,
+    format: String,
+    paths: Vec<String>,
+    config_path: Option<String>,
+) -> Result<(String, bool), Error> {
+    let format: ReportFormat = format
+        .parse()
+        .map_err(|message: String| Error::new(ruby.exception_arg_error(), message))?;
+
+    let reports = paths
+        .iter()
+        .map(|path| {
+            let config = resolve_config(ruby, config_path.as_deref(), Some(path))?;
+            Ok(FileReport::check(std::path::Path::new(path), &config))
+        })
+        .collect::<Result<Vec<FileReport>, Error>>()?;
+    let passed = reports.iter().all(|report| report.outcome.is_unchanged());
+    Ok((report::render(format, &reports), passed))
+}
+
+/// Serialize the effective configuration so Ruby can display exactly what
+/// the formatter will use (CLI `config` command, --config fail-fast check);
+/// with `file_path`, the configuration discovered for that file with the
+/// overrides matching it applied
+fn resolved_config_yaml(
+    ruby: &Ruby,
+    config_path: Option<String>,
+    file_path: Option<String>,
+) -> Result<String, Error> {
+    let config = resolve_config(ruby, config_path.as_deref(), file_path.as_deref())?;
+
+    serde_yaml::to_string(&config)
+        .map_err(|e| Error::new(ruby.exception_standard_error(), e.to_string()))
+}
+
+fn resolve_config(
+    ruby: &Ruby,
+    config_path: Option<&str>,
+    file_path: Option<&str>,
+) -> Result<Config, Error> {
+    Config::resolve(
+        config_path.map(std::path::Path::new),
+        file_path.map(std::path::Path::new),
+    )
+    .map_err(|e| e.to_magnus_error(ruby))
+}
+
+/// `source`, a configuration file, rewritten to the current version, with
+/// the deprecated settings it used and whether its comments were kept
+fn migrate_config_source(
+    ruby: &Ruby,
+    source: String,
+) -> Result<(String, Vec<String>, bool), Error> {
+    let migration =
+        crate::config::migrate::migrate(&source).map_err(|e| e.to_magnus_error(ruby))?;
+    let deprecations = migration
+        .deprecations
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    Ok((migration.source, deprecations, migration.comments_kept))
+}
+
+/// Parse Ruby source code and return the internal AST representation
+/// This is useful for debugging and integration testing
+fn parse_to_json(ruby: &Ruby, source: String) -> Result<String, Error> {
+    let parser = NativeAdapter::new();
+    let ast = parser.parse(&source).map_err(|e| e.to_magnus_error(ruby))?;
+
+    Ok(format!("{:#?}", ast))
+}
+
+fn rust_version() -> String {
+    format!("{} (Rust)", env!("CARGO_PKG_VERSION"))
+}
+
+#[magnus::init]
+fn init(ruby: &Ruby) -> Result<(), Error> {
+    logging::RfmtLogger::init();
+
+    let module = ruby.define_module("Rfmt")?;
+    crate::error::define_exception_classes(ruby, module)?;
+
+    module.define_singleton_method("format_code", function!(format_ruby_code, 1))?;
+    module.define_singleton_method(
+        "format_code_with_config",
+        function!(format_ruby_code_with_config, 3),
+    )?;
+    module.define_singleton_method(
+        "format_code_with_cursor",
+        function!(format_code_with_cursor, 3),
+    )?;
+    module.define_singleton_method("format_code_tolerant", function!(format_code_tolerant, 2))?;
+    module.define_singleton_method("lsp_text_edits", function!(lsp_text_edits, 2))?;
+    module.define_singleton_method("report_files", function!(report_files, 3))?;
+    module.define_singleton_method("parse_to_json", function!(parse_to_json, 1))?;
+    module.define_singleton_method("resolved_config_yaml", function!(resolved_config_yaml, 2))?;
+    module.define_singleton_method("migrate_config_source", function!(migrate_config_source, 1))?;
+    module.define_singleton_method("rust_version", function!(rust_version, 0))?;
+
+    Ok(())
+}
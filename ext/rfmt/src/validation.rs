@@ -1,30 +1,517 @@
 //! Output guard: never return syntactically invalid Ruby to the caller.
 //! Moved here from lib/rfmt.rb's validate_output! at the phase-6 switchover.
+//!
+//! [`verify_comments`] checks that every comment made it into the output
+//! exactly once. With `validation.ast_equivalence`, [`verify_equivalence`]
+//! also checks that formatting did not change what the code means.
 
-use crate::error::{Result, RfmtError};
+use std::collections::HashMap;
+
+use crate::ast::{Node, NodeType};
+use crate::error::{Result, RfmtError, Span};
+use crate::line_index::LineIndex;
+use crate::parser::NativeAdapter;
+use ruby_prism::ParseResult;
+
+/// Metadata the formatter changes on purpose: `parameters_text` is a raw
+/// slice (the parameters are compared as children), `key_kind` follows
+/// `style.hash_syntax`, and `data_start_offset` moves with the code above
+/// `__END__`.
+const FORMATTING_METADATA_KEYS: [&str; 3] = ["parameters_text", "key_kind", "data_start_offset"];
 
 pub fn validate_output(formatted: &str) -> Result<()> {
-    let bytes = formatted.as_bytes();
-    let parse_result = ruby_prism::parse(bytes);
+    check_syntax(formatted, &ruby_prism::parse(formatted.as_bytes()))
+}
 
+/// [`validate_output`] on `parse_result`, prism's parse of `formatted`.
+pub fn check_syntax(formatted: &str, parse_result: &ParseResult<'_>) -> Result<()> {
     let Some(error) = parse_result.errors().next() else {
         return Ok(());
     };
 
-    let line = 1 + bytes[..error.location().start_offset()]
+    let location = error.location();
+    let span = Span::new(
+        &LineIndex::new(formatted.as_bytes()),
+        location.start_offset()..location.end_offset(),
+    );
+    Err(RfmtError::ValidationError {
+        message: format!(
+            "Formatter produced syntactically invalid output (this is a bug in rfmt, not in your code): {} at line {}",
+            error.message(),
+            span.line
+        ),
+        file: None,
+        span: Some(span),
+    })
+}
+
+/// Compares the comments of `ast` (the flat list on the root) with those
+/// of `parse_result`, prism's parse of `formatted`, as multisets of their
+/// text with trailing whitespace removed from each line. The error names
+/// the first comment, by input line, that was lost or duplicated.
+pub fn verify_comments(ast: &Node, formatted: &str, parse_result: &ParseResult<'_>) -> Result<()> {
+    let mut expected: HashMap<String, Vec<usize>> = HashMap::new();
+    for comment in &ast.comments {
+        expected
+            .entry(comment_key(&comment.text))
+            .or_default()
+            .push(comment.location.start_line);
+    }
+
+    let index = LineIndex::new(formatted.as_bytes());
+    let mut printed: HashMap<String, Vec<Span>> = HashMap::new();
+    for comment in parse_result.comments() {
+        let location = comment.location();
+        printed
+            .entry(comment_key(&String::from_utf8_lossy(location.as_slice())))
+            .or_default()
+            .push(Span::new(
+                &index,
+                location.start_offset()..location.end_offset(),
+            ));
+    }
+
+    let mut lost: Vec<(usize, &str)> = Vec::new();
+    for (text, lines) in &expected {
+        let count = printed.get(text).map_or(0, Vec::len);
+        lost.extend(lines.iter().skip(count).map(|&line| (line, text.as_str())));
+    }
+    if let Some((line, text)) = lost.into_iter().min() {
+        return Err(RfmtError::ValidationError {
+            message: format!(
+                "Formatter dropped a comment (this is a bug in rfmt, not in your code): {:?} from line {}",
+                text, line
+            ),
+            file: None,
+            span: None,
+        });
+    }
+
+    let duplicated = printed
         .iter()
-        .filter(|&&b| b == b'\n')
-        .count();
-    Err(RfmtError::ValidationError(format!(
-        "Formatter produced syntactically invalid output (this is a bug in rfmt, not in your code): {} at line {}",
-        error.message(),
-        line
-    )))
+        .flat_map(|(text, spans)| {
+            let count = expected.get(text).map_or(0, Vec::len);
+            spans.iter().skip(count).map(move |span| (span, text))
+        })
+        .min_by_key(|(span, _)| span.range.start);
+    if let Some((span, text)) = duplicated {
+        return Err(RfmtError::ValidationError {
+            message: format!(
+                "Formatter duplicated a comment (this is a bug in rfmt, not in your code): {:?} at line {}",
+                text, span.line
+            ),
+            file: None,
+            span: Some(span.clone()),
+        });
+    }
+
+    Ok(())
+}
+
+/// A comment's text as printed: the formatter strips trailing whitespace,
+/// and an embdoc's slice ends with the newline after `=end`.
+fn comment_key(text: &str) -> String {
+    text.lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses `formatted` and compares its tree with `ast`, the tree of
+/// `source`: node types, children and metadata must match, and leaf nodes
+/// must have the same text. Locations, formatting info and comments are
+/// ignored, and so are the differences formatting is allowed to make to a
+/// leaf: the indentation of a squiggly heredoc's body, the quotes around a
+/// plain string and the form of a symbol hash key (`:key` / `key:`).
+///
+/// `parse_result` is prism's parse of `formatted`. The error names the
+/// path to the first node that differs and points at it in `formatted`.
+pub fn verify_equivalence(
+    source: &str,
+    ast: &Node,
+    formatted: &str,
+    parse_result: &ParseResult<'_>,
+) -> Result<()> {
+    let output = NativeAdapter::new().convert(formatted, parse_result)?;
+    let texts = Texts { source, formatted };
+    let mut path = vec![segment(&output, None)];
+
+    let Some(difference) = texts.compare(ast, &output, false, &mut path) else {
+        return Ok(());
+    };
+
+    let location = &difference.node.location;
+    Err(RfmtError::ValidationError {
+        message: format!(
+            "Formatter changed the structure of the code (this is a bug in rfmt, not in your code): {} at {}",
+            difference.reason,
+            path.join(" > ")
+        ),
+        file: None,
+        span: Some(Span::new(
+            &LineIndex::new(formatted.as_bytes()),
+            location.start_offset..location.end_offset,
+        )),
+    })
+}
+
+/// What differs, and the output node where it does.
+struct Difference<'a> {
+    reason: String,
+    node: &'a Node,
+}
+
+/// The texts both trees were parsed from.
+struct Texts<'a> {
+    source: &'a str,
+    formatted: &'a str,
+}
+
+impl Texts<'_> {
+    /// First difference between `before` and `after` in pre-order; `path`
+    /// is left ending at the node it is found on. `squiggly` says the nodes
+    /// are parts of a `<<~` heredoc's body.
+    fn compare<'n>(
+        &self,
+        before: &Node,
+        after: &'n Node,
+        squiggly: bool,
+        path: &mut Vec<String>,
+    ) -> Option<Difference<'n>> {
+        let differ = |reason: String| {
+            Some(Difference {
+                reason,
+                node: after,
+            })
+        };
+
+        if before.node_type != after.node_type {
+            return differ(format!(
+                "{} became {}",
+                type_name(&before.node_type),
+                type_name(&after.node_type)
+            ));
+        }
+
+        let mut keys: Vec<&String> = before
+            .metadata
+            .keys()
+            .chain(after.metadata.keys())
+            .collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            if FORMATTING_METADATA_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            let (old, new) = (before.metadata.get(key), after.metadata.get(key));
+            if old != new {
+                let show = |value: Option<&String>| {
+                    value.map_or("nothing".to_string(), |value| format!("{:?}", value))
+                };
+                return differ(format!("{} {} became {}", key, show(old), show(new)));
+            }
+        }
+
+        if before.children.is_empty() && after.children.is_empty() {
+            let old = leaf_text(before, self.source, squiggly);
+            let new = leaf_text(after, self.formatted, squiggly);
+            if old != new {
+                return differ(format!("text {:?} became {:?}", old, new));
+            }
+        }
+
+        let squiggly = squiggly
+            || (is_squiggly_heredoc(before, self.source)
+                && is_squiggly_heredoc(after, self.formatted));
+        for (index, (old, new)) in before.children.iter().zip(&after.children).enumerate() {
+            path.push(segment(new, Some(index)));
+            if let Some(difference) = self.compare(old, new, squiggly, path) {
+                return Some(difference);
+            }
+            path.pop();
+        }
+
+        if before.children.len() != after.children.len() {
+            return differ(format!(
+                "{} children became {}",
+                before.children.len(),
+                after.children.len()
+            ));
+        }
+
+        None
+    }
+}
+
+/// `DefNode[1](name)`: the node type, its index among its parent's
+/// children and its name, when it has one.
+fn segment(node: &Node, index: Option<usize>) -> String {
+    let mut segment = type_name(&node.node_type);
+    if let Some(index) = index {
+        segment.push_str(&format!("[{}]", index));
+    }
+    if let Some(name) = node.metadata.get("name") {
+        segment.push_str(&format!("({})", name));
+    }
+    segment
+}
+
+fn type_name(node_type: &NodeType) -> String {
+    match node_type {
+        NodeType::Unknown(name) => name.clone(),
+        known => format!("{:?}", known),
+    }
+}
+
+/// A leaf's source text, normalized so that the rewrites the formatter
+/// makes compare equal. `squiggly` says the leaf is part of a `<<~`
+/// heredoc's body, whose indentation the formatter may change.
+fn leaf_text(node: &Node, text: &str, squiggly: bool) -> String {
+    let start = node.location.start_offset;
+    let slice = text
+        .get(start..node.location.end_offset)
+        .unwrap_or_default();
+    let is_string = matches!(node.node_type, NodeType::StringNode | NodeType::XStringNode);
+    if let Some((opener, body)) = heredoc(slice).filter(|_| is_string) {
+        let body = if opener.starts_with("<<~") {
+            strip_indentation(body, true)
+        } else {
+            body.to_string()
+        };
+        return format!("{}\n{}", opener, body);
+    }
+    if squiggly {
+        let at_line_start = text
+            .get(..start)
+            .is_some_and(|before| before.is_empty() || before.ends_with('\n'));
+        return strip_indentation(slice, at_line_start);
+    }
+
+    let slice = match node.node_type {
+        NodeType::StringNode => unquote(slice),
+        NodeType::SymbolNode if !slice.starts_with("::") => unquote(
+            slice
+                .strip_prefix(':')
+                .unwrap_or(slice)
+                .trim_end_matches(':'),
+        ),
+        _ => slice,
+    };
+    slice.to_string()
+}
+
+/// Whether `node` is an interpolated `<<~` heredoc, whose string parts are
+/// its body.
+fn is_squiggly_heredoc(node: &Node, text: &str) -> bool {
+    matches!(
+        node.node_type,
+        NodeType::InterpolatedStringNode | NodeType::InterpolatedXStringNode
+    ) && text
+        .get(node.location.start_offset..node.location.end_offset)
+        .is_some_and(|slice| slice.starts_with("<<~"))
+}
+
+/// Splits a heredoc's source into its opener (`<<~SQL`) and its body. The
+/// rest of the opener's line belongs to other nodes and is left out.
+fn heredoc(slice: &str) -> Option<(&str, &str)> {
+    let rest = slice.strip_prefix("<<")?.trim_start_matches(['~', '-']);
+    let name_len = match rest.chars().next()? {
+        quote @ ('\'' | '"' | '`') => rest[1..].find(quote)? + 2,
+        _ => rest
+            .find(|c: char| !(c == '_' || c.is_alphanumeric()))
+            .unwrap_or(rest.len()),
+    };
+    let opener = &slice[..slice.len() - rest.len() + name_len];
+    let body = slice.find('\n').map_or("", |newline| &slice[newline + 1..]);
+    Some((opener, body))
+}
+
+/// The content of a plain `'…'` / `"…"` literal without backslashes, the
+/// only kind StringRule requotes; anything else is returned as written.
+fn unquote(literal: &str) -> &str {
+    let Some(quote) = literal.chars().next().filter(|&c| matches!(c, '"' | '\'')) else {
+        return literal;
+    };
+    match literal
+        .strip_prefix(quote)
+        .and_then(|rest| rest.strip_suffix(quote))
+    {
+        Some(content) if !content.contains('\\') => content,
+        _ => literal,
+    }
+}
+
+/// `text` with the spaces and tabs that start each of its lines removed;
+/// `at_line_start` says whether `text` itself starts a line.
+fn strip_indentation(text: &str, at_line_start: bool) -> String {
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            if i > 0 || at_line_start {
+                line.trim_start_matches([' ', '\t'])
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::RubyParser;
+
+    fn verify(source: &str, formatted: &str) -> Result<()> {
+        let ast = NativeAdapter::new().parse(source)?;
+        verify_equivalence(
+            source,
+            &ast,
+            formatted,
+            &ruby_prism::parse(formatted.as_bytes()),
+        )
+    }
+
+    fn divergence(source: &str, formatted: &str) -> (String, Span) {
+        match verify(source, formatted).unwrap_err() {
+            RfmtError::ValidationError {
+                message,
+                span: Some(span),
+                ..
+            } => (message, span),
+            other => panic!("expected ValidationError, got {other:?}"),
+        }
+    }
+
+    fn check_comments(source: &str, formatted: &str) -> Result<()> {
+        verify_comments(
+            &NativeAdapter::new().parse(source)?,
+            formatted,
+            &ruby_prism::parse(formatted.as_bytes()),
+        )
+    }
+
+    #[test]
+    fn comments_may_move_and_lose_trailing_whitespace() {
+        let source = "# top   \nx = 1 # one\n=begin\nblock\n=end\n";
+        let formatted = "# top\nx = 1\n# one\n=begin\nblock\n=end\n";
+        assert!(check_comments(source, formatted).is_ok());
+    }
+
+    #[test]
+    fn reports_a_lost_comment_by_its_input_line() {
+        let source = "x = 1 # same\ny = 2 # same\nz = 3 # gone\n";
+        let err = check_comments(source, "x = 1 # same\ny = 2\nz = 3 # gone\n").unwrap_err();
+
+        match err {
+            RfmtError::ValidationError { message, span, .. } => {
+                assert!(
+                    message.ends_with("dropped a comment (this is a bug in rfmt, not in your code): \"# same\" from line 2"),
+                    "unexpected message: {message}"
+                );
+                assert_eq!(span, None);
+            }
+            other => panic!("expected ValidationError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reports_a_duplicated_comment_where_it_was_printed() {
+        let source = "foo do\n  # note\nend\n";
+        let err = check_comments(source, "foo do\n  # note\nend\n# note\n").unwrap_err();
+
+        match err {
+            RfmtError::ValidationError { message, span, .. } => {
+                assert!(
+                    message.ends_with("\"# note\" at line 4"),
+                    "unexpected message: {message}"
+                );
+                assert_eq!(span.map(|span| span.range), Some(20..26));
+            }
+            other => panic!("expected ValidationError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn equivalence_ignores_layout_quotes_and_hash_syntax() {
+        assert!(verify(
+            "def foo(a,b)\n  bar( 'x', :k => 1 )\nend\n",
+            "def foo(a, b)\n  bar(\n    \"x\",\n    k: 1,\n  )\nend\n"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn equivalence_ignores_heredoc_indentation() {
+        assert!(verify(
+            "x = <<~TEXT\n      one\n        two\n    TEXT\n",
+            "x = <<~TEXT\n  one\n    two\nTEXT\n"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn equivalence_ignores_interpolated_heredoc_indentation() {
+        assert!(verify(
+            "x = <<~TEXT\n      one #{a} b\n        two\n    TEXT\n",
+            "x = <<~TEXT\n  one #{a} b\n    two\nTEXT\n"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn equivalence_rejects_reordered_statements() {
+        let (message, span) = divergence("def foo\n  a\n  b\nend\n", "def foo\n  b\n  a\nend\n");
+
+        assert!(
+            message.starts_with("Formatter changed the structure of the code"),
+            "unexpected message: {message}"
+        );
+        assert!(
+            message.ends_with(
+                "name \"a\" became \"b\" at ProgramNode > DefNode[0](foo) > StatementsNode[0] > CallNode[0](b)"
+            ),
+            "unexpected message: {message}"
+        );
+        assert_eq!(span.line, 2);
+    }
+
+    #[test]
+    fn equivalence_rejects_dropped_code() {
+        let (message, _) = divergence("a\nb\n", "a\n");
+
+        assert!(
+            message.ends_with("2 children became 1 at ProgramNode"),
+            "unexpected message: {message}"
+        );
+    }
+
+    #[test]
+    fn equivalence_rejects_changed_literals() {
+        let (message, _) = divergence("x = 'it\\'s'\n", "x = \"it's\"\n");
+
+        assert!(
+            message.contains("text \"'it\\\\'s'\" became \"it's\""),
+            "unexpected message: {message}"
+        );
+    }
+
+    #[test]
+    fn equivalence_rejects_changed_whitespace_in_literals() {
+        let (message, _) = divergence("x = \"a b\"\n", "x = \"ab\"\n");
+
+        assert!(
+            message.contains("text \"a b\" became \"ab\""),
+            "unexpected message: {message}"
+        );
+
+        let (message, _) = divergence("x = <<-TEXT\n  one\n  TEXT\n", "x = <<-TEXT\none\n  TEXT\n");
+        assert!(
+            message.contains("text \"<<-TEXT\\n  one\\n  TEXT\\n\""),
+            "unexpected message: {message}"
+        );
+    }
 
     #[test]
     fn accepts_valid_ruby() {
@@ -36,7 +523,7 @@ mod tests {
         let err = validate_output("def broken(\n").unwrap_err();
 
         match err {
-            RfmtError::ValidationError(message) => {
+            RfmtError::ValidationError { message, .. } => {
                 assert!(
                     message.starts_with(
                         "Formatter produced syntactically invalid output (this is a bug in rfmt, not in your code): "
@@ -57,11 +544,12 @@ mod tests {
         let err = validate_output("x = 1\ny = 2\ndef broken(\n").unwrap_err();
 
         match err {
-            RfmtError::ValidationError(message) => {
+            RfmtError::ValidationError { message, span, .. } => {
                 assert!(
                     message.contains(" at line 3"),
                     "unexpected message: {message}"
                 );
+                assert_eq!(span.map(|span| span.line), Some(3));
             }
             other => panic!("expected ValidationError, got {other:?}"),
         }
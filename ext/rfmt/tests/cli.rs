@@ -0,0 +1,257 @@
+//! End-to-end runs of the native `rfmt` binary: exit codes, in-place
+//! writes, path filtering, stdin mode and machine-readable reports.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+const UNFORMATTED: &str = "class Foo\ndef bar\n1\nend\nend\n";
+const FORMATTED: &str = "class Foo\n  def bar\n    1\n  end\nend\n";
+
+fn rfmt(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rfmt"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("run rfmt")
+}
+
+fn write(dir: &Path, file: &str, contents: &str) {
+    let path = dir.join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn check_fails_until_files_are_written() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "lib/foo.rb", UNFORMATTED);
+    write(dir.path(), "lib/ok.rb", FORMATTED);
+
+    let check = rfmt(dir.path(), &["--check"]);
+    assert_eq!(check.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&check.stdout);
+    assert!(stdout.contains("lib/foo.rb needs formatting"), "{stdout}");
+    assert!(!stdout.contains("lib/ok.rb"), "{stdout}");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib/foo.rb")).unwrap(),
+        UNFORMATTED
+    );
+
+    let format = rfmt(dir.path(), &[]);
+    assert_eq!(format.status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib/foo.rb")).unwrap(),
+        FORMATTED
+    );
+
+    assert_eq!(rfmt(dir.path(), &["--check"]).status.code(), Some(0));
+}
+
+#[test]
+fn diff_prints_changes_without_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "foo.rb", "x  =  1\n");
+
+    let output = rfmt(dir.path(), &["--diff", "foo.rb"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("--- a/foo.rb\n+++ b/foo.rb\n@@ -1 +1 @@\n-x  =  1\n+x = 1\n"),
+        "{stdout}"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("foo.rb")).unwrap(),
+        "x  =  1\n"
+    );
+}
+
+#[test]
+fn directories_honor_exclude_patterns() {
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path(),
+        ".rfmt.yml",
+        "version: \"1.0\"\ninclude:\n  - \"**/*.rb\"\nexclude:\n  - \"generated/**/*\"\n",
+    );
+    write(dir.path(), "app/foo.rb", UNFORMATTED);
+    write(dir.path(), "generated/schema.rb", UNFORMATTED);
+
+    let output = rfmt(dir.path(), &["--check", "."]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("app/foo.rb needs formatting"), "{stdout}");
+    assert!(!stdout.contains("generated"), "{stdout}");
+}
+
+#[test]
+fn overrides_apply_to_matching_files() {
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path(),
+        ".rfmt.yml",
+        "overrides:\n  - files: [\"db/migrate/**\"]\n    formatting:\n      quote_style: single\n",
+    );
+    write(dir.path(), "app/foo.rb", "x = 'a'\n");
+    write(dir.path(), "db/migrate/001_init.rb", "x = \"a\"\n");
+
+    assert_eq!(rfmt(dir.path(), &[]).status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("app/foo.rb")).unwrap(),
+        "x = \"a\"\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("db/migrate/001_init.rb")).unwrap(),
+        "x = 'a'\n"
+    );
+}
+
+#[test]
+fn nested_configuration_applies_to_its_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path(),
+        ".rfmt.yml",
+        "formatting:\n  quote_style: single\n",
+    );
+    write(
+        dir.path(),
+        "engines/billing/.rfmt.yml",
+        "root: true\nformatting:\n  quote_style: double\n",
+    );
+    write(dir.path(), "app/foo.rb", "x = \"a\"\n");
+    write(dir.path(), "engines/billing/lib/invoice.rb", "x = 'a'\n");
+
+    assert_eq!(rfmt(dir.path(), &[]).status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("app/foo.rb")).unwrap(),
+        "x = 'a'\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("engines/billing/lib/invoice.rb")).unwrap(),
+        "x = \"a\"\n"
+    );
+}
+
+#[test]
+fn errors_are_reported_per_file() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "broken.rb", "def broken(\n");
+    write(dir.path(), "foo.rb", UNFORMATTED);
+
+    let output = rfmt(dir.path(), &[]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Error in broken.rb: "), "{stderr}");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("foo.rb")).unwrap(),
+        FORMATTED
+    );
+}
+
+#[test]
+fn output_format_reports_each_file_without_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "broken.rb", "x = 1\ndef broken(\n");
+    write(dir.path(), "foo.rb", UNFORMATTED);
+    write(dir.path(), "ok.rb", FORMATTED);
+
+    let output = rfmt(dir.path(), &["--output-format", "json", "."]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect(line))
+        .collect();
+    let statuses: Vec<(&str, &str)> = lines
+        .iter()
+        .map(|line| {
+            (
+                line["file"].as_str().unwrap(),
+                line["status"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("broken.rb", "parse_error"),
+            ("foo.rb", "changed"),
+            ("ok.rb", "unchanged")
+        ]
+    );
+    assert_eq!(lines[0]["line"], 2);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("foo.rb")).unwrap(),
+        UNFORMATTED
+    );
+
+    let output = rfmt(dir.path(), &["--output-format=github", "foo.rb"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "::error file=foo.rb,line=2,col=1,endLine=2,endColumn=1,title=rfmt (needs-formatting)::File needs formatting\n"
+    );
+    assert_eq!(
+        rfmt(dir.path(), &["--output-format", "sarif", "ok.rb"])
+            .status
+            .code(),
+        Some(0)
+    );
+}
+
+#[test]
+fn stdin_is_formatted_to_stdout() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rfmt"))
+        .arg("--stdin")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("run rfmt");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(UNFORMATTED.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), FORMATTED);
+}
+
+#[test]
+fn missing_config_file_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = rfmt(dir.path(), &["--config", "missing.yml"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Configuration file not found: missing.yml"));
+}
+
+#[test]
+fn migrate_config_rewrites_old_files() {
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path(),
+        ".rfmt.yml",
+        "version: \"1.0\"\n# house style\nformatting:\n  style:\n    quotes: single\n",
+    );
+
+    let check = rfmt(dir.path(), &["migrate-config", "--check"]);
+    assert_eq!(check.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&check.stdout);
+    assert!(
+        stdout.contains("formatting.style.quotes is deprecated, use formatting.quote_style"),
+        "{stdout}"
+    );
+
+    assert_eq!(rfmt(dir.path(), &["migrate-config"]).status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(dir.path().join(".rfmt.yml")).unwrap(),
+        "version: \"2\"\n# house style\nformatting:\n  quote_style: single\n"
+    );
+    let again = rfmt(dir.path(), &["migrate-config", "--check"]);
+    assert_eq!(again.status.code(), Some(0));
+}
@@ -0,0 +1,75 @@
+//! `Formatter::format_range`: only the statements covering the range are
+//! rewritten, at their body's indentation.
+
+use rfmt::config::Config;
+use rfmt::diff::TextEdit;
+use rfmt::format::Formatter;
+use rfmt::parser::{NativeAdapter, RubyParser};
+
+fn edit(source: &str, start: usize, end: usize) -> Option<TextEdit> {
+    let ast = NativeAdapter::new().parse(source).unwrap();
+    Formatter::new(Config::default())
+        .format_range(source, &ast, start, end)
+        .unwrap()
+}
+
+/// Formats the line containing `needle`.
+fn format_line_of(source: &str, needle: &str) -> String {
+    let start = source.find(needle).unwrap();
+    let edit = edit(source, start, start + needle.len()).expect("an edit");
+    edit.apply(source)
+}
+
+#[test]
+fn formats_only_the_selected_statement() {
+    let source = "x  =  1\ny  =  2\nz  =  3\n";
+    let edit = edit(source, 9, 12).expect("an edit");
+    assert_eq!(edit.range, 8..15);
+    assert_eq!(edit.new_text, "y = 2");
+    assert_eq!(edit.apply(source), "x  =  1\ny = 2\nz  =  3\n");
+}
+
+#[test]
+fn reindents_nested_statements_to_their_body() {
+    let source = "class Foo\n  def bar\n        a  =  1\n        b = 2\n  end\nend\n";
+    assert_eq!(
+        format_line_of(source, "a  =  1"),
+        "class Foo\n  def bar\n    a = 1\n        b = 2\n  end\nend\n"
+    );
+}
+
+#[test]
+fn keeps_comments_outside_the_selection() {
+    let source = "# note\n\n  x  =  1 # one\ny  =  2\n";
+    assert_eq!(
+        format_line_of(source, "x  =  1"),
+        "# note\n\nx = 1 # one\ny  =  2\n"
+    );
+}
+
+#[test]
+fn widens_to_statements_sharing_a_line() {
+    let source = "x = 1\nfoo  =  1; bar  =  2\n";
+    let start = source.find("bar").unwrap();
+    let edit = edit(source, start, start + 3).expect("an edit");
+    assert_eq!(edit.range, 6..source.len() - 1);
+    assert_eq!(edit.apply(source), "x = 1\nfoo = 1\nbar = 2\n");
+}
+
+#[test]
+fn returns_none_without_changes() {
+    let source = "x = 1\n\n\ny  =  2\n";
+    // Cursor on an already formatted statement
+    assert_eq!(edit(source, 0, 0), None);
+    // Cursor on a blank line
+    assert_eq!(edit(source, 6, 6), None);
+}
+
+#[test]
+fn rejects_invalid_ranges() {
+    let source = "x = 1\n";
+    let ast = NativeAdapter::new().parse(source).unwrap();
+    let formatter = Formatter::new(Config::default());
+    assert!(formatter.format_range(source, &ast, 4, 2).is_err());
+    assert!(formatter.format_range(source, &ast, 0, 99).is_err());
+}
@@ -0,0 +1,102 @@
+//! The library entry point Rust tools use in place of the Ruby bindings.
+//! Runs under `cargo test --no-default-features` as well.
+
+use rfmt::config::Config;
+use rfmt::error::RfmtError;
+
+#[test]
+fn formats_with_the_given_config() {
+    let source = "class Foo\ndef bar\n1\nend\nend\n";
+
+    let formatted = rfmt::format_source(source, &Config::default()).unwrap();
+    assert_eq!(formatted, "class Foo\n  def bar\n    1\n  end\nend\n");
+
+    let mut config = Config::default();
+    config.formatting.indent_width = 4;
+    let formatted = rfmt::format_source(source, &config).unwrap();
+    assert_eq!(
+        formatted,
+        "class Foo\n    def bar\n        1\n    end\nend\n"
+    );
+}
+
+#[test]
+fn reports_parse_errors() {
+    let err = rfmt::format_source("def broken(", &Config::default()).unwrap_err();
+    assert!(matches!(err, RfmtError::ParseError { .. }), "{err:?}");
+}
+
+#[test]
+fn carries_the_cursor_into_the_result() {
+    let source = "# doc\ndef  foo\n1\nend\n";
+    let config = Config::default();
+
+    for token in ["foo", "1", "end"] {
+        let cursor = source.find(token).unwrap();
+        let (formatted, new_cursor) =
+            rfmt::format_source_with_cursor(source, &config, cursor).unwrap();
+        assert_eq!(formatted, "# doc\ndef foo\n  1\nend\n");
+        assert!(
+            formatted[new_cursor..].starts_with(token),
+            "{token}: {:?}",
+            &formatted[new_cursor..]
+        );
+    }
+}
+
+#[test]
+fn tolerant_formatting_copies_broken_statements() {
+    // `**` with no `**` parameter to forward is an error inside `foo`
+    let source = "x  =  1\ndef foo\n  bar(**)\nend\ny  =  2\n";
+
+    let (formatted, skipped) = rfmt::format_source_tolerant(source, &Config::default()).unwrap();
+    assert_eq!(formatted, "x = 1\ndef foo\n  bar(**)\nend\ny = 2\n");
+    assert_eq!(skipped, vec![8..30]);
+}
+
+#[test]
+fn tolerant_formatting_keeps_blank_lines_around_broken_code() {
+    let source = "x  =  1\n\n\ndef foo(a\n";
+
+    let (formatted, skipped) = rfmt::format_source_tolerant(source, &Config::default()).unwrap();
+    assert_eq!(formatted, "x = 1\n\ndef foo(a\n");
+    assert_eq!(skipped, vec![10..20]);
+
+    let (formatted, skipped) =
+        rfmt::format_source_tolerant("x  =  1\n", &Config::default()).unwrap();
+    assert_eq!(formatted, "x = 1\n");
+    assert!(skipped.is_empty());
+}
+
+#[test]
+fn error_tolerance_setting_applies_to_format_source() {
+    let mut config = Config::default();
+    config.parser.error_tolerance = true;
+
+    let formatted = rfmt::format_source("x  =  1\ndef foo(a\n", &config).unwrap();
+    assert_eq!(formatted, "x = 1\ndef foo(a\n");
+}
+
+#[test]
+fn ast_equivalence_setting_accepts_formatted_output() {
+    let mut config = Config::default();
+    config.validation.ast_equivalence = true;
+
+    let source = "class Foo\ndef bar( a,b )\nbaz :k => 'v'\nend\nend\n";
+    let formatted = rfmt::format_source(source, &config).unwrap();
+    assert_eq!(
+        formatted,
+        rfmt::format_source(source, &Config::default()).unwrap()
+    );
+}
+
+#[test]
+fn directives_keep_code_as_written() {
+    let source = "x  =  1\n# rfmt:disable\nTABLE = {\n  a:   1,\n  bb:  2,\n}\n# rfmt:enable\ny  =  2\n# rfmt:skip\nz  =  [1,2]\nw  =  3\n";
+
+    let formatted = rfmt::format_source(source, &Config::default()).unwrap();
+    assert_eq!(
+        formatted,
+        "x = 1\n# rfmt:disable\nTABLE = {\n  a:   1,\n  bb:  2,\n}\n# rfmt:enable\ny = 2\n# rfmt:skip\nz  =  [1,2]\nw = 3\n"
+    );
+}
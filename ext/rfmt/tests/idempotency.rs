@@ -0,0 +1,58 @@
+//! Formatting is idempotent: every Ruby fixture under tests/fixtures and
+//! spec/fixtures/corpus formats to output that a second pass leaves alone
+//! (`Formatter::with_idempotency_check`).
+
+use rfmt::config::Config;
+use rfmt::format::Formatter;
+use rfmt::parser::{NativeAdapter, RubyParser};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn ruby_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("{}: {}", dir.display(), err))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            ruby_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rb") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn every_fixture_formats_idempotently() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut files = Vec::new();
+    ruby_files(&root.join("tests/fixtures"), &mut files);
+    ruby_files(&root.join("../../spec/fixtures/corpus"), &mut files);
+    assert!(!files.is_empty(), "no fixtures found");
+
+    let formatter = Formatter::new(Config::default()).with_idempotency_check(true);
+    let failures: Vec<String> = files
+        .iter()
+        .filter_map(|path| {
+            let source = fs::read_to_string(path).unwrap();
+            let ast = NativeAdapter::new().parse(&source).unwrap();
+            let error = formatter.format(&source, &ast).err()?;
+            Some(format!("{}: {}", path.display(), error))
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn check_passes_formatted_output_through() {
+    let formatter = Formatter::new(Config::default()).with_idempotency_check(true);
+    let source = "class Foo\ndef bar\n1\nend\nend\n";
+    let ast = NativeAdapter::new().parse(source).unwrap();
+
+    assert_eq!(
+        formatter.format(source, &ast).unwrap(),
+        "class Foo\n  def bar\n    1\n  end\nend\n"
+    );
+}
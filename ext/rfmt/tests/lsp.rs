@@ -0,0 +1,443 @@
+//! The language server, driven in process by a minimal client.
+
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use rfmt::line_index::{LineIndex, Position};
+use rfmt::lsp::{path_to_file_uri, read_message, run, write_message, Server};
+use serde_json::{json, Value};
+
+/// Sends messages straight to a [`Server`], as an editor would over stdio.
+struct Client {
+    server: Server,
+    next_id: u64,
+}
+
+impl Client {
+    fn new() -> Self {
+        Self::with_params(json!({}))
+    }
+
+    fn with_params(params: Value) -> Self {
+        let mut client = Self {
+            server: Server::new(),
+            next_id: 0,
+        };
+        client.request("initialize", params);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    /// Sends a request and returns its response.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let mut replies = self.server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id,
+            "method": method,
+            "params": params,
+        }));
+        assert_eq!(replies.len(), 1, "one response to {method}: {replies:?}");
+        let response = replies.remove(0);
+        assert_eq!(response["id"], self.next_id);
+        response
+    }
+
+    /// Sends a notification and returns what the server sent back.
+    fn notify(&mut self, method: &str, params: Value) -> Vec<Value> {
+        self.server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        }))
+    }
+
+    fn open(&mut self, uri: &str, text: &str) -> Vec<Value> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "ruby", "version": 1, "text": text } }),
+        )
+    }
+
+    fn format(&mut self, uri: &str) -> Value {
+        let response = self.request(
+            "textDocument/formatting",
+            json!({ "textDocument": { "uri": uri }, "options": { "tabSize": 2, "insertSpaces": true } }),
+        );
+        response["result"].clone()
+    }
+}
+
+fn position(line: usize, character: usize) -> Value {
+    json!({ "line": line, "character": character })
+}
+
+fn insertion(line: usize, text: &str) -> Value {
+    json!({ "range": { "start": position(line, 0), "end": position(line, 0) }, "newText": text })
+}
+
+/// Applies LSP text edits to `source`, last edit first.
+fn apply(source: &str, edits: &Value) -> String {
+    let index = LineIndex::new(source.as_bytes());
+    let offset = |value: &Value| {
+        let position = Position {
+            line: value["line"].as_u64().unwrap() as usize,
+            character: value["character"].as_u64().unwrap() as usize,
+        };
+        index.offset(source, position)
+    };
+
+    let mut edits: Vec<_> = edits
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|edit| {
+            let range = offset(&edit["range"]["start"])..offset(&edit["range"]["end"]);
+            (range, edit["newText"].as_str().unwrap().to_string())
+        })
+        .collect();
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+
+    let mut result = source.to_string();
+    for (range, new_text) in edits {
+        result.replace_range(range, &new_text);
+    }
+    result
+}
+
+fn diagnostics_of(messages: &[Value]) -> &Value {
+    let publish = messages
+        .iter()
+        .find(|m| m["method"] == "textDocument/publishDiagnostics")
+        .expect("diagnostics published");
+    &publish["params"]["diagnostics"]
+}
+
+const URI: &str = "file:///nonexistent/rfmt-lsp-test.rb";
+
+#[test]
+fn initialize_advertises_formatting_capabilities() {
+    let mut client = Client {
+        server: Server::new(),
+        next_id: 0,
+    };
+    let result = client.request("initialize", json!({}))["result"].clone();
+
+    let capabilities = &result["capabilities"];
+    assert_eq!(capabilities["documentFormattingProvider"], true);
+    assert_eq!(capabilities["documentRangeFormattingProvider"], true);
+    assert_eq!(
+        capabilities["documentOnTypeFormattingProvider"]["firstTriggerCharacter"],
+        "d"
+    );
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(result["serverInfo"]["name"], "rfmt");
+}
+
+#[test]
+fn formats_a_document_with_minimal_edits() {
+    let mut client = Client::new();
+    client.open(URI, "class Foo\ndef bar\n42\nend\nend\n");
+
+    assert_eq!(
+        client.format(URI),
+        json!([insertion(1, "  "), insertion(2, "    "), insertion(3, "  ")])
+    );
+}
+
+#[test]
+fn formatted_document_needs_no_edits() {
+    let mut client = Client::new();
+    client.open(URI, "x = 1\n");
+
+    assert_eq!(client.format(URI), json!([]));
+}
+
+#[test]
+fn formats_only_the_requested_range() {
+    let source = "x  =  1\ny  =  2\nz  =  3\n";
+    let mut client = Client::new();
+    client.open(URI, source);
+
+    let response = client.request(
+        "textDocument/rangeFormatting",
+        json!({
+            "textDocument": { "uri": URI },
+            "range": { "start": position(1, 0), "end": position(1, 7) },
+            "options": { "tabSize": 2, "insertSpaces": true },
+        }),
+    );
+    assert_eq!(
+        apply(source, &response["result"]),
+        "x  =  1\ny = 2\nz  =  3\n"
+    );
+}
+
+#[test]
+fn formats_the_construct_closed_by_a_typed_end() {
+    let source = "class Foo\n  def bar\n        1\n      end\n\n  x  =  2\nend\n";
+    let mut client = Client::new();
+    client.open(URI, source);
+
+    let response = client.request(
+        "textDocument/onTypeFormatting",
+        json!({
+            "textDocument": { "uri": URI },
+            "position": position(3, 9),
+            "ch": "d",
+            "options": { "tabSize": 2, "insertSpaces": true },
+        }),
+    );
+    assert_eq!(
+        apply(source, &response["result"]),
+        "class Foo\n  def bar\n    1\n  end\n\n  x  =  2\nend\n"
+    );
+}
+
+#[test]
+fn on_type_formatting_ignores_words_ending_in_end() {
+    let source = "list.append\n";
+    let mut client = Client::new();
+    client.open(URI, source);
+
+    let response = client.request(
+        "textDocument/onTypeFormatting",
+        json!({
+            "textDocument": { "uri": URI },
+            "position": position(0, 11),
+            "ch": "d",
+            "options": { "tabSize": 2, "insertSpaces": true },
+        }),
+    );
+    assert_eq!(response["result"], json!([]));
+}
+
+#[test]
+fn publishes_parse_errors_as_diagnostics() {
+    let mut client = Client::new();
+
+    let published = client.open(URI, "x = 1\ndef broken(\n");
+    let diagnostics = diagnostics_of(&published).as_array().unwrap();
+    assert!(!diagnostics.is_empty());
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["source"], "rfmt");
+    assert!(diagnostics[0]["range"]["start"]["line"].as_u64().unwrap() >= 1);
+    // Unparsable code is left alone
+    assert_eq!(client.format(URI), json!([]));
+
+    let published = client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": "x = 1\n" }],
+        }),
+    );
+    assert_eq!(diagnostics_of(&published), &json!([]));
+
+    let published = client.notify(
+        "textDocument/didClose",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert_eq!(diagnostics_of(&published), &json!([]));
+}
+
+#[test]
+fn skips_changes_to_an_already_checked_version() {
+    let mut client = Client::new();
+    client.open(URI, "x = 1\n");
+
+    let change = |version: u64, text: &str| {
+        json!({
+            "textDocument": { "uri": URI, "version": version },
+            "contentChanges": [{ "text": text }],
+        })
+    };
+    let published = client.notify("textDocument/didChange", change(2, "def broken(\n"));
+    assert!(!diagnostics_of(&published).as_array().unwrap().is_empty());
+    assert_eq!(
+        client.notify("textDocument/didChange", change(2, "def broken(\n")),
+        Vec::<Value>::new()
+    );
+    let published = client.notify("textDocument/didChange", change(3, "x = 1\n"));
+    assert_eq!(diagnostics_of(&published), &json!([]));
+}
+
+#[test]
+fn publishes_config_errors_as_diagnostics() {
+    let mut client = Client::with_params(json!({
+        "initializationOptions": { "configPath": "/nonexistent/.rfmt.yml" },
+    }));
+
+    let published = client.open(URI, "x = 1\n");
+    let diagnostics = diagnostics_of(&published);
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert!(diagnostics[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Failed to read config file"));
+}
+
+fn write_config(dir: &Path, indent_width: usize) {
+    let yaml = format!("version: \"1.0\"\nformatting:\n  indent_width: {indent_width}\n");
+    fs::write(dir.join(".rfmt.yml"), yaml).unwrap();
+}
+
+#[test]
+fn reloads_configuration_when_rfmt_yml_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    write_config(dir.path(), 4);
+    let uri = path_to_file_uri(&dir.path().join("a.rb"));
+    let source = "def a\n1\nend\n";
+
+    let mut client = Client::with_params(json!({ "rootUri": path_to_file_uri(dir.path()) }));
+    client.open(&uri, source);
+    assert_eq!(apply(source, &client.format(&uri)), "def a\n    1\nend\n");
+
+    write_config(dir.path(), 2);
+    let published = client.notify(
+        "workspace/didChangeWatchedFiles",
+        json!({ "changes": [{ "uri": path_to_file_uri(&dir.path().join(".rfmt.yml")), "type": 2 }] }),
+    );
+    // Open documents are checked again against the new configuration
+    assert_eq!(diagnostics_of(&published), &json!([]));
+    assert_eq!(apply(source, &client.format(&uri)), "def a\n  1\nend\n");
+}
+
+#[test]
+fn registers_a_config_file_watcher_when_the_client_can() {
+    let mut server = Server::new();
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "capabilities": { "workspace": { "didChangeWatchedFiles": { "dynamicRegistration": true } } },
+        },
+    }));
+
+    let sent = server.handle(&json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }));
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0]["method"], "client/registerCapability");
+    let registration = &sent[0]["params"]["registrations"][0];
+    assert_eq!(registration["method"], "workspace/didChangeWatchedFiles");
+    assert!(registration["registerOptions"]["watchers"]
+        .as_array()
+        .unwrap()
+        .contains(&json!({ "globPattern": "**/.rfmt.yml" })));
+    assert!(registration["registerOptions"]["watchers"]
+        .as_array()
+        .unwrap()
+        .contains(&json!({ "globPattern": "**/.rubocop.yml" })));
+
+    // The client's reply needs no answer
+    let reply = json!({ "jsonrpc": "2.0", "id": sent[0]["id"], "result": null });
+    assert!(server.handle(&reply).is_empty());
+}
+
+#[test]
+fn reloads_configuration_when_editorconfig_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let editorconfig = dir.path().join(".editorconfig");
+    fs::write(&editorconfig, "[*.rb]\nindent_size = 4\n").unwrap();
+    let uri = path_to_file_uri(&dir.path().join("a.rb"));
+    let source = "def a\n1\nend\n";
+
+    let mut client = Client::with_params(json!({ "rootUri": path_to_file_uri(dir.path()) }));
+    client.open(&uri, source);
+    assert_eq!(apply(source, &client.format(&uri)), "def a\n    1\nend\n");
+
+    fs::write(&editorconfig, "[*.rb]\nindent_size = 2\n").unwrap();
+    client.notify(
+        "workspace/didChangeWatchedFiles",
+        json!({ "changes": [{ "uri": path_to_file_uri(&editorconfig), "type": 2 }] }),
+    );
+    assert_eq!(apply(source, &client.format(&uri)), "def a\n  1\nend\n");
+}
+
+#[test]
+fn watches_and_reloads_inherited_configuration_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    let shared = root.join("shared.yml");
+    fs::write(&shared, "formatting:\n  indent_width: 4\n").unwrap();
+    fs::write(app.join(".rfmt.yml"), "inherit_from: ../shared.yml\n").unwrap();
+    let uri = path_to_file_uri(&app.join("a.rb"));
+    let source = "def a\n1\nend\n";
+
+    let mut client = Client::with_params(json!({
+        "rootUri": path_to_file_uri(&root),
+        "capabilities": { "workspace": { "didChangeWatchedFiles": { "dynamicRegistration": true } } },
+    }));
+    // Discovering the document's configuration reads shared.yml, which the
+    // `**/.rfmt.yml` patterns do not cover
+    let sent = client.open(&uri, source);
+    let registration = sent
+        .iter()
+        .find(|message| message["method"] == "client/registerCapability")
+        .expect("a watcher for shared.yml");
+    assert_eq!(
+        registration["params"]["registrations"][0]["registerOptions"]["watchers"],
+        json!([{ "globPattern": shared.to_string_lossy().replace('\\', "/") }])
+    );
+    assert_eq!(apply(source, &client.format(&uri)), "def a\n    1\nend\n");
+
+    fs::write(&shared, "formatting:\n  indent_width: 2\n").unwrap();
+    let published = client.notify(
+        "workspace/didChangeWatchedFiles",
+        json!({ "changes": [{ "uri": path_to_file_uri(&shared), "type": 2 }] }),
+    );
+    assert_eq!(diagnostics_of(&published), &json!([]));
+    assert_eq!(apply(source, &client.format(&uri)), "def a\n  1\nend\n");
+}
+
+#[test]
+fn unknown_requests_are_method_not_found() {
+    let mut client = Client::new();
+    let response = client.request("textDocument/hover", json!({}));
+    assert_eq!(response["error"]["code"], -32601);
+}
+
+#[test]
+fn exit_code_depends_on_shutdown() {
+    let mut client = Client::new();
+    assert_eq!(
+        client.request("shutdown", json!(null))["result"],
+        Value::Null
+    );
+    client.notify("exit", json!(null));
+    assert_eq!(client.server.exit_code(), Some(0));
+
+    let mut client = Client::new();
+    client.notify("exit", json!(null));
+    assert_eq!(client.server.exit_code(), Some(1));
+}
+
+#[test]
+fn run_serves_framed_messages_until_exit() {
+    let mut input = Vec::new();
+    input.extend_from_slice(b"Content-Length: 9\r\n\r\nnot json!");
+    for message in [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+    ] {
+        write_message(&mut input, &message).unwrap();
+    }
+
+    let mut output = Vec::new();
+    assert_eq!(run(Cursor::new(input), &mut output).unwrap(), 0);
+
+    let mut output = Cursor::new(output);
+    let mut responses = Vec::new();
+    while let Some(body) = read_message(&mut output).unwrap() {
+        responses.push(serde_json::from_str::<Value>(&body).unwrap());
+    }
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["error"]["code"], -32700);
+    assert_eq!(responses[1]["id"], 1);
+    assert_eq!(responses[2]["id"], 2);
+}
@@ -8,7 +8,7 @@
 //! NativeAdapter's output shape against future ruby-prism crate bumps; only
 //! `bundle exec ruby scripts/gen_parity_fixtures.rb` may regenerate the JSON.
 
-use rfmt::ast::{Location, Node};
+use rfmt::ast::{Location, Node, NodeType};
 use rfmt::parser::{NativeAdapter, PrismAdapter, RubyParser};
 use std::collections::HashMap;
 use std::fs;
@@ -19,6 +19,10 @@ use std::path::{Path, PathBuf};
 /// side before the exact metadata comparison.
 const DEAD_METADATA_KEYS: [&str; 4] = ["parameters_count", "message", "content", "value"];
 
+/// Keys only the native converter emits (the bridge predates them); they
+/// are stripped from the native side before the comparison.
+const NATIVE_ONLY_METADATA_KEYS: [&str; 3] = ["key_kind", "key_name", "endless"];
+
 fn fixtures_dir() -> PathBuf {
     Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/parity")
 }
@@ -39,9 +43,17 @@ fn push_diff<T: std::fmt::Debug + PartialEq>(
 }
 
 fn live_metadata(metadata: &HashMap<String, String>) -> HashMap<String, String> {
+    without_keys(metadata, &DEAD_METADATA_KEYS)
+}
+
+fn bridge_metadata(metadata: &HashMap<String, String>) -> HashMap<String, String> {
+    without_keys(metadata, &NATIVE_ONLY_METADATA_KEYS)
+}
+
+fn without_keys(metadata: &HashMap<String, String>, keys: &[&str]) -> HashMap<String, String> {
     metadata
         .iter()
-        .filter(|(key, _)| !DEAD_METADATA_KEYS.contains(&key.as_str()))
+        .filter(|(key, _)| !keys.contains(&key.as_str()))
         .map(|(key, value)| (key.clone(), value.clone()))
         .collect()
 }
@@ -104,7 +116,8 @@ fn compare_nodes(path: &str, json: &Node, native: &Node, diffs: &mut Vec<String>
     );
 
     let json_metadata = live_metadata(&json.metadata);
-    push_diff(diffs, path, "metadata", &json_metadata, &native.metadata);
+    let native_metadata = bridge_metadata(&native.metadata);
+    push_diff(diffs, path, "metadata", &json_metadata, &native_metadata);
 
     push_diff(
         diffs,
@@ -243,3 +256,64 @@ fn native_adapter_reports_parse_errors_with_position() {
     // Same line:column shape the Ruby bridge raises (line 2, byte column)
     assert!(message.contains("2:"), "{}", message);
 }
+
+#[test]
+fn native_adapter_reports_assoc_key_kinds() {
+    let tree = NativeAdapter::new()
+        .parse("{ a: 1, :b => 2, \"c\": 3, :\"d\" => 4, 5 => 6 }\n")
+        .unwrap();
+    let hash = tree.children.first().expect("hash child");
+    let kinds: Vec<(Option<&str>, Option<&str>)> = hash
+        .children
+        .iter()
+        .map(|assoc| {
+            (
+                assoc.metadata.get("key_kind").map(String::as_str),
+                assoc.metadata.get("key_name").map(String::as_str),
+            )
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (Some("label"), Some("a")),
+            (Some("symbol"), Some("b")),
+            (Some("quoted_label"), Some("c")),
+            (Some("quoted_symbol"), Some("d")),
+            (Some("other"), None),
+        ]
+    );
+}
+
+#[test]
+fn native_adapter_exposes_def_parameters() {
+    let tree = NativeAdapter::new()
+        .parse("def foo(a, b = 1, *rest, c:, d: 2, **opts, &blk)\n  a\nend\n")
+        .unwrap();
+    let def = tree.children.first().expect("def child");
+    let kinds: Vec<NodeType> = def.children.iter().map(|c| c.node_type.clone()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            NodeType::RequiredParameterNode,
+            NodeType::OptionalParameterNode,
+            NodeType::RestParameterNode,
+            NodeType::RequiredKeywordParameterNode,
+            NodeType::OptionalKeywordParameterNode,
+            NodeType::KeywordRestParameterNode,
+            NodeType::BlockParameterNode,
+            NodeType::StatementsNode,
+        ]
+    );
+    assert_eq!(def.metadata.get("endless"), None);
+
+    let tree = NativeAdapter::new()
+        .parse("def bar(...) = baz(...)\n")
+        .unwrap();
+    let def = tree.children.first().expect("def child");
+    assert_eq!(def.children[0].node_type, NodeType::ForwardingParameterNode);
+    assert_eq!(
+        def.metadata.get("endless").map(String::as_str),
+        Some("true")
+    );
+}
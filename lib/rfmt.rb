@@ -7,29 +7,46 @@ require_relative 'rfmt/native_extension_loader'
 Rfmt::NativeExtensionLoader.load_extension
 
 module Rfmt
+  # The native extension defines these classes and their readers
+  # (ext/rfmt/src/error/mod.rs) and raises them with structured attributes:
+  #   code       - error code from docs/error_reference.md, e.g. "E001"
+  #   file       - file the error is about (the config file for ConfigError)
+  #   line       - 1-based line of the error
+  #   column     - 0-based byte column of the error
+  #   byte_range - byte offsets the error covers
+  #   kind       - kind of the first syntax error (ParseError only)
+  #   errors     - every syntax error as a Hash (ParseError only)
+  # Attributes that do not apply are nil.
   class Error < StandardError; end
+
   # Errors from Rust side
   class RfmtError < Error; end
+  # Source that does not parse
+  class ParseError < RfmtError; end
   # AST validation errors
   class ValidationError < RfmtError; end
-
-  # Rust reports errors as plain StandardError with a [Rfmt::<kind>] prefix;
-  # these two kinds map onto the public exception classes.
-  NATIVE_PARSE_ERROR_PREFIX = '[Rfmt::ParseError] '
-  NATIVE_VALIDATION_ERROR_PREFIX = '[Rfmt::ValidationError] '
-  NATIVE_CONFIG_ERROR_PREFIX = '[Rfmt::ConfigError] '
-  private_constant :NATIVE_PARSE_ERROR_PREFIX, :NATIVE_VALIDATION_ERROR_PREFIX,
-                   :NATIVE_CONFIG_ERROR_PREFIX
+  # Invalid or unreadable configuration
+  class ConfigError < RfmtError; end
+  # Failures while formatting
+  class FormatError < RfmtError; end
+  # Output that changes when formatted again (validation.idempotency)
+  class IdempotencyError < RfmtError; end
+  # Input rfmt refuses, such as files over the size limit
+  class UnsupportedFeature < RfmtError; end
+  # Problems inside the embedded prism parser
+  class PrismError < RfmtError; end
 
   # Format Ruby source code
   # Parsing, config resolution, and output validation all happen natively in Rust
   # @param source [String] Ruby source code to format
   # @param config_path [String, nil] Explicit config file path; nil discovers
   #   rfmt.yml/.rfmt.yml from the current directory upward (cached per process)
+  # @param path [String, nil] File the source was read from; the configuration's
+  #   overrides matching it are applied
   # @return [String] Formatted Ruby code
-  def self.format(source, config_path: nil)
-    if config_path
-      format_code_with_config(source, config_path.to_s)
+  def self.format(source, config_path: nil, path: nil)
+    if config_path || path
+      format_code_with_config(source, config_path&.to_s, path&.to_s)
     else
       format_code(source)
     end
@@ -37,18 +54,69 @@ module Rfmt
     raise wrap_native_error(e)
   end
 
-  def self.wrap_native_error(error)
-    message = error.message
-    if message.start_with?(NATIVE_PARSE_ERROR_PREFIX)
-      Error.new("Failed to parse Ruby code: #{message.delete_prefix(NATIVE_PARSE_ERROR_PREFIX)}")
-    elsif message.start_with?(NATIVE_VALIDATION_ERROR_PREFIX)
-      ValidationError.new(message.delete_prefix(NATIVE_VALIDATION_ERROR_PREFIX))
-    elsif message.start_with?(NATIVE_CONFIG_ERROR_PREFIX)
-      Error.new("Configuration error: #{message.delete_prefix(NATIVE_CONFIG_ERROR_PREFIX)}")
-    else
-      Error.new("Unexpected error during formatting: #{error.class}: #{message}")
+  # Format Ruby source code and carry a cursor position over into the result,
+  # so editors replacing the whole document can restore the caret
+  # @param source [String] Ruby source code to format
+  # @param cursor [Integer] Byte offset of the cursor in source
+  # @param config_path [String, nil] Explicit config file path; nil discovers
+  # @return [Array(String, Integer)] Formatted code and the cursor's byte offset in it
+  def self.format_with_cursor(source, cursor, config_path: nil)
+    format_code_with_cursor(source, cursor, config_path&.to_s)
+  rescue StandardError => e
+    raise wrap_native_error(e)
+  end
+
+  # Format Ruby source code that may not parse: top-level statements with
+  # syntax errors are kept verbatim and everything around them is formatted,
+  # so format-on-save still works mid-edit
+  # @param source [String] Ruby source code to format
+  # @param config_path [String, nil] Explicit config file path; nil discovers
+  # @return [Array(String, Array<Range>)] Formatted code and the byte ranges of
+  #   source left as they were (empty when the source parses)
+  def self.format_tolerant(source, config_path: nil)
+    formatted, skipped = format_code_tolerant(source, config_path&.to_s)
+    [formatted, skipped.map { |start, stop| start...stop }]
+  rescue StandardError => e
+    raise wrap_native_error(e)
+  end
+
+  # Check files without writing them and report the results for CI
+  # @param paths [Array<String>] Files to check
+  # @param format [String, Symbol] "json" (one JSON object per line), "sarif"
+  #   (SARIF 2.1.0 log) or "github" (GitHub Actions ::error annotations)
+  # @param config_path [String, nil] Explicit config file path; nil discovers
+  # @return [Array(String, Boolean)] The report and whether every file is formatted
+  def self.report(paths, format:, config_path: nil)
+    report_files(format.to_s, paths.map(&:to_s), config_path&.to_s)
+  rescue ArgumentError
+    raise
+  rescue StandardError => e
+    raise wrap_native_error(e)
+  end
+
+  # Minimal LSP text edits turning source into formatted, so editors keep
+  # undo history, folds and marks instead of replacing the whole document
+  # @param source [String] Original document text
+  # @param formatted [String] Formatted document text
+  # @return [Array<Hash>] LSP TextEdits (zero-based lines, UTF-16 columns)
+  def self.text_edits(source, formatted)
+    lsp_text_edits(source, formatted).map do |start_line, start_character, end_line, end_character, new_text|
+      {
+        range: {
+          start: { line: start_line, character: start_character },
+          end: { line: end_line, character: end_character }
+        },
+        newText: new_text
+      }
     end
   end
+
+  # Errors raised by the extension are already Rfmt::Error subclasses
+  def self.wrap_native_error(error)
+    return error if error.is_a?(Error)
+
+    Error.new("Unexpected error during formatting: #{error.class}: #{error.message}")
+  end
   private_class_method :wrap_native_error
 
   # Format a Ruby file
@@ -56,16 +124,32 @@ module Rfmt
   # @return [String] Formatted Ruby code
   def self.format_file(path)
     source = File.read(path)
-    format(source)
+    format(source, path: path)
   rescue Errno::ENOENT
     raise Error, "File not found: #{path}"
+  rescue Error => e
+    e.instance_variable_set(:@file, path.to_s) unless e.file
+    raise
   end
 
   # Effective configuration as the Rust formatter resolves it
   # @param config_path [String, nil] Explicit config file path; nil discovers
+  # @param path [String, nil] Show the configuration for this file, with the
+  #   overrides matching it applied
   # @return [String] YAML dump of the resolved configuration
-  def self.resolved_config(config_path: nil)
-    resolved_config_yaml(config_path&.to_s)
+  def self.resolved_config(config_path: nil, path: nil)
+    resolved_config_yaml(config_path&.to_s, path&.to_s)
+  rescue StandardError => e
+    raise wrap_native_error(e)
+  end
+
+  # Rewrite a configuration file's contents to the current configuration
+  # version, mapping deprecated settings to their replacements
+  # @param source [String] Contents of a .rfmt.yml file
+  # @return [Array(String, Array<String>, Boolean)] The rewritten contents,
+  #   a message per deprecated setting, and whether comments were kept
+  def self.migrate_config(source)
+    migrate_config_source(source)
   rescue StandardError => e
     raise wrap_native_error(e)
   end
@@ -93,7 +177,7 @@ module Rfmt
       # This file controls how rfmt formats your Ruby code.
       # See https://github.com/fs0414/rfmt for full documentation.
 
-      version: "1.0"
+      version: "2"
 
       # Formatting options
       formatting:
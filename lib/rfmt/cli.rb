@@ -72,9 +72,12 @@ module Rfmt
     option :cache, type: :boolean, default: true, desc: 'Use cache to skip unchanged files'
     option :cache_dir, type: :string, desc: 'Cache directory (default: ~/.cache/rfmt)'
     option :quiet, type: :boolean, aliases: '-q', desc: 'Minimal output (errors and summary only)'
+    option :output_format, type: :string, enum: %w[json sarif github],
+                           desc: 'Check files and report results as json, sarif or github annotations'
     def format(*files)
       config = load_config
       files = files.empty? ? config.files_to_format : files.flatten
+      return report_files(files) if options[:output_format]
 
       if files.empty?
         say 'No files to format', :yellow
@@ -125,9 +128,34 @@ module Rfmt
       say "Rust extension: #{Rfmt.rust_version}"
     end
 
-    desc 'config', 'Show the effective configuration the formatter will use'
-    def config_cmd
-      say Rfmt.resolved_config(config_path: options[:config])
+    desc 'config [FILE]', 'Show the effective configuration the formatter will use, for FILE when given'
+    def config_cmd(file = nil)
+      say Rfmt.resolved_config(config_path: options[:config], path: file)
+    rescue Rfmt::Error => e
+      raise Thor::Error, e.message
+    end
+
+    desc 'migrate-config [FILE]', 'Rewrite a configuration file (default: the nearest .rfmt.yml) to the current version'
+    option :check, type: :boolean, desc: 'Only report whether the file needs migrating'
+    def migrate_config(file = nil)
+      file ||= options[:config] || Rfmt::Config.find
+      raise Thor::Error, 'No configuration file found' unless file
+      raise Thor::Error, "Configuration file not found: #{file}" unless File.exist?(file)
+
+      source = File.read(file)
+      migrated, deprecations, comments_kept = Rfmt.migrate_config(source)
+      deprecations.each { |deprecation| say "#{file}: #{deprecation}" }
+
+      if migrated == source
+        say "#{file} is already at the current version", :green
+      elsif options[:check]
+        say "#{file} needs migrating", :yellow
+        exit 1
+      else
+        atomic_write(file, migrated)
+        warn "#{file} could not be edited in place and was rewritten without its comments" unless comments_kept
+        say "Migrated #{file}", :green
+      end
     rescue Rfmt::Error => e
       raise Thor::Error, e.message
     end
@@ -229,6 +257,13 @@ module Rfmt
       say "ℹ Skipped #{skipped_count} unchanged file(s) (cached)", :cyan
     end
 
+    # Machine-readable check results for CI; files are never written
+    def report_files(files)
+      report, passed = Rfmt.report(files, format: options[:output_format], config_path: options[:config])
+      $stdout.write(report)
+      exit(1) unless passed
+    end
+
     def format_files_sequential(files)
       show_progress = should_show_progress?(files)
 
@@ -272,7 +307,7 @@ module Rfmt
       start_time = Time.now
       source = File.read(file)
 
-      formatted = Rfmt.format(source, config_path: options[:config])
+      formatted = Rfmt.format(source, config_path: options[:config], path: file)
       changed = source != formatted
 
       {
@@ -5,33 +5,16 @@ require 'rfmt'
 module Rfmt
   module LSP
     class Formatter
-      def self.format_edits(source)
-        formatted = source.empty? ? "\n" : Rfmt.format(source)
+      # @param path [String, nil] File the document was opened from; its
+      #   configuration is discovered from there
+      def self.format_edits(source, path: nil)
+        formatted = source.empty? ? "\n" : Rfmt.format(source, path: path)
         return [] if formatted == source
 
-        [
-          {
-            range: full_document_range(source),
-            newText: formatted
-          }
-        ]
+        Rfmt.text_edits(source, formatted)
       rescue Rfmt::Error
         []
       end
-
-      def self.full_document_range(source)
-        return { start: { line: 0, character: 0 }, end: { line: 0, character: 0 } } if source.empty?
-
-        lines = source.split("\n", -1)
-
-        {
-          start: { line: 0, character: 0 },
-          end: {
-            line: lines.length - 1,
-            character: lines.last.length
-          }
-        }
-      end
     end
   end
 end
@@ -125,7 +125,9 @@ module Rfmt
         uri = params.fetch('textDocument').fetch('uri')
         source = @documents.source_for(uri) || read_file_source(uri)
         edits = if source
-                  @workspace.with_root_for(uri) { Formatter.format_edits(source) }
+                  @workspace.with_root_for(uri) do
+                    Formatter.format_edits(source, path: URI.file_uri_to_path(uri))
+                  end
                 else
                   []
                 end
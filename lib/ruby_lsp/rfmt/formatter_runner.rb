@@ -8,11 +8,11 @@ module RubyLsp
       # @param uri [URI::Generic] Document URI
       # @param document [RubyLsp::RubyDocument] Target document
       # @return [String, nil] Formatted text or nil on error
-      def run_formatting(_uri, document)
+      def run_formatting(uri, document)
         source = document.source
         return "\n" if source.empty?
 
-        ::Rfmt.format(source)
+        ::Rfmt.format(source, path: document_path(uri))
       rescue ::Rfmt::Error
         nil
       end
@@ -23,6 +23,16 @@ module RubyLsp
       def run_diagnostic(_uri, _document)
         []
       end
+
+      private
+
+      # File the document was opened from, so its configuration is found
+      # from there; nil for unsaved documents
+      def document_path(uri)
+        return uri.to_standardized_path if uri.respond_to?(:to_standardized_path)
+
+        uri.path if uri.scheme.nil? || uri.scheme == 'file'
+      end
     end
   end
 end
@@ -39,13 +39,24 @@ RSpec.describe Rfmt, 'Chain reformatting with multi-line arguments' do
     expect(formatted).to include('        .limit(5)')
   end
 
-  it 'does not shift non-chain lines when no chain reformat happens' do
+  it 'joins a short argument list onto one line' do
     source = <<~RUBY
       foo(
         arg1,
         arg2
       )
     RUBY
+    expect(idempotent(source)).to eq("foo(arg1, arg2)\n")
+  end
+
+  it 'keeps an argument list that does not fit broken one argument per line' do
+    source = <<~RUBY
+      foo(
+        first_argument_with_a_long_name,
+        second_argument_with_a_long_name,
+        third_argument_with_a_long_name,
+      )
+    RUBY
     expect(idempotent(source)).to eq(source)
   end
 
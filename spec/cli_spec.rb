@@ -2,6 +2,7 @@
 
 require 'spec_helper'
 require 'rfmt/cli'
+require 'json'
 
 RSpec.describe Rfmt::CLI do
   let(:cli) { described_class.new }
@@ -66,6 +67,54 @@ RSpec.describe Rfmt::CLI do
     end
   end
 
+  describe '--output-format' do
+    require 'tmpdir'
+
+    it 'reports files as JSON lines without writing them' do
+      Dir.mktmpdir do |dir|
+        messy = File.join(dir, 'messy.rb')
+        File.write(messy, "x  =  1\n")
+        broken = File.join(dir, 'broken.rb')
+        File.write(broken, "def broken(\n")
+
+        output = StringIO.new
+        allow($stdout).to receive(:write) { |s| output.write(s) }
+        expect do
+          described_class.start(['format', '--output-format', 'json', messy, broken])
+        end.to raise_error(SystemExit) { |error| expect(error.status).to eq(1) }
+
+        lines = output.string.lines.map { |line| JSON.parse(line) }
+        expect(lines.map { |line| line['status'] }).to eq(%w[changed parse_error])
+        expect(lines.last).to include('code' => 'E001', 'line' => 1)
+        expect(File.read(messy)).to eq("x  =  1\n")
+      end
+    end
+  end
+
+  describe '#migrate_config' do
+    it 'rewrites deprecated settings and keeps comments' do
+      Dir.mktmpdir do |dir|
+        config_path = File.join(dir, '.rfmt.yml')
+        File.write(config_path, <<~YAML)
+          version: "1.0"
+          # house style
+          formatting:
+            style:
+              quotes: single
+        YAML
+
+        described_class.start(['migrate-config', config_path])
+
+        expect(File.read(config_path)).to eq(<<~YAML)
+          version: "2"
+          # house style
+          formatting:
+            quote_style: single
+        YAML
+      end
+    end
+  end
+
   describe '#config_cmd' do
     it 'shows the effective formatter configuration' do
       Dir.mktmpdir do |dir|
@@ -80,5 +129,24 @@ RSpec.describe Rfmt::CLI do
         expect(output.string).to include('line_length: 100')
       end
     end
+
+    it 'applies the overrides matching a given file' do
+      Dir.mktmpdir do |dir|
+        config_path = File.join(dir, 'custom.yml')
+        File.write(config_path, <<~YAML)
+          overrides:
+            - files: ["spec/**"]
+              formatting:
+                line_length: 120
+        YAML
+
+        output = StringIO.new
+        allow($stdout).to receive(:write) { |s| output.write(s) }
+        described_class.start(['config_cmd', 'spec/models/user_spec.rb', '--config', config_path])
+
+        expect(output.string).to include('line_length: 120')
+        expect(output.string).not_to include('overrides')
+      end
+    end
   end
 end
@@ -202,6 +202,21 @@ RSpec.describe 'Config-based formatting' do
         Rfmt.format(source_code, config_path: 'broken.yml')
       end.to raise_error(Rfmt::Error, /line_length/)
     end
+
+    it 'raises a ConfigError naming the file and the offending line' do
+      File.write('typo.yml', <<~YAML)
+        formatting:
+          line_length: long
+      YAML
+
+      expect do
+        Rfmt.format(source_code, config_path: 'typo.yml')
+      end.to raise_error(Rfmt::ConfigError) do |error|
+        expect(error.code).to eq('E002')
+        expect(error.file).to eq('typo.yml')
+        expect(error.line).to eq(2)
+      end
+    end
   end
 
   describe 'discovery cache invalidation' do
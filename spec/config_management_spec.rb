@@ -143,7 +143,7 @@ RSpec.describe Rfmt::Config do
       require 'yaml'
       config = YAML.safe_load(default_config)
 
-      expect(config['version']).to eq('1.0')
+      expect(config['version']).to eq('2')
       expect(config['formatting']['line_length']).to eq(100)
       expect(config['formatting']['indent_width']).to eq(2)
       expect(config['formatting']['indent_style']).to eq('spaces')
@@ -34,6 +34,12 @@ RSpec.describe Rfmt::LSP::Server do
     Rfmt::LSP::URI.path_to_file_uri(path)
   end
 
+  # LSP text edit inserting text at the start of a line
+  def insertion(line, text)
+    position = { 'line' => line, 'character' => 0 }
+    { 'range' => { 'start' => position, 'end' => position }, 'newText' => text }
+  end
+
   describe '#handle_message' do
     it 'returns formatting capabilities on initialize' do
       server, output = build_server
@@ -52,7 +58,7 @@ RSpec.describe Rfmt::LSP::Server do
       expect(response.dig('result', 'serverInfo', 'name')).to eq('rfmt')
     end
 
-    it 'formats an opened document with a full-document text edit' do
+    it 'formats an opened document with minimal text edits' do
       server, output = build_server
       uri = file_uri('/tmp/test.rb')
 
@@ -77,13 +83,11 @@ RSpec.describe Rfmt::LSP::Server do
                             })
 
       response = read_messages(output).last
-      edit = response['result'].first
-      expect(edit['range']).to eq({
-                                    'start' => { 'line' => 0, 'character' => 0 },
-                                    'end' => { 'line' => 5, 'character' => 0 }
-                                  })
-      expect(edit['newText']).to include("  def bar\n")
-      expect(edit['newText']).to include("    42\n")
+      expect(response['result']).to eq([
+                                         insertion(1, '  '),
+                                         insertion(2, '    '),
+                                         insertion(3, '  ')
+                                       ])
     end
 
     it 'returns no edits when formatting fails' do
@@ -182,9 +186,57 @@ RSpec.describe Rfmt::LSP::Server do
                                 }
                               })
 
-        edit = read_messages(output).last['result'].first
-        expect(edit['newText']).to include("    def bar\n")
-        expect(edit['newText']).to include("        42\n")
+        edits = read_messages(output).last['result']
+        expect(edits).to eq([
+                              insertion(1, '    '),
+                              insertion(2, '        '),
+                              insertion(3, '    ')
+                            ])
+      end
+    end
+
+    it 'uses the configuration of the directory holding the document' do
+      Dir.mktmpdir do |root|
+        lib = File.join(root, 'lib')
+        Dir.mkdir(lib)
+        File.write(File.join(lib, '.rfmt.yml'), <<~YAML)
+          formatting:
+            indent_width: 4
+        YAML
+        uri = file_uri(File.join(lib, 'test.rb'))
+        server, output = build_server
+
+        server.handle_message({
+                                'jsonrpc' => '2.0',
+                                'id' => 1,
+                                'method' => 'initialize',
+                                'params' => { 'rootUri' => file_uri(root) }
+                              })
+        server.handle_message({
+                                'jsonrpc' => '2.0',
+                                'method' => 'textDocument/didOpen',
+                                'params' => {
+                                  'textDocument' => {
+                                    'uri' => uri,
+                                    'text' => "def bar
+42
+end
+"
+                                  }
+                                }
+                              })
+        server.handle_message({
+                                'jsonrpc' => '2.0',
+                                'id' => 5,
+                                'method' => 'textDocument/formatting',
+                                'params' => {
+                                  'textDocument' => { 'uri' => uri },
+                                  'options' => {}
+                                }
+                              })
+
+        edits = read_messages(output).last['result']
+        expect(edits).to eq([insertion(1, '    ')])
       end
     end
 
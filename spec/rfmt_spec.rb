@@ -1,6 +1,7 @@
 # frozen_string_literal: true
 
 require 'spec_helper'
+require 'tempfile'
 
 RSpec.describe Rfmt do
   describe '.format' do
@@ -29,6 +30,33 @@ RSpec.describe Rfmt do
       end.to raise_error(Rfmt::Error, /\AFailed to parse Ruby code: Parse errors:\n1:/)
     end
 
+    it 'raises a ParseError locating the syntax error' do
+      source = "x = 1\ndef broken(\n"
+
+      expect { Rfmt.format(source) }.to raise_error(Rfmt::ParseError) do |error|
+        expect(error).to be_a(Rfmt::RfmtError)
+        expect(error.code).to eq('E001')
+        expect(error.line).to eq(2)
+        expect(error.column).to be >= 0
+        expect(error.byte_range).to be_a(Range)
+        expect(source.byteslice(0, error.byte_range.begin).count("\n")).to eq(1)
+        expect(error.kind).to match(/\A[a-z0-9_]+\z/)
+        expect(error.errors.first).to include(line: 2, kind: error.kind)
+        expect(error.file).to be_nil
+      end
+    end
+
+    it 'names the file in errors from format_file' do
+      Tempfile.create(['broken', '.rb']) do |file|
+        file.write('def broken(')
+        file.flush
+
+        expect { Rfmt.format_file(file.path) }.to raise_error(Rfmt::ParseError) do |error|
+          expect(error.file).to eq(file.path)
+        end
+      end
+    end
+
     it 'formats Rails migration with versioned superclass' do
       source = <<~RUBY
         class AddProfileToUsers < ActiveRecord::Migration[8.1]
@@ -405,6 +433,56 @@ RSpec.describe Rfmt do
     end
   end
 
+  describe '.format_with_cursor' do
+    it 'keeps the cursor on the same character' do
+      source = "class Foo\ndef bar\nbaz( 1,2 )\nend\nend\n"
+      cursor = source.index('2')
+
+      formatted, new_cursor = Rfmt.format_with_cursor(source, cursor)
+
+      expect(formatted).to eq("class Foo\n  def bar\n    baz(1, 2)\n  end\nend\n")
+      expect(formatted.byteslice(new_cursor)).to eq('2')
+    end
+
+    it 'raises the same errors as format' do
+      expect { Rfmt.format_with_cursor('def broken(', 0) }.to raise_error(Rfmt::Error)
+    end
+  end
+
+  describe '.format_tolerant' do
+    it 'formats around statements with syntax errors' do
+      source = "x  =  1\n\ndef broken(a\n"
+
+      formatted, skipped = Rfmt.format_tolerant(source)
+
+      expect(formatted).to eq("x = 1\n\ndef broken(a\n")
+      expect(skipped).to eq([9...source.bytesize])
+    end
+
+    it 'returns no skipped ranges for valid code' do
+      expect(Rfmt.format_tolerant("x  =  1\n")).to eq(["x = 1\n", []])
+    end
+  end
+
+  describe '.report' do
+    it 'renders GitHub annotations for files that need formatting' do
+      Tempfile.create(['messy', '.rb']) do |file|
+        file.write("x = 1\ny  =  2\n")
+        file.flush
+
+        report, passed = Rfmt.report([file.path], format: :github)
+
+        expect(passed).to be(false)
+        expect(report).to start_with("::error file=#{file.path},line=2,col=3,")
+        expect(report).to end_with("::File needs formatting\n")
+      end
+    end
+
+    it 'rejects unknown formats' do
+      expect { Rfmt.report([], format: :xml) }.to raise_error(ArgumentError, /Unknown output format/)
+    end
+  end
+
   describe '.version_info' do
     it 'returns version information' do
       version = Rfmt.version_info
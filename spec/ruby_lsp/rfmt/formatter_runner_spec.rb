@@ -1,6 +1,7 @@
 # frozen_string_literal: true
 
 require 'spec_helper'
+require 'tmpdir'
 require 'uri'
 require 'ruby_lsp/rfmt/formatter_runner'
 
@@ -33,6 +34,20 @@ RSpec.describe RubyLsp::Rfmt::FormatterRunner do
       end
     end
 
+    context 'with a configuration next to the document' do
+      it 'formats with that configuration' do
+        Dir.mktmpdir do |dir|
+          File.write(File.join(dir, '.rfmt.yml'), "formatting:\n  indent_width: 4\n")
+          file_uri = URI::Generic.build(scheme: 'file', path: File.join(dir, 'test.rb'))
+          document = double('Document', source: "def foo\n42\nend\n")
+
+          result = runner.run_formatting(file_uri, document)
+
+          expect(result).to eq("def foo\n    42\nend\n")
+        end
+      end
+    end
+
     context 'with empty source' do
       it 'returns newline for empty input' do
         source = ''